@@ -1,16 +1,26 @@
 use std::collections::HashMap;
 
 use anyhow::{anyhow, Context, Result};
-use camino::Utf8PathBuf;
-use clap::Parser;
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Parser, Subcommand};
 use requestty::Question;
 use semver::Version;
 use tinytemplate::TinyTemplate;
 
 use crate::{
     //cmd::builder::prompt::build_prompt,
-    core::manifest::Manifest,
+    core::{
+        choice::Choice,
+        context::{StaticContext, TextContext},
+        manifest::Manifest,
+        path::{PathData, PathLookup},
+        player::Player,
+        prompt::{Prompt, PromptModel},
+        resources::Resources,
+        text::display::{translation_coverage, Text, TextSpeed, Translations},
+    },
     loading::{loader::Loader, saves::SaveManager},
+    text_context,
 };
 
 pub const TEMPLATE_MANIFEST: &'static str = include_str!("../template/nage.yml");
@@ -27,6 +37,28 @@ pub enum CliCommand {
         new: bool,
         #[arg(short, long, help = "Pick from a list of multiple saves instead of the last used")]
         pick: bool,
+        #[arg(short, long, help = "The random seed to use, overriding the game's setting")]
+        seed: Option<u64>,
+        #[arg(
+            long = "var",
+            value_name = "NAME=VALUE",
+            value_parser = CliCommand::parse_var,
+            help = "Set a variable when starting a new game. Can be repeated"
+        )]
+        vars: Vec<(String, String)>,
+        #[arg(
+            long,
+            help = "The directory to store game data in, overriding the default location and NAGE_SAVES_DIR"
+        )]
+        saves_dir: Option<Utf8PathBuf>,
+        #[arg(short, long, help = "Hide warnings about the game's content")]
+        quiet: bool,
+        #[arg(long, help = "Treat warnings about the game's content as errors")]
+        strict: bool,
+        #[arg(long, help = "A plain-text file to copy the game's output to as it is played")]
+        log_output: Option<Utf8PathBuf>,
+        #[arg(long, requires = "log_output", help = "Also copy player input to the output log")]
+        log_input: bool,
     },
     #[command(about = "Create a new Nagame template")]
     New {
@@ -39,10 +71,82 @@ pub enum CliCommand {
     Saves {
         #[arg(help = "The game ID. Attempts to default to current directory")]
         path: Option<Utf8PathBuf>,
+        #[arg(
+            long,
+            help = "The directory to store game data in, overriding the default location and NAGE_SAVES_DIR"
+        )]
+        saves_dir: Option<Utf8PathBuf>,
+        #[command(subcommand)]
+        action: Option<SavesAction>,
+    },
+    #[command(about = "Report translation key coverage across languages")]
+    Lang {
+        #[arg(help = "The game directory. Defaults to the current directory")]
+        path: Option<Utf8PathBuf>,
+    },
+    #[command(about = "Preview a prompt's text and choices without playing")]
+    Show {
+        #[arg(help = "The prompt ID in the form 'file/prompt'")]
+        prompt: String,
+        #[arg(help = "The game directory. Defaults to the current directory")]
+        path: Option<Utf8PathBuf>,
+        #[arg(
+            long = "note",
+            value_name = "NOTE",
+            help = "Apply a note while rendering. Can be repeated"
+        )]
+        notes: Vec<String>,
+        #[arg(
+            long = "var",
+            value_name = "NAME=VALUE",
+            value_parser = CliCommand::parse_var,
+            help = "Set a variable while rendering. Can be repeated"
+        )]
+        vars: Vec<(String, String)>,
+    },
+    #[command(about = "Play a Nagame non-interactively from a file of input lines")]
+    Replay {
+        #[arg(help = "The file of input lines, one per line")]
+        script: Utf8PathBuf,
+        #[arg(help = "The game directory. Defaults to the current directory")]
+        path: Option<Utf8PathBuf>,
+        #[arg(
+            short,
+            long,
+            help = "The random seed to use, overriding the game's setting. Defaults to 0 if the game sets none"
+        )]
+        seed: Option<u64>,
+        #[arg(short, long, help = "A transcript file that the replay output must match")]
+        expected: Option<Utf8PathBuf>,
+    },
+    #[command(about = "Pick a Nagame to run from a directory of games", alias = "l")]
+    Launch {
+        #[arg(
+            help = "The directory containing game folders and archives. Defaults to the current directory"
+        )]
+        dir: Option<Utf8PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+/// An action on a game's save files instead of opening its save directory.
+pub enum SavesAction {
+    #[command(about = "Delete a save file by name")]
+    Rm {
+        #[arg(help = "The save file name, without its extension")]
+        name: String,
     },
 }
 
 impl CliCommand {
+    /// Parses a `--var` argument in the form `name=value`.
+    fn parse_var(arg: &str) -> std::result::Result<(String, String), String> {
+        match arg.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok((name.to_owned(), value.to_owned())),
+            _ => Err(format!("Invalid variable '{arg}'; expected the form 'name=value'")),
+        }
+    }
+
     fn new_properties() -> Result<HashMap<String, String>> {
         let module = requestty::PromptModule::new(vec![
             Question::input("name").message("Game name").build(),
@@ -78,7 +182,7 @@ impl CliCommand {
         std::fs::write("prompts/main.yml", TEMPLATE_MAIN)?;
 
         if full {
-            for dir in ["info", "lang", "scripts", "sounds"] {
+            for dir in ["info", "init", "lang", "scripts", "sounds"] {
                 let _ = std::fs::create_dir(dir);
             }
         }
@@ -97,30 +201,220 @@ impl CliCommand {
     }
 
     /// Handles a [`Data`](CliCommand::Saves) command.
-    fn saves(path: &Option<Utf8PathBuf>) -> Result<()> {
+    fn saves(
+        path: &Option<Utf8PathBuf>,
+        saves_dir: Option<&Utf8Path>,
+        action: &Option<SavesAction>,
+    ) -> Result<()> {
         let loader = Loader::from_dir_or_current(path.clone());
+        if let Some(SavesAction::Rm { name }) = action {
+            let config = Manifest::load(&loader)?;
+            let saves = SaveManager::new(&config, false, true, saves_dir)?;
+            saves.delete(&saves.find(name)?)?;
+            println!("Deleted save file '{name}'");
+            return Ok(());
+        }
         match Manifest::load(&loader) {
             Ok(config) => {
-                open::that(SaveManager::game_dir(&config)?)?;
+                open::that(SaveManager::game_dir(&config, saves_dir)?)?;
             }
             Err(_) => {
                 if let Some(dir) = path {
                     return Err(anyhow!("Game ID '{dir}' does not exist"));
                 }
-                open::that(SaveManager::generic_dir()?)?;
+                open::that(SaveManager::generic_dir(saves_dir)?)?;
             }
         };
         Ok(())
     }
 
+    /// Handles a [`Lang`](CliCommand::Lang) command.
+    fn lang(path: &Option<Utf8PathBuf>) -> Result<()> {
+        let loader = Loader::from_dir_or_current(path.clone());
+        let config = Manifest::load(&loader)?;
+        let translations: Translations = loader.load_content("lang")?;
+        println!("{}", translation_coverage(&translations, &config.settings.text.lang())?);
+        Ok(())
+    }
+
+    /// Handles a [`Show`](CliCommand::Show) command.
+    ///
+    /// Prints a prompt as it would appear to a player, but instantly.
+    ///
+    /// The prompt is rendered for a [preview player](Player::preview) holding only the given notes and variables.
+    fn show(
+        prompt: &str,
+        path: &Option<Utf8PathBuf>,
+        notes: &[String],
+        vars: &[(String, String)],
+    ) -> Result<()> {
+        let (file, name) = prompt
+            .rsplit_once('/')
+            .ok_or(anyhow!("Invalid prompt ID '{prompt}'; expected the form 'file/prompt'"))?;
+        let path = Loader::dir_or_current(path.clone());
+        let mapping = Loader::mapping(&path)?;
+        let archive = Loader::archive(&mapping)?;
+        let tree = Loader::tree(&archive)?;
+        let loader = Loader::new(path, &archive, &tree)?;
+        let config = Manifest::load(&loader)?;
+        let resources = Resources::load(&loader, &config, Resources::seed(&config, None))?;
+        let stc = StaticContext::new(&config, &resources, config.settings.text.accessible());
+
+        let lookup: PathData = PathLookup::new(file, name).into();
+        let mut player = Player::preview(
+            &config,
+            &lookup,
+            notes.iter().cloned().collect(),
+            vars.iter().cloned().collect(),
+            Vec::new(),
+        );
+        player.speed = TextSpeed::INSTANT;
+        let text_context = text_context!(&stc, player);
+
+        let prompt = Prompt::get(&resources.prompts, &lookup)?;
+        let model = prompt.model(&text_context)?;
+        let choices: Vec<&Choice> = prompt
+            .usable_choices(&lookup, &player.notes, &player.session, &text_context)?
+            .into_iter()
+            .map(|(choice, _)| choice)
+            .collect();
+        prompt.print(&player, &model, true, &choices, &text_context)?;
+        if let PromptModel::Ending(lines) = model {
+            Text::print_lines(lines, &player, &text_context)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the input lines of a [`Replay`](CliCommand::Replay) script.
+    pub fn replay_script(script: &Utf8PathBuf) -> Result<Vec<String>> {
+        let content = std::fs::read_to_string(script)
+            .with_context(|| format!("Failed to read replay script '{script}'"))?;
+        Ok(content.lines().map(str::to_owned).collect())
+    }
+
+    /// Handles a [`Replay`](CliCommand::Replay) command with an expected transcript.
+    ///
+    /// Compares the `transcript` of a finished replay, [captured](crate::core::text::output::OutputLog::capture) as it was printed,
+    /// line by line against the `expected` file.
+    pub fn compare_replay(expected: &Utf8PathBuf, transcript: &str) -> Result<()> {
+        let expected_content = std::fs::read_to_string(expected)
+            .with_context(|| format!("Failed to read expected transcript '{expected}'"))?;
+        let actual: Vec<&str> = transcript.lines().collect();
+        let wanted: Vec<&str> = expected_content.lines().collect();
+        let divergence = actual
+            .iter()
+            .zip(&wanted)
+            .position(|(a, b)| a != b)
+            .or((actual.len() != wanted.len()).then(|| actual.len().min(wanted.len())));
+        if let Some(index) = divergence {
+            return Err(anyhow!(
+                "Replay output diverges from '{expected}' at line {}\nExpected: {}\nActual:   {}",
+                index + 1,
+                wanted.get(index).unwrap_or(&"<end of transcript>"),
+                actual.get(index).unwrap_or(&"<end of transcript>")
+            ));
+        }
+        println!("\nReplay matches '{expected}'");
+        Ok(())
+    }
+
+    /// Loads the manifest of a candidate game folder or archive.
+    fn load_candidate(path: &Utf8PathBuf) -> Result<Manifest> {
+        let mapping = Loader::mapping(path)?;
+        let archive = Loader::archive(&mapping)?;
+        let tree = Loader::tree(&archive)?;
+        let loader = Loader::new(path.clone(), &archive, &tree)?;
+        Manifest::load(&loader)
+    }
+
+    /// Finds all subfolders containing a manifest file and all zip archives within a directory.
+    fn launch_candidates(dir: &Utf8PathBuf) -> Result<Vec<Utf8PathBuf>> {
+        let mut result: Vec<Utf8PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| Utf8PathBuf::from_path_buf(entry.path()).ok())
+            .filter(|path| {
+                if path.is_dir() {
+                    return path.join(Manifest::FILE).exists();
+                }
+                path.extension().map(|ext| ext == "zip").unwrap_or(false)
+            })
+            .collect();
+        result.sort();
+        Ok(result)
+    }
+
+    /// Handles a [`Launch`](CliCommand::Launch) command.
+    ///
+    /// Lists the games within a directory by their metadata and returns the path of the chosen game.
+    /// Candidates that fail to load are listed as disabled alongside their error.
+    pub fn launch(dir: Option<Utf8PathBuf>) -> Result<Utf8PathBuf> {
+        let dir = Loader::dir_or_current(dir);
+        let mut games = Vec::new();
+        let mut failed = Vec::new();
+        for path in Self::launch_candidates(&dir)? {
+            match Self::load_candidate(&path) {
+                Ok(config) => {
+                    let metadata = &config.metadata;
+                    let display = format!(
+                        "{} v{} by {}",
+                        metadata.name,
+                        metadata.version,
+                        metadata.authors.join(", ")
+                    );
+                    games.push((path, display));
+                }
+                Err(err) => failed.push(format!(
+                    "{} (disabled: {err})",
+                    path.file_name().unwrap_or(path.as_str())
+                )),
+            }
+        }
+        if games.is_empty() {
+            let details = failed
+                .iter()
+                .map(|line| format!("\n- {line}"))
+                .collect::<String>();
+            return Err(anyhow!("No loadable games found in '{dir}'{details}"));
+        }
+        // Disabled entries are separators placed after every game so that indices match
+        let mut question = Question::select("Choose a game")
+            .choices(games.iter().map(|(_, display)| display.clone()));
+        for line in failed {
+            question = question.separator(line);
+        }
+        let choice = requestty::prompt_one(question.build())?
+            .as_list_item()
+            .unwrap()
+            .index;
+
+        println!();
+
+        Ok(games.swap_remove(choice).0)
+    }
+
     pub fn run(&self) -> Result<()> {
         use CliCommand::*;
         match self {
             &New { full } => Self::new(full),
             Builder => Self::builder(),
-            Saves { path } => {
-                Self::saves(path).with_context(|| anyhow!("Failed to open saves directory"))
+            Saves {
+                path,
+                saves_dir,
+                action,
+            } => Self::saves(path, saves_dir.as_deref(), action).with_context(|| match action {
+                Some(_) => anyhow!("Failed to manage save files"),
+                None => anyhow!("Failed to open saves directory"),
+            }),
+            Lang { path } => {
+                Self::lang(path).with_context(|| anyhow!("Failed to check translations"))
             }
+            Show {
+                prompt,
+                path,
+                notes,
+                vars,
+            } => Self::show(prompt, path, notes, vars)
+                .with_context(|| anyhow!("Failed to show prompt '{prompt}'")),
             _ => unreachable!(),
         }
     }
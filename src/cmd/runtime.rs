@@ -1,19 +1,27 @@
 use anyhow::{anyhow, Result};
 use clap::Parser;
+use result::OptionResultExt;
 
 use crate::{
     core::{
         audio::Audio,
+        choice::UsableChoices,
         context::{StaticContext, TextContext},
+        manifest::LogSettings,
         path::{PathData, PathLookup},
         player::Player,
         prompt::Prompt as PromptUtil,
         resources::Resources,
         state::{
             info::{InfoPages, UnlockedInfoPages},
-            notes::Notes,
+            notes::NoteEntry,
+            variables::{VariableEntries, VariableEntry},
+        },
+        text::{
+            display::{Text, Translations},
+            output::OutputLog,
+            templating::TemplatableString,
         },
-        text::display::Translations,
     },
     game::gloop::GameLoopResult,
     loading::saves::SaveManager,
@@ -24,6 +32,8 @@ use crate::{
 pub enum RuntimeCommand {
     #[command(about = "Try going back a choice")]
     Back,
+    #[command(about = "Go back to an earlier prompt")]
+    Rewind,
     #[command(about = "Manage the display language")]
     Lang,
     #[command(about = "Display an info page")]
@@ -32,6 +42,21 @@ pub enum RuntimeCommand {
     Log,
     #[command(about = "Manage sound effects and music channels")]
     Sound,
+    #[command(about = "Adjust the text speed")]
+    Speed,
+    #[command(about = "Display a random tip")]
+    Tip,
+    #[command(about = "Toggle debug commands with the game's password")]
+    Debug,
+    #[command(about = "Print a shareable code of the current save")]
+    ExportCode,
+    #[command(about = "Replace the current save with a shared code")]
+    ImportCode,
+    #[command(about = "Preview the first line of the prompt a choice leads to")]
+    Peek {
+        #[arg(help = "The number of the choice to preview")]
+        choice: usize,
+    },
     #[command(about = "Save the player data")]
     Save,
     #[command(about = "Save and quits the game")]
@@ -42,6 +67,14 @@ pub enum RuntimeCommand {
     Notes,
     #[command(about = "List the currently applied variable names and their values", hide = true)]
     Variables,
+    #[command(about = "Set a variable to a new value", hide = true)]
+    Set,
+    #[command(about = "Toggle whether a note is applied", hide = true)]
+    Note,
+    #[command(about = "Delete save files", hide = true)]
+    Delete,
+    #[command(external_subcommand)]
+    Custom(Vec<String>),
 }
 
 /// The result of a runtime command.
@@ -59,18 +92,63 @@ impl CommandResult {
 }
 
 impl RuntimeCommand {
+    /// The text speed multipliers selectable with a [`Speed`](RuntimeCommand::Speed) command.
+    const SPEED_PRESETS: [f32; 6] = [0.5, 0.75, 1.0, 1.5, 2.0, 3.0];
+    /// The line printed after changing the text speed.
+    const SPEED_SAMPLE: &'static str = "This is how fast text will be printed.";
+
     /// Determines if this command is allowed in a default, non-debug environment.
     fn is_normal(&self) -> bool {
         use RuntimeCommand::*;
-        matches!(&self, Back | Lang | Info | Log | Sound | Save | Quit)
+        matches!(
+            &self,
+            Back | Rewind
+                | Lang
+                | Info
+                | Log
+                | Sound
+                | Speed
+                | Tip
+                | Debug
+                | ExportCode
+                | ImportCode
+                | Peek { .. }
+                | Save
+                | Quit
+                | Custom(_)
+        )
     }
 
     /// Handles a [`Back`](RuntimeCommand::Back) command.
-    fn back(player: &mut Player) -> Result<CommandResult> {
+    fn back(player: &mut Player, reprompt_input: bool) -> Result<CommandResult> {
         if player.history.len() <= 1 {
             return Err(anyhow!("Can't go back right now!"));
         }
-        player.back()?;
+        player.back(reprompt_input)?;
+        Ok(CommandResult::Submit(GameLoopResult::Continue))
+    }
+
+    /// Handles a [`Rewind`](RuntimeCommand::Rewind) command.
+    fn rewind(player: &mut Player, reprompt_input: bool) -> Result<CommandResult> {
+        let targets: Vec<(usize, String)> = player
+            .rewind_targets()
+            .into_iter()
+            .map(|index| (index, player.history[index].path.to_string()))
+            .collect();
+        if targets.is_empty() {
+            return Err(anyhow!("Can't go back right now!"));
+        }
+
+        println!();
+
+        let choices: Vec<&str> = targets.iter().map(|(_, path)| path.as_str()).collect();
+        let rewind_question = requestty::Question::select("Rewind to a prompt")
+            .choices(choices)
+            .build();
+        let rewind_choice = requestty::prompt_one(rewind_question)?;
+        let index = targets[rewind_choice.as_list_item().unwrap().index].0;
+
+        player.rewind(index, reprompt_input)?;
         Ok(CommandResult::Submit(GameLoopResult::Continue))
     }
 
@@ -92,7 +170,14 @@ impl RuntimeCommand {
     }
 
     /// Handles an [`Info`](RuntimeCommand::Info) command.
-    fn info(unlocked_pages: &UnlockedInfoPages, pages: &InfoPages) -> Result<CommandResult> {
+    ///
+    /// Page content is filled against the text context if it contains templating delimiters,
+    /// leaving any that [aren't known](TemplatableString::fill_known) as written so that markdown renders intact.
+    fn info(
+        unlocked_pages: &UnlockedInfoPages,
+        pages: &InfoPages,
+        text_context: &TextContext,
+    ) -> Result<CommandResult> {
         if unlocked_pages.is_empty() {
             return Err(anyhow!("No info pages unlocked"));
         }
@@ -112,13 +197,19 @@ impl RuntimeCommand {
 
         println!();
 
-        termimad::print_text(pages.get(&page.name).unwrap());
+        let content = pages.get(&page.name).unwrap();
+        if TemplatableString::is_str_templatable(content) {
+            let filled = TemplatableString::from(content.clone()).fill_known(text_context)?;
+            termimad::print_text(&filled);
+        } else {
+            termimad::print_text(content);
+        }
 
         Ok(CommandResult::retry())
     }
 
     /// Handles a [`Log`](RuntimeCommand::Log) command.
-    fn log(player: &Player) -> Result<CommandResult> {
+    fn log(player: &Player, settings: &LogSettings) -> Result<CommandResult> {
         if player.log.is_empty() {
             return Err(anyhow!("No log entries to display"));
         }
@@ -127,7 +218,7 @@ impl RuntimeCommand {
 
         let pages = player.log_pages();
         let page_question = requestty::Question::raw_select("Log page")
-            .choices(Player::log_page_fronts(&pages))
+            .choices(Player::log_page_fronts(&pages, settings))
             .build();
         let page_choice = requestty::prompt_one(page_question)?;
 
@@ -139,7 +230,14 @@ impl RuntimeCommand {
     }
 
     /// Handles a [`Sound`](RuntimeCommand::Sound) command.
-    fn sound(player: &mut Player, audio_res: &Option<Audio>) -> Result<CommandResult> {
+    ///
+    /// The resulting channel toggles are recorded in the game's profile for future new games.
+    fn sound(
+        player: &mut Player,
+        saves: &SaveManager,
+        stc: &StaticContext,
+    ) -> Result<CommandResult> {
+        let audio_res = &stc.resources.audio;
         let audio = audio_res
             .as_ref()
             .ok_or(anyhow!("No sound channels loaded"))?;
@@ -168,16 +266,114 @@ impl RuntimeCommand {
                 player.channels.insert(channel.clone());
             } else {
                 player.channels.remove(channel);
-                audio.get_player(channel)?.stop();
+                audio.stop(channel)?;
             }
         }
+        saves.record_channels(stc.config, audio.players.keys(), &player.channels)?;
 
         Ok(CommandResult::retry())
     }
 
+    /// Handles a [`Speed`](RuntimeCommand::Speed) command.
+    ///
+    /// The chosen speed is recorded in the game's profile so that new games start from it.
+    fn speed(
+        player: &mut Player,
+        saves: &SaveManager,
+        text_context: &TextContext,
+    ) -> Result<CommandResult> {
+        let speed = &text_context.config().settings.text.speed;
+        OutputLog::print(&format!(
+            "\nCurrent speed: {}x ({})\n",
+            player.speed,
+            speed.describe(player.speed, text_context)?
+        ));
+
+        let mut choices: Vec<String> = Self::SPEED_PRESETS
+            .iter()
+            .map(|preset| format!("{preset}x"))
+            .collect();
+        choices.push("Custom".to_owned());
+        let default = Self::SPEED_PRESETS
+            .iter()
+            .position(|&preset| preset == player.speed)
+            .unwrap_or(choices.len() - 1);
+        let speed_question = requestty::Question::select("Select a text speed")
+            .choices(choices)
+            .default(default)
+            .build();
+        let speed_choice = requestty::prompt_one(speed_question)?;
+        let index = speed_choice.as_list_item().unwrap().index;
+
+        player.speed = match Self::SPEED_PRESETS.get(index) {
+            Some(&preset) => preset,
+            None => {
+                let custom_question = requestty::Question::float("Speed multiplier")
+                    .default(player.speed as f64)
+                    .validate(|value, _| {
+                        if value <= 0.0 {
+                            return Err("Speed multiplier must be positive".to_owned());
+                        }
+                        Ok(())
+                    })
+                    .build();
+                requestty::prompt_one(custom_question)?.as_float().unwrap() as f32
+            }
+        };
+        saves.record_speed(text_context.config(), player.speed)?;
+
+        OutputLog::print("");
+        speed.print(&Self::SPEED_SAMPLE, player.speed, text_context)?;
+        OutputLog::print("");
+
+        Ok(CommandResult::retry())
+    }
+
+    /// Handles a [`Peek`](RuntimeCommand::Peek) command.
+    ///
+    /// Only choices with a non-templatable `jump` can be previewed, since templated jumps depend on state
+    /// that choosing may change. The choice itself is not applied.
+    fn peek(
+        index: usize,
+        player: &Player,
+        choices: &UsableChoices,
+        stc: &StaticContext,
+        text_context: &TextContext,
+    ) -> Result<CommandResult> {
+        if !stc.config.settings.peek && !stc.config.settings.debug && !player.debug_unlocked {
+            return Err(anyhow!("Previewing choices is disabled for this game"));
+        }
+        let (choice, _) = index
+            .checked_sub(1)
+            .and_then(|i| choices.get(i))
+            .ok_or(anyhow!("Invalid choice number '{index}'"))?;
+        let jump = choice
+            .jump_target(text_context)?
+            .ok_or(anyhow!("Choice #{index} doesn't lead to another prompt"))?;
+        let latest = player.latest_entry()?;
+        let file = jump
+            .static_file(&latest.path.file)
+            .ok_or(anyhow!("Cannot preview choice #{index}"))?;
+        let prompt = jump
+            .prompt()
+            .content()
+            .ok_or(anyhow!("Cannot preview choice #{index}"))?;
+        let target =
+            PromptUtil::get(&stc.resources.prompts, &PathLookup::new(&file, prompt).into())?;
+        let line = match target.lines(text_context)? {
+            Some(lines) => Text::get_separated_lines(lines, text_context)?
+                .first()
+                .map(|(_, line)| line.get(text_context))
+                .invert()?,
+            None => None,
+        };
+        let line = line.ok_or(anyhow!("Choice #{index} leads to a prompt without text"))?;
+        Ok(CommandResult::Output(format!("\n{line}")))
+    }
+
     /// Handles a [`Prompt`](RuntimeCommand::Prompt) command.
     fn prompt(
-        notes: &Notes,
+        player: &Player,
         resources: &Resources,
         text_context: &TextContext,
     ) -> Result<CommandResult> {
@@ -200,11 +396,64 @@ impl RuntimeCommand {
         Ok(CommandResult::Output(prompt.debug_info(
             &lookup.into(),
             &resources.prompts,
-            notes,
+            &player.notes,
+            &player.session,
             text_context,
         )?))
     }
 
+    /// Handles a [`Debug`](RuntimeCommand::Debug) command.
+    ///
+    /// Debug commands are toggled for the current session only if the player enters the `settings.debug_password`.
+    fn debug(player: &mut Player, stc: &StaticContext) -> Result<CommandResult> {
+        if stc.config.settings.debug {
+            return Err(anyhow!("Debug commands are always enabled for this game"));
+        }
+        let password = stc
+            .config
+            .settings
+            .debug_password
+            .as_ref()
+            .ok_or(anyhow!("Debug commands can't be enabled for this game"))?;
+        if !player.debug_unlocked {
+            println!();
+            let question = requestty::Question::password("Debug password")
+                .mask('*')
+                .build();
+            let attempt = requestty::prompt_one(question)?;
+            if attempt.as_string() != Some(password.as_str()) {
+                return Err(anyhow!("Incorrect password"));
+            }
+        }
+        player.debug_unlocked = !player.debug_unlocked;
+        let state = if player.debug_unlocked {
+            "enabled"
+        } else {
+            "disabled"
+        };
+        Ok(CommandResult::Output(format!("Debug commands {state}")))
+    }
+
+    /// Handles an [`ImportCode`](RuntimeCommand::ImportCode) command.
+    ///
+    /// The current player is only replaced once the code is decoded and the player confirms.
+    fn import_code(player: &mut Player, stc: &StaticContext) -> Result<CommandResult> {
+        println!();
+        let code_question = requestty::Question::input("Save code").build();
+        let code = requestty::prompt_one(code_question)?;
+        let imported = SaveManager::import_code(stc.config, code.as_string().unwrap())?;
+
+        let confirm_question =
+            requestty::Question::confirm("Replace your current progress with this save?")
+                .default(false)
+                .build();
+        if !requestty::prompt_one(confirm_question)?.as_bool().unwrap() {
+            return Ok(CommandResult::retry());
+        }
+        *player = imported;
+        Ok(CommandResult::Submit(GameLoopResult::Continue))
+    }
+
     /// Handles a [`Notes`](RuntimeCommand::Notes) command.
     fn notes(player: &Player) -> Result<CommandResult> {
         if player.notes.is_empty() {
@@ -229,6 +478,146 @@ impl RuntimeCommand {
         Ok(CommandResult::Output(format!("\n{vars}")))
     }
 
+    /// Handles a [`Set`](RuntimeCommand::Set) command.
+    ///
+    /// The change is recorded in the player's history so that it can be reversed.
+    fn set(player: &mut Player, stc: &StaticContext) -> Result<CommandResult> {
+        println!();
+
+        let name_question = requestty::Question::input("Variable name")
+            .validate(|name, _| {
+                if name.trim().is_empty() {
+                    return Err("Variable name can't be empty".to_owned());
+                }
+                Ok(())
+            })
+            .build();
+        let name = requestty::prompt_one(name_question)?
+            .as_string()
+            .unwrap()
+            .trim()
+            .to_owned();
+
+        let mut value_question = requestty::Question::input(format!("Value of '{name}'"));
+        if let Some(current) = player.variables.get(&name) {
+            value_question = value_question.default(current.clone());
+        }
+        let value = requestty::prompt_one(value_question.build())?
+            .as_string()
+            .unwrap()
+            .to_owned();
+
+        let entry = VariableEntry::new(&name, value, &player.variables);
+        player.debug_edit(None, Some(VariableEntries::from([(name, entry)])), stc)?;
+        Ok(CommandResult::Submit(GameLoopResult::Continue))
+    }
+
+    /// Handles a [`Note`](RuntimeCommand::Note) command.
+    ///
+    /// The note is given if not applied and taken otherwise.
+    /// The change is recorded in the player's history so that it can be reversed.
+    fn note(player: &mut Player, stc: &StaticContext) -> Result<CommandResult> {
+        println!();
+
+        let name_question = requestty::Question::input("Note name")
+            .validate(|name, _| {
+                if name.trim().is_empty() {
+                    return Err("Note name can't be empty".to_owned());
+                }
+                Ok(())
+            })
+            .build();
+        let name = requestty::prompt_one(name_question)?
+            .as_string()
+            .unwrap()
+            .trim()
+            .to_owned();
+
+        let take = player.notes.contains(&name);
+        player.debug_edit(Some(vec![NoteEntry::new(name, take)]), None, stc)?;
+        Ok(CommandResult::Submit(GameLoopResult::Continue))
+    }
+
+    /// Handles a [`Delete`](RuntimeCommand::Delete) command.
+    ///
+    /// Deleting the current save file is allowed, but saving again afterward recreates it.
+    fn delete(saves: &SaveManager) -> Result<CommandResult> {
+        let files = saves.list()?;
+        if files.is_empty() {
+            return Err(anyhow!("No save files to delete"));
+        }
+        println!();
+
+        let names: Vec<String> = files
+            .iter()
+            .map(|file| SaveManager::save_name(file).to_owned())
+            .collect();
+        let selection = requestty::Question::multi_select("Select save files to delete")
+            .choices(names.clone())
+            .build();
+        let selected: Vec<usize> = requestty::prompt_one(selection)?
+            .as_list_items()
+            .unwrap()
+            .iter()
+            .map(|choice| choice.index)
+            .collect();
+        if selected.is_empty() {
+            return Ok(CommandResult::retry());
+        }
+
+        let confirm = requestty::Question::confirm(format!(
+            "Delete {} save file(s)? This can't be undone",
+            selected.len()
+        ))
+        .default(false)
+        .build();
+        if !requestty::prompt_one(confirm)?.as_bool().unwrap() {
+            return Ok(CommandResult::retry());
+        }
+
+        for index in &selected {
+            let file = &files[*index];
+            if saves.is_active(file) {
+                OutputLog::print(&format!(
+                    "Warning: '{}' is the current save file; saving again will recreate it",
+                    names[*index]
+                ));
+            }
+            saves.delete(file)?;
+        }
+        Ok(CommandResult::Output(format!("\nDeleted {} save file(s)", selected.len())))
+    }
+
+    /// Handles a [`Custom`](RuntimeCommand::Custom) command by evaluating its registered script.
+    ///
+    /// If the script changes any player state, the current prompt is reloaded.
+    fn custom(
+        args: &Vec<String>,
+        player: &mut Player,
+        stc: &StaticContext,
+        text_context: &TextContext,
+    ) -> Result<CommandResult> {
+        let (name, args) = args.split_first().unwrap();
+        let file = stc
+            .config
+            .commands
+            .get(name)
+            .ok_or(anyhow!("Unknown command '{name}'"))?;
+        let directive = stc.resources.scripts.command(file, args, text_context)?;
+        if !directive.changes_state() {
+            let result = directive
+                .output
+                .map(CommandResult::Output)
+                .unwrap_or(CommandResult::retry());
+            return Ok(result);
+        }
+        player.apply_directive(&directive, stc)?;
+        if let Some(output) = &directive.output {
+            OutputLog::print(output);
+        }
+        Ok(CommandResult::Submit(GameLoopResult::Continue))
+    }
+
     /// Executes a runtime command if the player has permission to do so.
     ///
     /// Any errors will be reported to the input loop with a retry following.
@@ -236,28 +625,45 @@ impl RuntimeCommand {
         &self,
         player: &mut Player,
         saves: &SaveManager,
+        choices: &UsableChoices,
         stc: &StaticContext,
         text_context: &TextContext,
     ) -> Result<CommandResult> {
-        if !self.is_normal() && !stc.config.settings.debug {
+        if !self.is_normal() && !stc.config.settings.debug && !player.debug_unlocked {
             return Err(anyhow!("Unable to access debug commands"));
         }
         use CommandResult::*;
         use RuntimeCommand::*;
         let result = match self {
-            Back => Self::back(player)?,
+            Back => Self::back(player, stc.config.settings.history.reprompt_input)?,
+            Rewind => Self::rewind(player, stc.config.settings.history.reprompt_input)?,
             Lang => Self::lang(player, &stc.resources.translations)?,
-            Info => Self::info(&player.info_pages, &stc.resources.info_pages)?,
-            Log => Self::log(&player)?,
-            Sound => Self::sound(player, &stc.resources.audio)?,
+            Info => Self::info(&player.info_pages, &stc.resources.info_pages, text_context)?,
+            Log => Self::log(&player, &stc.config.settings.log)?,
+            Sound => Self::sound(player, saves, stc)?,
+            Speed => Self::speed(player, saves, text_context)?,
+            Tip => Output(
+                stc.config
+                    .entry
+                    .random_tip(text_context)?
+                    .ok_or(anyhow!("No tips available"))?,
+            ),
+            Debug => Self::debug(player, stc)?,
+            ExportCode => Output(SaveManager::export_code(stc.config, player)?),
+            ImportCode => Self::import_code(player, stc)?,
+            Peek { choice } => Self::peek(*choice, player, choices, stc, text_context)?,
             Save => {
                 saves.write(player)?;
                 Output("Saving... ".to_owned())
             }
             Quit => Submit(GameLoopResult::Shutdown(false)),
-            Prompt => Self::prompt(&player.notes, stc.resources, text_context)?,
+            Prompt => Self::prompt(player, stc.resources, text_context)?,
             Notes => Self::notes(player)?,
             Variables => Self::variables(player)?,
+            Set => Self::set(player, stc)?,
+            Note => Self::note(player, stc)?,
+            Delete => Self::delete(saves)?,
+            Custom(args) => Self::custom(args, player, stc, text_context)?,
         };
         Ok(result)
     }
@@ -1,6 +1,7 @@
 pub mod audio;
 pub mod choice;
 pub mod context;
+pub mod dice;
 pub mod discord;
 pub mod manifest;
 pub mod path;
@@ -9,4 +10,7 @@ pub mod prompt;
 pub mod resources;
 pub mod scripts;
 pub mod state;
+#[cfg(test)]
+pub mod testing;
 pub mod text;
+pub mod warnings;
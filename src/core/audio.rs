@@ -1,13 +1,24 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Cursor,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 
 use anyhow::{anyhow, Context as _, Result};
-use playback_rs::{Player as AudioPlayer, Song};
+use camino::Utf8PathBuf;
+use playback_rs::{Hint, Player as AudioPlayer, Song};
+use rand::Rng;
 use result::OptionResultExt;
 use rlua::{Context, Table};
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{value::SeqAccessDeserializer, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
 use strum::{Display, EnumIter, EnumString};
 
 use crate::{
@@ -15,14 +26,52 @@ use crate::{
     loading::loader::Loader,
 };
 
-use super::{context::TextContext, manifest::Manifest, player::Player};
+use super::{
+    context::TextContext,
+    manifest::{DuckSettings, Manifest},
+    player::Player,
+};
 
 /// A map of channel names to audio player instances and whether they are currently enabled.
-pub type AudioPlayers = HashMap<String, AudioPlayer>;
+pub type AudioPlayers = HashMap<String, Arc<AudioPlayer>>;
 /// A map of song names to decoded song content.
 pub type Sounds = BTreeMap<String, Song>;
+/// A map of song names to their undecoded sources.
+pub type SoundSources = BTreeMap<String, SoundSource>;
+
+/// An undecoded sound file.
+pub enum SoundSource {
+    /// A sound file that can be read directly from the file system.
+    File(Utf8PathBuf),
+    /// The encoded content of a sound file that can't be seeked, such as one inside an archive.
+    ///
+    /// The content is shared so that decoding it again doesn't copy it.
+    Buffer {
+        extension: Option<String>,
+        content: Arc<[u8]>,
+    },
+}
+
+impl SoundSource {
+    /// Decodes the whole sound file into a playable [`Song`].
+    ///
+    /// Decoding isn't incremental: the song is fully decoded before it can be played, even for streamed channels.
+    pub fn decode(&self) -> Result<Song> {
+        let result = match self {
+            SoundSource::File(path) => Song::from_file(path, None),
+            SoundSource::Buffer { extension, content } => {
+                let mut hint = Hint::new();
+                if let Some(extension) = extension {
+                    hint.with_extension(extension);
+                }
+                Song::new(Box::new(Cursor::new(Arc::clone(content))), &hint, None)
+            }
+        };
+        result.map_err(|err| anyhow!(err))
+    }
+}
 
-#[derive(Deserialize, Serialize, Display, Debug, Clone, EnumString, EnumIter)]
+#[derive(Deserialize, Serialize, Display, Debug, Clone, Copy, PartialEq, EnumString, EnumIter)]
 #[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 /// A [`SoundAction`] method type.
@@ -41,6 +90,12 @@ pub enum SoundActionMode {
     Pause,
     /// Un-pauses a channel.
     Play,
+    /// Immediately plays a sound on the channel and plays it again whenever it finishes.
+    ///
+    /// The sound loops until another sound is overwritten or looped on the channel, or the channel is skipped.
+    Loop,
+    /// Stops playback and clears the queue on every channel, ignoring the action's channel.
+    StopAll,
 }
 
 impl Default for SoundActionMode {
@@ -53,7 +108,82 @@ impl SoundActionMode {
     /// Whether this action requires a specific sound file to be present.
     pub fn is_specific(&self) -> bool {
         use SoundActionMode::*;
-        matches!(&self, Queue | Overwrite | Passive)
+        matches!(&self, Queue | Overwrite | Passive | Loop)
+    }
+}
+
+#[derive(Debug)]
+/// The sound file of a [`SoundAction`], or a pool of sound files to pick from at random.
+///
+/// Can be specified as either a single sound name or a sequence of candidate names.
+pub struct SoundName {
+    pub candidates: Vec<TemplatableString>,
+}
+
+struct SoundNameVisitor;
+
+impl<'de> Visitor<'de> for SoundNameVisitor {
+    type Value = Vec<TemplatableString>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("string or sequence")
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(vec![v.to_owned().into()])
+    }
+
+    fn visit_seq<A>(self, seq: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        Deserialize::deserialize(SeqAccessDeserializer::new(seq))
+    }
+}
+
+impl<'de> Deserialize<'de> for SoundName {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self {
+            candidates: deserializer.deserialize_any(SoundNameVisitor)?,
+        })
+    }
+}
+
+impl Serialize for SoundName {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self.candidates.as_slice() {
+            [name] => name.serialize(serializer),
+            candidates => candidates.serialize(serializer),
+        }
+    }
+}
+
+impl From<String> for SoundName {
+    fn from(name: String) -> Self {
+        Self {
+            candidates: vec![name.into()],
+        }
+    }
+}
+
+impl SoundName {
+    /// Picks a candidate using the game's seeded random number generator and fills it.
+    pub fn pick(&self, text_context: &TextContext) -> Result<String> {
+        let index = match self.candidates.len() {
+            0 => return Err(anyhow!("Sound action has no sound files to pick from")),
+            1 => 0,
+            len => text_context.resources().random(|rng| rng.gen_range(0..len)),
+        };
+        self.candidates[index].fill(text_context)
     }
 }
 
@@ -63,11 +193,13 @@ impl SoundActionMode {
 /// Essentially a wrapper around [`playback_rs`] functionality.
 pub struct SoundAction {
     #[serde(skip_serializing_if = "Option::is_none")]
-    /// The sound file to submit.
+    /// The sound file to submit, or a pool of sound files to pick one from each time the action is applied.
     /// Only required for specific [`SoundActionMode`]s.
-    pub name: Option<TemplatableString>,
+    pub name: Option<SoundName>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     /// The channel to modify playback on.
-    pub channel: TemplatableString,
+    /// Required for all [`SoundActionMode`]s except [`StopAll`](SoundActionMode::StopAll).
+    pub channel: Option<TemplatableString>,
     #[serde(default)]
     /// The method to apply to the sound channel.
     pub mode: TemplatableValue<SoundActionMode>,
@@ -77,6 +209,60 @@ pub struct SoundAction {
     #[serde(skip_serializing_if = "Option::is_none")]
     /// The playback multiplier of the sound.
     pub speed: Option<TemplatableValue<f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The volume of the channel, from `0.0` to `1.0`.
+    pub volume: Option<TemplatableValue<f32>>,
+    #[serde(alias = "fade in", skip_serializing_if = "Option::is_none")]
+    /// How long, in milliseconds, to ramp up the volume of a sound that starts playing.
+    pub fade_in: Option<TemplatableValue<u64>>,
+    #[serde(alias = "fade out", skip_serializing_if = "Option::is_none")]
+    /// How long, in milliseconds, to ramp down the volume of a sound before it is skipped, paused, or overwritten.
+    pub fade_out: Option<TemplatableValue<u64>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+/// The filled fade durations of a [`SoundAction`], in milliseconds.
+struct Fades {
+    fade_in: Option<u64>,
+    fade_out: Option<u64>,
+}
+
+#[derive(Default)]
+/// The state of the latest fade on a channel.
+struct ChannelFade {
+    /// Incremented whenever a fade starts, cancelling any fade still running.
+    generation: Arc<AtomicU64>,
+    /// The volume the channel is left at once the fade finishes, if a fade was started.
+    resting: Option<f32>,
+}
+
+#[derive(Clone)]
+/// The highest volume a channel may be set to, shared with the threads that change its volume.
+///
+/// This is lowered while the channel is [ducked](Audio::duck) so that fades aim for the ducked level instead.
+struct VolumeCeiling(Arc<AtomicU32>);
+
+impl Default for VolumeCeiling {
+    fn default() -> Self {
+        Self(Arc::new(AtomicU32::new(f32::INFINITY.to_bits())))
+    }
+}
+
+impl VolumeCeiling {
+    fn set(&self, volume: f32) {
+        self.0.store(volume.to_bits(), Ordering::SeqCst);
+    }
+
+    /// Lifts the ceiling so that any volume can be set.
+    fn clear(&self) {
+        self.set(f32::INFINITY);
+    }
+
+    /// Sets a player's volume, lowered to the ceiling if it's above it.
+    fn apply(&self, player: &AudioPlayer, volume: f32) {
+        let ceiling = f32::from_bits(self.0.load(Ordering::SeqCst));
+        player.set_volume(volume.min(ceiling));
+    }
 }
 
 /// A collection of ordered [`SoundAction`]s to be submitted in order.
@@ -86,19 +272,43 @@ impl SoundAction {
     /// Validates a single [`SoundAction`] against the [`Audio`] resource.
     ///
     /// A sound action is valid if:
-    /// - Its `name` key matches a loaded sound effect
-    /// - Its `channel` key matches a created audio channel
+    /// - Each of its `name` candidates matches a loaded sound effect; language variants are optional, but the base sound is not
+    /// - Its `channel` key matches a created audio channel, and is present unless the `mode` is `stop_all`
     /// - The [specificity](SoundActionMode::is_specific) of its `mode` matches whether the sound effect is present
+    /// - Its `volume`, if not templatable, is between `0.0` and `1.0`
+    /// - Its `fade_in` and `fade_out`, if present, are used with a `mode` that they apply to
     pub fn validate(&self, audio: &Audio) -> Result<()> {
         if let Some(name) = &self.name {
-            if let Some(sound) = name.content() {
-                let _ = audio.get_sound(sound)?;
+            if name.candidates.is_empty() {
+                return Err(anyhow!("Sound action has an empty list of sound effects"));
+            }
+            for sound in name.candidates.iter().filter_map(|sound| sound.content()) {
+                let _ = audio.get_source(sound)?;
             }
         }
-        if let Some(channel) = self.channel.content() {
+        if let Some(channel) = self.channel.as_ref().and_then(|channel| channel.content()) {
             let _ = audio.get_player(channel)?;
         }
+        if let Some(volume) = self.volume.as_ref().and_then(|volume| volume.value) {
+            if !(0.0..=1.0).contains(&volume) {
+                return Err(anyhow!(
+                    "Sound action volume must be between 0 and 1, but is {volume}"
+                ));
+            }
+        }
         if let Some(mode) = &self.mode.value {
+            use SoundActionMode::*;
+            if self.channel.is_none() && *mode != StopAll {
+                return Err(anyhow!(
+                    "Sound action '{mode}' requires a channel, but none is provided"
+                ));
+            }
+            if self.fade_in.is_some() && matches!(mode, Queue | Skip | Pause | StopAll) {
+                return Err(anyhow!("Sound action '{mode}' does not support `fade_in`"));
+            }
+            if self.fade_out.is_some() && matches!(mode, Queue | Passive | Play | StopAll) {
+                return Err(anyhow!("Sound action '{mode}' does not support `fade_out`"));
+            }
             if mode.is_specific() && self.name.is_none() {
                 return Err(anyhow!(
                     "Sound action '{mode}' requires a sound effect name, but none is provided"
@@ -123,7 +333,7 @@ impl SoundAction {
     }
 }
 
-/// A container for [`AudioPlayers`] and [`Sounds`].
+/// A container for [`AudioPlayers`], [`SoundSources`], and preloaded [`Sounds`].
 ///
 /// A pair of a channel and an audio player corresponds to a single connection to a sound device,
 /// wherein one sound file can be playing at a time. Overlapping sounds requires multiple connections
@@ -131,39 +341,99 @@ impl SoundAction {
 ///
 /// Channels are only created on startup. They are never dynamically loaded and must
 /// be specified in the manifest file prior to runtime.
+///
+/// Sounds are decoded on startup, except for those in the folder of a streamed channel, which are decoded in full each time they're played.
+/// See [`ChannelSettings`](super::manifest::ChannelSettings) for more information.
 pub struct Audio {
     pub players: AudioPlayers,
+    sources: SoundSources,
     sounds: Sounds,
+    /// The channels currently ducked by [`Audio::duck`] mapped to their prior volumes.
+    ducked: RefCell<HashMap<String, f32>>,
+    /// The latest fade started on each channel.
+    fades: RefCell<HashMap<String, ChannelFade>>,
+    /// The latest restore from ducking started on each channel by [`Audio::restore`].
+    ///
+    /// These are tracked apart from `fades` so that ducking never cancels a sound's own fade.
+    restores: RefCell<HashMap<String, ChannelFade>>,
+    /// The volume ceiling of each channel that has had one created.
+    ceilings: RefCell<HashMap<String, VolumeCeiling>>,
+    /// The manifest's master volume, which all channel volumes are scaled by.
+    master: f32,
+    /// The channels currently [looping](SoundActionMode::Loop) a sound mapped to whether their loop is still active.
+    loops: RefCell<HashMap<String, Arc<AtomicBool>>>,
 }
 
 impl Audio {
+    /// The interval between volume steps when gradually changing a channel's volume.
+    const VOLUME_STEP: Duration = Duration::from_millis(20);
+    /// The interval between checks for whether a looping sound has finished.
+    const LOOP_POLL: Duration = Duration::from_millis(50);
+
     /// Creates [`AudioPlayers`]s and maps them to the config settings' `channels`.
+    ///
+    /// Each player starts at the master volume.
     fn load_players(config: &Manifest) -> Option<Result<AudioPlayers>> {
         config.settings.channels.as_ref().map(|channels| {
             channels
                 .iter()
                 .map(|(channel, _)| {
                     AudioPlayer::new(None)
-                        .map(|player| (channel.clone(), player))
+                        .map(|player| {
+                            player.set_volume(config.settings.volume);
+                            (channel.clone(), Arc::new(player))
+                        })
                         .map_err(|err| anyhow!(err))
                 })
                 .try_collect()
         })
     }
 
+    /// Whether a sound is located in the folder of a streamed channel.
+    fn is_streamed(name: &str, streamed: &HashSet<String>) -> bool {
+        name.split_once('/')
+            .map(|(dir, _)| streamed.contains(dir))
+            .unwrap_or(false)
+    }
+
+    /// Decodes all [`SoundSources`] that aren't [streamed](Self::is_streamed).
+    fn preload(sources: &SoundSources, streamed: &HashSet<String>) -> Result<Sounds> {
+        sources
+            .iter()
+            .filter(|(name, _)| !Self::is_streamed(name, streamed))
+            .map(|(name, source)| {
+                let sound = source
+                    .decode()
+                    .with_context(|| format!("Failed to decode sound file '{name}'"))?;
+                Ok((name.clone(), sound))
+            })
+            .collect()
+    }
+
     /// Loads an [`Audio`] container.
     ///
     /// If [`AudioPlayer`] creation using [`load_players`](Self::load_players) fails, it fails silently
     /// and brings the down the whole audio system with it, signaling [None] within the wrapped option.
     ///
-    /// An [`Err`] is only returned if [`load_sounds`](Self::load_sounds) errors.
+    /// An [`Err`] is only returned if loading or [preloading](Self::preload) sounds errors.
     pub fn load(loader: &Loader, config: &Manifest) -> Result<Option<Self>> {
+        let streamed = config.settings.streamed_audio_channels();
         Self::load_players(config)
             .map(|result| {
-                result.ok().map(|players| {
-                    loader
-                        .load_sounds("sounds")
-                        .map(|sounds| Self { players, sounds })
+                result.ok().map(|players| -> Result<Self> {
+                    let sources = loader.load_sound_sources("sounds")?;
+                    let sounds = Self::preload(&sources, &streamed)?;
+                    Ok(Self {
+                        players,
+                        sources,
+                        sounds,
+                        ducked: RefCell::new(HashMap::new()),
+                        fades: RefCell::new(HashMap::new()),
+                        restores: RefCell::new(HashMap::new()),
+                        ceilings: RefCell::new(HashMap::new()),
+                        master: config.settings.volume,
+                        loops: RefCell::new(HashMap::new()),
+                    })
                 })
             })
             .flatten()
@@ -171,19 +441,31 @@ impl Audio {
     }
 
     /// Retrieves an [`AudioPlayer`], if any, by a channel name.
-    pub fn get_player(&self, channel: &str) -> Result<&AudioPlayer> {
+    pub fn get_player(&self, channel: &str) -> Result<&Arc<AudioPlayer>> {
         self.players
             .get(channel)
             .ok_or(anyhow!("Invalid sound channel '{channel}'"))
     }
 
-    /// Retrieves a [`Song`], if any, by a sound name.
-    pub fn get_sound(&self, name: &str) -> Result<&Song> {
-        self.sounds
+    /// Retrieves a [`SoundSource`], if any, by a sound name.
+    pub fn get_source(&self, name: &str) -> Result<&SoundSource> {
+        self.sources
             .get(name)
             .ok_or(anyhow!("Invalid sound file '{name}'"))
     }
 
+    /// Resolves the name of a sound's variant for a language, such as `line_42.en_us` for `line_42`.
+    ///
+    /// Falls back to the base name if no such variant is loaded.
+    pub fn localized_name(&self, name: &str, lang: &str) -> String {
+        let localized = format!("{name}.{lang}");
+        if self.sources.contains_key(&localized) {
+            localized
+        } else {
+            name.to_owned()
+        }
+    }
+
     /// Returns this controller's channel names mapped to whether they are enabled on the [`Player`].
     pub fn channel_statuses(&self, player: &Player) -> Vec<(String, bool)> {
         self.players
@@ -192,6 +474,57 @@ impl Audio {
             .collect()
     }
 
+    /// Lowers the volume of the channels specified by some [`DuckSettings`], remembering their prior volumes.
+    ///
+    /// Channels that are disabled on the [`Player`] or already ducked are left untouched.
+    /// A channel still being [restored](Audio::restore) stops rising, and its restored volume is remembered instead.
+    /// Likewise, a channel that is fading remembers the volume its fade ends at, and the fade stays below the ducked level.
+    pub fn duck(&self, player: &Player, settings: &DuckSettings) -> Result<()> {
+        let mut ducked = self.ducked.borrow_mut();
+        for channel in &settings.channels {
+            if !player.channels.contains(channel) || ducked.contains_key(channel) {
+                continue;
+            }
+            let audio_player = self.get_player(channel)?;
+            self.begin_fade(&self.restores, channel, audio_player);
+            let prior = self
+                .fades
+                .borrow()
+                .get(channel)
+                .and_then(|fade| fade.resting)
+                .unwrap_or_else(|| audio_player.get_volume());
+            ducked.insert(channel.clone(), prior);
+            let level = (settings.volume * self.master).min(prior);
+            self.ceiling(channel).set(level);
+            audio_player.set_volume(level.min(audio_player.get_volume()));
+        }
+        Ok(())
+    }
+
+    /// Raises all ducked channels back to their prior volumes over `millis` milliseconds on background threads.
+    ///
+    /// If `millis` is zero, the volumes are restored immediately.
+    pub fn restore(&self, millis: u64) -> Result<()> {
+        let ducked: Vec<(String, f32)> = self.ducked.borrow_mut().drain().collect();
+        for (channel, prior) in ducked {
+            let audio_player = self.get_player(&channel)?;
+            let ceiling = self.ceiling(&channel);
+            ceiling.clear();
+            let cancelled = self.begin_fade(&self.restores, &channel, audio_player);
+            if millis == 0 {
+                audio_player.set_volume(prior);
+                continue;
+            }
+            let current = audio_player.get_volume();
+            Self::set_resting(&self.restores, &channel, prior);
+            let audio_player = audio_player.clone();
+            std::thread::spawn(move || {
+                Self::ramp(&audio_player, current, prior, millis, &ceiling, &cancelled)
+            });
+        }
+        Ok(())
+    }
+
     /// Creates a Lua table mapping each loaded audio player to a table of their data.
     ///
     /// This table is formatted as follows:
@@ -200,10 +533,18 @@ impl Audio {
     /// - `has_sound_queued`: Whether the player has a sound queued, but not playing
     /// - `position`: If the player has a sound playing, returns the position in milliseconds
     /// - `sound_duration`: If the player has a sound playing, returns its duration in milliseconds
-    pub fn create_audio_table<'a>(&self, context: &Context<'a>) -> Result<Table<'a>, rlua::Error> {
+    /// - `volume`: The current gain of the channel, including the master volume and any ducking or fading
+    /// - `enabled`: Whether the player has the channel enabled
+    pub fn create_audio_table<'a>(
+        &self,
+        context: &Context<'a>,
+        text_context: &TextContext,
+    ) -> Result<Table<'a>, rlua::Error> {
         let table = context.create_table()?;
         for (channel, player) in &self.players {
             let channel_table = context.create_table()?;
+            channel_table.set("volume", player.get_volume())?;
+            channel_table.set("enabled", text_context.channels.contains(channel))?;
             channel_table.set("is_playing", player.is_playing())?;
             channel_table.set("has_sound", player.has_current_song())?;
             channel_table.set("has_sound_queued", player.has_next_song())?;
@@ -216,40 +557,268 @@ impl Audio {
         Ok(table)
     }
 
+    /// Sets the volume of a channel, scaled by the master volume.
+    ///
+    /// If the channel is currently [ducked](Audio::duck), the volume is instead restored once ducking ends.
+    /// Otherwise, any [restore](Audio::restore) still raising the channel is cancelled.
+    fn set_volume(&self, channel: &str, player: &AudioPlayer, volume: Option<f32>) {
+        if let Some(volume) = volume {
+            let volume = volume * self.master;
+            match self.ducked.borrow_mut().get_mut(channel) {
+                Some(prior) => *prior = volume,
+                None => {
+                    self.begin_fade(&self.restores, channel, player);
+                    player.set_volume(volume);
+                }
+            }
+        }
+    }
+
+    /// Returns the [`VolumeCeiling`] of a channel, creating it if needed.
+    fn ceiling(&self, channel: &str) -> VolumeCeiling {
+        self.ceilings
+            .borrow_mut()
+            .entry(channel.to_owned())
+            .or_default()
+            .clone()
+    }
+
+    /// The volume a channel should rest at, which is its prior volume if it is currently [ducked](Audio::duck).
+    fn resting_volume(&self, channel: &str, player: &AudioPlayer) -> f32 {
+        self.ducked
+            .borrow()
+            .get(channel)
+            .copied()
+            .unwrap_or_else(|| player.get_volume())
+    }
+
+    /// Cancels any fade in `fades` running on a channel and restores the volume that fade would have left it at.
+    ///
+    /// Returns a check for whether a fade started afterward on the channel has since been cancelled.
+    fn begin_fade(
+        &self,
+        fades: &RefCell<HashMap<String, ChannelFade>>,
+        channel: &str,
+        player: &AudioPlayer,
+    ) -> impl Fn() -> bool + Send + 'static {
+        let mut fades = fades.borrow_mut();
+        let fade = fades.entry(channel.to_owned()).or_default();
+        let generation = fade.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(resting) = fade.resting.take() {
+            self.ceiling(channel).apply(player, resting);
+        }
+        let counter = fade.generation.clone();
+        move || counter.load(Ordering::SeqCst) != generation
+    }
+
+    /// Records the volume a channel rests at once the fade started by [`Audio::begin_fade`] finishes.
+    fn set_resting(fades: &RefCell<HashMap<String, ChannelFade>>, channel: &str, volume: f32) {
+        if let Some(fade) = fades.borrow_mut().get_mut(channel) {
+            fade.resting = Some(volume);
+        }
+    }
+
+    /// Gradually changes a player's volume over `millis` milliseconds, staying below a `ceiling`.
+    ///
+    /// Returns `false` if the fade was cancelled partway through.
+    fn ramp(
+        player: &AudioPlayer,
+        from: f32,
+        to: f32,
+        millis: u64,
+        ceiling: &VolumeCeiling,
+        cancelled: &impl Fn() -> bool,
+    ) -> bool {
+        let steps = (millis / Self::VOLUME_STEP.as_millis() as u64).max(1);
+        for step in 1..=steps {
+            std::thread::sleep(Self::VOLUME_STEP);
+            if cancelled() {
+                return false;
+            }
+            ceiling.apply(player, from + (to - from) * (step as f32 / steps as f32));
+        }
+        true
+    }
+
+    /// Ends the loop on a channel, if any, letting the current sound finish without playing it again.
+    fn end_loop(&self, channel: &str) {
+        if let Some(active) = self.loops.borrow_mut().remove(channel) {
+            active.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Marks a channel as looping, ending any previous loop on it.
+    ///
+    /// Returns the flag that the loop's [`repeat`](Audio::repeat) should check.
+    fn start_loop(&self, channel: &str) -> Arc<AtomicBool> {
+        self.end_loop(channel);
+        let active = Arc::new(AtomicBool::new(true));
+        self.loops
+            .borrow_mut()
+            .insert(channel.to_owned(), active.clone());
+        active
+    }
+
+    /// Plays a sound again whenever a player finishes its current sound, until the loop is no longer active.
+    ///
+    /// Since [`playback_rs`] has no native looping, this blocks and polls the player every [`Audio::LOOP_POLL`].
+    fn repeat(player: &AudioPlayer, sfx: &Song, active: &AtomicBool) {
+        while active.load(Ordering::SeqCst) {
+            if !player.has_current_song() {
+                let _ = player.play_song_now(sfx, None);
+            }
+            std::thread::sleep(Self::LOOP_POLL);
+        }
+    }
+
+    /// Stops playback on a channel entirely, ending any loop on it.
+    pub fn stop(&self, channel: &str) -> Result<()> {
+        self.end_loop(channel);
+        self.get_player(channel)?.stop();
+        Ok(())
+    }
+
     /// Applies actions requiring that a specified sound file is **not** present.
-    fn accept_general(player: &AudioPlayer, seek: Option<Duration>, mode: SoundActionMode) {
+    ///
+    /// A `fade_out` ramps the channel down before skipping or pausing, after which its volume is restored.
+    /// A `fade_in` ramps the channel up from silence when playing.
+    fn accept_general(
+        &self,
+        channel: &str,
+        player: &Arc<AudioPlayer>,
+        seek: Option<Duration>,
+        volume: Option<f32>,
+        fades: Fades,
+        mode: SoundActionMode,
+    ) {
         use SoundActionMode::*;
+        let cancelled = self.begin_fade(&self.fades, channel, player);
+        self.set_volume(channel, player, volume);
+        if mode == Skip {
+            self.end_loop(channel);
+        }
         if let Some(duration) = seek {
             player.seek(duration);
         }
-        match mode {
-            Skip => player.skip(),
-            Play => player.set_playing(true),
-            Pause => player.set_playing(false),
+        let resting = self.resting_volume(channel, player);
+        let ceiling = self.ceiling(channel);
+        match (mode, fades) {
+            (
+                Skip | Pause,
+                Fades {
+                    fade_out: Some(millis),
+                    ..
+                },
+            ) => {
+                Self::set_resting(&self.fades, channel, resting);
+                let player = player.clone();
+                std::thread::spawn(move || {
+                    if Self::ramp(&player, resting, 0.0, millis, &ceiling, &cancelled) {
+                        match mode {
+                            Skip => player.skip(),
+                            _ => player.set_playing(false),
+                        }
+                        ceiling.apply(&player, resting);
+                    }
+                });
+            }
+            (
+                Play,
+                Fades {
+                    fade_in: Some(millis),
+                    ..
+                },
+            ) => {
+                Self::set_resting(&self.fades, channel, resting);
+                player.set_volume(0.0);
+                player.set_playing(true);
+                let player = player.clone();
+                std::thread::spawn(move || {
+                    Self::ramp(&player, 0.0, resting, millis, &ceiling, &cancelled)
+                });
+            }
+            (Skip, _) => player.skip(),
+            (Play, _) => player.set_playing(true),
+            (Pause, _) => player.set_playing(false),
             _ => (),
         }
     }
 
     /// Applies actions requiring both a [`SoundActionMode`] and accompanying sound effect.
+    ///
+    /// Fades only apply to sounds that start playing immediately rather than being queued.
+    /// A `fade_out` ramps down the sound being replaced by an [`Overwrite`](SoundActionMode::Overwrite),
+    /// and a `fade_in` ramps the new sound up from silence.
+    ///
+    /// A [`Loop`](SoundActionMode::Loop) starts like an overwrite and keeps replaying the sound on a background thread.
     fn accept_specific(
-        player: &AudioPlayer,
+        &self,
+        channel: &str,
+        player: &Arc<AudioPlayer>,
         sfx: &Song,
         seek: Option<Duration>,
+        volume: Option<f32>,
+        fades: Fades,
         mode: SoundActionMode,
     ) {
         use SoundActionMode::*;
-        let _ = match mode {
-            Queue => player.play_song_next(sfx, seek),
-            Overwrite => player.play_song_now(sfx, seek),
-            Passive => {
-                if !player.has_current_song() {
-                    player.play_song_now(sfx, seek)
-                } else {
-                    Ok(())
-                }
+        let cancelled = self.begin_fade(&self.fades, channel, player);
+        self.set_volume(channel, player, volume);
+        let starts_now = match mode {
+            Overwrite | Loop => true,
+            Passive => !player.has_current_song(),
+            _ => false,
+        };
+        let looping = match mode {
+            Overwrite => {
+                self.end_loop(channel);
+                None
             }
-            _ => Ok(()),
+            Loop => Some(self.start_loop(channel)),
+            _ => None,
         };
+        if !starts_now {
+            if mode == Queue {
+                let _ = player.play_song_next(sfx, seek);
+            }
+            return;
+        }
+        let resting = self.resting_volume(channel, player);
+        let ceiling = self.ceiling(channel);
+        let fade_out = fades.fade_out.filter(|_| player.has_current_song());
+        if fade_out.is_none() && fades.fade_in.is_none() {
+            let _ = player.play_song_now(sfx, seek);
+            if let Some(active) = looping {
+                let player = player.clone();
+                let sfx = sfx.clone();
+                std::thread::spawn(move || Self::repeat(&player, &sfx, &active));
+            }
+            return;
+        }
+        Self::set_resting(&self.fades, channel, resting);
+        if fade_out.is_none() {
+            player.set_volume(0.0);
+            let _ = player.play_song_now(sfx, seek);
+        }
+        let player = player.clone();
+        let sfx = sfx.clone();
+        std::thread::spawn(move || {
+            if let Some(millis) = fade_out {
+                if !Self::ramp(&player, resting, 0.0, millis, &ceiling, &cancelled) {
+                    return;
+                }
+                let _ = player.play_song_now(&sfx, seek);
+            }
+            match fades.fade_in {
+                Some(millis) => {
+                    Self::ramp(&player, 0.0, resting, millis, &ceiling, &cancelled);
+                }
+                None => ceiling.apply(&player, resting),
+            }
+            if let Some(active) = looping {
+                Self::repeat(&player, &sfx, &active);
+            }
+        });
     }
 
     /// Applies a [`SoundAction`] to a particular channel.
@@ -259,7 +828,21 @@ impl Audio {
         action: &SoundAction,
         text_context: &TextContext,
     ) -> Result<()> {
-        let channel = action.channel.fill(text_context)?;
+        let mode = action.mode.get_value(text_context)?;
+
+        if mode == SoundActionMode::StopAll {
+            for channel in self.players.keys() {
+                self.begin_fade(&self.fades, channel, self.get_player(channel)?);
+                self.stop(channel)?;
+            }
+            return Ok(());
+        }
+
+        let channel = action
+            .channel
+            .as_ref()
+            .ok_or(anyhow!("Sound action '{mode}' requires a channel"))?
+            .fill(text_context)?;
         let audio_player = self.get_player(&channel)?;
 
         if !player.channels.contains(&channel) {
@@ -275,14 +858,46 @@ impl Audio {
             })
             .invert()?;
 
-        let mode = action.mode.get_value(text_context)?;
+        // Templated volumes can't be validated ahead of time, so they are clamped instead
+        let volume = action
+            .volume
+            .as_ref()
+            .map(|volume| volume.get_value(text_context))
+            .invert()?
+            .map(|volume| volume.clamp(0.0, 1.0));
+
+        let fade = |fade: &Option<TemplatableValue<u64>>| {
+            fade.as_ref()
+                .map(|millis| millis.get_value(text_context))
+                .invert()
+        };
+        let fades = Fades {
+            fade_in: fade(&action.fade_in)?,
+            fade_out: fade(&action.fade_out)?,
+        };
 
         match &action.name {
-            None => Self::accept_general(audio_player, seek, mode),
+            None => self.accept_general(&channel, audio_player, seek, volume, fades, mode),
             Some(name) => {
-                let sound = name.fill(text_context)?;
-                let sfx = self.get_sound(&sound)?;
-                Self::accept_specific(audio_player, sfx, seek, mode);
+                let sound = self.localized_name(&name.pick(text_context)?, text_context.lang());
+                match self.sounds.get(&sound) {
+                    Some(sfx) => {
+                        self.accept_specific(&channel, audio_player, sfx, seek, volume, fades, mode)
+                    }
+                    None => {
+                        // Streamed sounds are decoded on demand and dropped by the player once finished
+                        let sfx = self.get_source(&sound)?.decode()?;
+                        self.accept_specific(
+                            &channel,
+                            audio_player,
+                            &sfx,
+                            seek,
+                            volume,
+                            fades,
+                            mode,
+                        );
+                    }
+                }
             }
         }
 
@@ -293,3 +908,14 @@ impl Audio {
         Ok(())
     }
 }
+
+impl Drop for Audio {
+    /// Ends every loop so that their threads release the audio players,
+    /// such as when resources are replaced after a reload.
+    fn drop(&mut self) {
+        let channels: Vec<String> = self.loops.get_mut().keys().cloned().collect();
+        for channel in channels {
+            self.end_loop(&channel);
+        }
+    }
+}
@@ -1,17 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use super::{
     audio::{SoundAction, SoundActions},
     context::{StaticContext, TextContext},
-    path::{Path, PathData, PathLookup},
+    dice::{DiceRoll, RollResult},
+    path::{MatchJump, Path, PathData, PathLookup},
     player::HistoryEntry,
     prompt::{Prompt, PromptModel},
     state::{
+        cost::Cost,
         info::{InfoApplication, InfoApplications},
         notes::{NoteActions, Notes},
+        schedule::Schedule,
         variables::{
-            NamedVariableEntry, VariableApplications, VariableEntries, VariableEntry,
-            VariableInput, Variables,
+            ChoiceSelection, NamedVariableEntry, VariableApplications, VariableEntries,
+            VariableEntry, VariableInput, Variables,
         },
     },
     text::{
@@ -36,6 +39,11 @@ pub fn default_true() -> TemplatableValue<bool> {
 ///
 /// Choices can require specific player state be present to be usable, and also modify player state.
 pub struct Choice {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// A stable identifier for this choice, unique within its prompt.
+    /// If present, tracked state such as use counts is keyed by this rather than the choice's position,
+    /// so it survives choices being reordered.
+    pub id: Option<String>,
     #[serde(default, deserialize_with = "choice_text", skip_serializing_if = "Option::is_none")]
     /// The response text to display, in order, when a player is presented with [`Choices`].
     /// Only required when there is more than one choice available.
@@ -47,6 +55,14 @@ pub struct Choice {
     /// See [`Choice::tag`] for more information.
     pub tag: Option<TemplatableString>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    /// A symbol to display in front of choice responses, before the tag.
+    /// See [`Choice::icon`] for more information.
+    pub icon: Option<TemplatableString>,
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none")]
+    /// Keywords that a player can type to use this choice.
+    /// If every choice in a prompt has keywords, the prompt has the [`Keyword`](PromptModel::Keyword) model.
+    pub keywords: Option<Vec<TemplatableString>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     /// A container to prompt player input to save to a variable.
     /// There can only be one choice in an input prompt. It also has its own prompt model: [`Input`](PromptModel::Input).
     pub input: Option<VariableInput>,
@@ -54,20 +70,42 @@ pub struct Choice {
     /// The prompt to jump to after the choice is made and state is modified.
     /// Mutually exclusive with `ending`.
     pub jump: Option<Path>,
+    #[serde(alias = "match jump", skip_serializing_if = "Option::is_none")]
+    /// A jump target selected from a table of cases by a templated value.
+    /// Mutually exclusive with `jump` and `ending`.
+    pub match_jump: Option<MatchJump>,
     #[serde(default = "default_true")]
     /// Whether to display the next prompt's introductory text.
     pub display: TemplatableValue<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    /// Whether to wait for the player to press enter before jumping.
+    /// Only applies to a prompt's sole choice when it lacks `response` and `input`, giving the prompt the [`Continue`](PromptModel::Continue) model.
+    pub pause: Option<TemplatableValue<bool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// How long, in milliseconds, to pause after this choice is applied and before the next prompt is shown.
+    /// Useful for pacing a chain of redirect prompts like a cutscene.
+    pub delay: Option<TemplatableValue<u64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     /// Whether to prevent a player from reversing this choice in their history.
     /// If [`None`], defaults to the config.
     pub lock: Option<TemplatableValue<bool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Note actions to apply and require from a player.
     pub notes: Option<NoteActions>,
+    #[serde(alias = "require condition", skip_serializing_if = "Option::is_none")]
+    /// The name of a shared condition from the `conditions` directory that must be met
+    /// in addition to any `notes.require` requirements.
+    pub require_condition: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Variables to statically apply to a player without their input.
     pub variables: Option<VariableApplications>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    /// An option of a group of choices that set the same variable, marked when it is the current selection.
+    pub select: Option<ChoiceSelection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Amounts of numeric variables that the player must have to use this choice, which are deducted upon use.
+    pub cost: Option<Cost>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     /// A singular log string to append to a player's log entries.
     pub log: Option<TemplatableString>,
     #[serde(rename = "info", skip_serializing_if = "Option::is_none")]
@@ -77,13 +115,45 @@ pub struct Choice {
     /// Ordered sound actions to submit to the game's [`Audio`] resource upon using this choice.
     pub sounds: Option<SoundActions>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    /// A dice roll that decides whether to jump to its `pass` path or this choice's `jump` path.
+    pub roll: Option<DiceRoll>,
+    #[serde(default, alias = "session once", skip_serializing_if = "std::ops::Not::not")]
+    /// Whether this choice can only be used once per session.
+    ///
+    /// Used choices are tracked by the player's session rather than in notes, so they are never saved
+    /// and become usable again after the game is restarted or a new game is started.
+    pub session_once: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    /// Whether to save the player's data as a checkpoint once this choice is applied.
+    /// Has no effect if `settings.save` is disabled.
+    pub save: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    /// Whether this choice stays on the current prompt instead of jumping, re-rendering it once applied
+    /// so that choices made usable by its notes or variables appear.
+    ///
+    /// The choice is recorded in history like any other, so going back reverses its effects.
+    pub reveal: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// A forced jump to schedule for after a number of choices are made.
+    pub schedule: Option<Schedule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     /// Text lines to signify the ending of a game. Printed in the same way as prompt text.
     /// If this ending choice is the only one in a prompt, `response` is optional.
     /// If in this case `response` is [`None`], the prompt will have the [`Ending`](PromptModel::Ending) model.
     pub ending: Option<TextLines>,
+    #[serde(alias = "ending id", skip_serializing_if = "Option::is_none")]
+    /// An ID recorded in the game's profile once this choice's `ending` is reached,
+    /// which later playthroughs can check with `(ending_reached:id)`.
+    pub ending_id: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    /// Whether to roll the manifest's `entry.credits` after this choice's `ending` is printed.
+    pub credits: bool,
     #[serde(alias = "discord rich presence", skip_serializing_if = "Option::is_none")]
     /// A custom detail to show up in Discord Rich Presence after this choice is taken.
     pub drp: Option<TemplatableString>,
+    #[serde(default, rename = "_note", alias = "#comment", skip_serializing)]
+    /// An author note that is never displayed to players.
+    pub comment: Option<serde_yaml::Value>,
 }
 
 /// A list of ordered [`Choice`]s.
@@ -91,35 +161,96 @@ pub type Choices = Vec<Choice>;
 
 pub type UsableChoices<'a> = Vec<(&'a Choice, Option<String>)>;
 
+/// A set of [`Choice::key`] values for `session_once` choices that have been used this session.
+pub type SessionChoices = HashSet<String>;
+
 impl Choice {
+    /// Validates that a path points to a valid prompt if it **is not templatable**.
+    pub fn validate_path(path: &Path, local_file: &str, stc: &StaticContext) -> Result<()> {
+        if let Some(file) = &path.static_file(local_file) {
+            if let Some(prompt) = path.prompt().content() {
+                let _ =
+                    Prompt::get(&stc.resources.prompts, &PathLookup::new(&file, prompt).into())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that each script referenced by the `log` and `drp` templates exists.
+    fn validate_templates(&self, stc: &StaticContext) -> Result<()> {
+        for (key, template) in [("log", &self.log), ("drp", &self.drp)] {
+            if let Some(template) = template {
+                for script in template.script_references() {
+                    stc.resources
+                        .scripts
+                        .validate(script)
+                        .with_context(|| format!("`{key}` section references a missing script"))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Validates a choice amongst the global prompt context.
     ///
     /// A choice is valid if:
-    /// - It has either a `jump` or `ending` section
+    /// - It has either a `jump`, `match_jump`, or `ending` section, or `reveal` is enabled without any
     /// - Its `jump` section **is not templatable** and points to a valid prompt
+    /// - Each of its `match_jump` cases and its `match_jump.default` point to valid prompts if **not templatable**
     /// 	- The `file` key has to exist and the `prompt` key has to exist in that [`PromptFile`]
     /// - It has a `response` section if there is more than one choice in the prompt
+    /// - If `credits` is enabled, it has an `ending` section and `entry.credits` is declared
+    /// - If it has a `roll` section, it also has a `jump` section, and the roll's `pass` section is valid
+    /// - If it has a `pause` section, it is the only choice in the prompt and lacks `response` and `input` sections
+    /// - If `settings.validate_templates` is enabled, its `log` and `drp` sections only reference existing scripts
     pub fn validate(&self, local_file: &str, has_company: bool, stc: &StaticContext) -> Result<()> {
+        if self.reveal && (self.jump.is_some() || self.ending.is_some()) {
+            return Err(anyhow!("'reveal' is mutually exclusive with 'jump' and 'ending'"));
+        }
+        if let Some(match_jump) = &self.match_jump {
+            if self.jump.is_some() || self.ending.is_some() || self.reveal {
+                return Err(anyhow!(
+                    "'match_jump' is mutually exclusive with 'jump', 'ending', and 'reveal'"
+                ));
+            }
+            if self.roll.is_some() {
+                return Err(anyhow!("'match_jump' and 'roll' are mutually exclusive"));
+            }
+            match_jump
+                .validate(local_file, stc)
+                .with_context(|| "`match_jump` section points to invalid prompt")?;
+        }
         match &self.jump {
             None => {
-                if self.ending.is_none() {
+                if self.ending.is_none() && self.match_jump.is_none() && !self.reveal {
                     return Err(anyhow!(
                         "Lacks `jump` section, but doesn't have an `ending` section"
                     ));
                 }
             }
             Some(jump) => {
-                if let Some(file) = &jump.static_file(local_file) {
-                    if let Some(prompt) = jump.prompt().content() {
-                        let _ = Prompt::get(
-                            &stc.resources.prompts,
-                            &PathLookup::new(&file, prompt).into(),
-                        )
-                        .with_context(|| "`jump` section points to invalid prompt")?;
-                    }
-                }
+                Self::validate_path(jump, local_file, stc)
+                    .with_context(|| "`jump` section points to invalid prompt")?;
             }
         }
+        if self.credits {
+            if self.ending.is_none() {
+                return Err(anyhow!("Has `credits` enabled, but lacks an `ending` section"));
+            }
+            if stc.config.entry.credits.is_none() {
+                return Err(anyhow!(
+                    "Has `credits` enabled, but no credits are declared in `entry.credits`"
+                ));
+            }
+        }
+        if let Some(roll) = &self.roll {
+            if self.jump.is_none() {
+                return Err(anyhow!("Has a `roll` section, but lacks a `jump` section"));
+            }
+            roll.validate()?;
+            Self::validate_path(&roll.pass, local_file, stc)
+                .with_context(|| "`roll.pass` section points to invalid prompt")?;
+        }
         if has_company && self.response.is_none() {
             return Err(anyhow!(
                 "Lacks `response` section, but multiple choices are present in prompt"
@@ -128,6 +259,43 @@ impl Choice {
         if self.response.is_some() && self.input.is_some() {
             return Err(anyhow!("'response' and 'input' are mutually exclusive"));
         }
+        if let Some(input) = &self.input {
+            input.validate(self.variables.as_ref())?;
+        }
+        if self.select.is_some() && self.input.is_some() {
+            return Err(anyhow!("'select' and 'input' are mutually exclusive"));
+        }
+        if self.keywords.is_some() && self.input.is_some() {
+            return Err(anyhow!("'match' and 'input' are mutually exclusive"));
+        }
+        if self.pause.is_some() {
+            if has_company || self.response.is_some() || self.input.is_some() {
+                return Err(anyhow!(
+                    "'pause' is only available on a sole choice without 'response' or 'input'"
+                ));
+            }
+            if self.jump.is_none() && self.match_jump.is_none() {
+                return Err(anyhow!("Has a `pause` section, but lacks a `jump` section"));
+            }
+        }
+        if let Some(notes) = &self.notes {
+            notes.validate()?;
+        }
+        if let Some(condition) = &self.require_condition {
+            stc.resources
+                .condition(condition)
+                .with_context(|| "`require_condition` references an unknown condition")?;
+        }
+        if let Some(cost) = &self.cost {
+            cost.validate()?;
+        }
+        if stc.config.settings.validate_templates {
+            self.validate_templates(stc)?;
+        }
+        if let Some(schedule) = &self.schedule {
+            Self::validate_path(&schedule.jump, local_file, stc)
+                .with_context(|| "`schedule.jump` section points to invalid prompt")?;
+        }
         if let Some(apps) = &self.info_pages {
             InfoApplication::validate_all(apps, &stc.resources.info_pages)?;
         }
@@ -143,9 +311,13 @@ impl Choice {
     ///
     /// If both the input result and this choice's `variables` key are [`None`], returns none.
     /// Otherwise, returns a combined map based on which inputs are present.
+    ///
+    /// If the input result and `variables` set the same variable, the value kept is decided by
+    /// the input's `precedence`, with the input winning by default.
     fn create_variable_entries(
         &self,
         input: Option<NamedVariableEntry>,
+        roll: Option<&RollResult>,
         variables: &Variables,
         text_context: &TextContext,
     ) -> Result<Option<VariableEntries>> {
@@ -154,32 +326,78 @@ impl Choice {
             .as_ref()
             .map(|vars| VariableEntry::from_map(&vars, variables, text_context))
             .invert()?;
-        if input.is_none() && var_entries.is_none() {
+        if input.is_none()
+            && roll.is_none()
+            && var_entries.is_none()
+            && self.select.is_none()
+            && self.cost.is_none()
+        {
             return Ok(None);
         }
         let mut entries = var_entries.unwrap_or(HashMap::new());
+        if let Some(select) = &self.select {
+            let named = select.to_entry(variables, text_context)?;
+            entries.insert(named.name, named.entry);
+        }
         if let Some(named) = input {
+            let input_wins = self
+                .input
+                .as_ref()
+                .map(|input| input.input_wins())
+                .unwrap_or(true);
+            if input_wins || !entries.contains_key(&named.name) {
+                entries.insert(named.name, named.entry);
+            }
+        }
+        if let Some(roll) = roll {
+            let named =
+                NamedVariableEntry::new(roll.variable.clone(), roll.total.to_string(), variables);
             entries.insert(named.name, named.entry);
         }
+        if let Some(cost) = &self.cost {
+            cost.apply(&mut entries, variables, text_context)?;
+        }
         Ok(Some(entries))
     }
 
     /// Constructs a [`HistoryEntry`] based on this choice object.
     ///
     /// Copies over control flags, the path based on the latest history entry, and notes and variable applications.
+    /// If a [`RollResult`] is present and passed, the path is instead based on the roll's `pass` section.
+    /// If the choice has a `match_jump` section, the path is that of the matching case.
+    /// If the choice is a `reveal` choice, the path is that of the latest history entry.
     pub fn to_history_entry(
         &self,
         latest: &HistoryEntry,
         input: Option<NamedVariableEntry>,
+        roll: Option<&RollResult>,
         variables: &Variables,
         model: &PromptModel,
         once: &Option<String>,
         stc: &StaticContext,
         text_context: &TextContext,
     ) -> Option<Result<HistoryEntry>> {
-        self.jump.as_ref().map(|jump| {
+        let jump = match &self.jump {
+            Some(jump) => match (&self.roll, roll) {
+                (Some(dice_roll), Some(result)) if result.passed => Some(&dice_roll.pass),
+                _ => Some(jump),
+            },
+            None if self.reveal => None,
+            None if self.match_jump.is_some() => None,
+            None => return None,
+        };
+        let entry = || -> Result<HistoryEntry> {
+            let jump = match &self.match_jump {
+                Some(match_jump) => Some(match_jump.resolve(text_context)?),
+                None => jump,
+            };
+            let path = match jump {
+                Some(jump) => jump.fill(&latest.path, text_context)?,
+                None => latest.path.clone(),
+            };
+            let input_name = input.as_ref().map(|named| named.name.clone());
             Ok(HistoryEntry {
-                path: jump.fill(&latest.path, text_context)?,
+                path,
                 display: self.display.get_value(text_context)?,
                 locked: self
                     .lock
@@ -193,16 +411,38 @@ impl Choice {
                     .as_ref()
                     .map(|n| n.to_note_entries(once, text_context))
                     .invert()?,
-                variables: self.create_variable_entries(input, variables, text_context)?,
+                variables: self.create_variable_entries(input, roll, variables, text_context)?,
                 log: self.log.is_some(),
+                input: input_name,
+                scheduled: None,
+                response: self
+                    .response
+                    .as_ref()
+                    .map(|response| response.content.fill(text_context))
+                    .invert()?,
             })
-        })
+        };
+        Some(entry())
+    }
+
+    /// Returns the key that tracks this choice's state, given it is at `index` within the prompt at `path`.
+    ///
+    /// If the choice has an `id`, the key is stable, such as `file/prompt@id`.
+    /// Otherwise, it is positional, such as `file/prompt#1`.
+    pub fn key(&self, path: &PathData, index: usize) -> String {
+        match &self.id {
+            Some(id) => format!("{path}@{id}"),
+            None => format!("{path}#{}", index + 1),
+        }
     }
 
     /// Determines if a player can use this choice.
     ///
     /// This check passes if:
     /// - All note requirement `has` fields match the state of the provided [`Notes`] object, and
+    /// - If any `require_any` groups are present, all requirements in at least one of them match, and
+    /// - If a `require_condition` is present, all requirements of the named condition match, and
+    /// - If a `cost` is present, the player's variables can afford it, and
     /// - The notes object does not contain the `once` value, if any is present
     ///     - The filled `once` value is also returned. It must be carried over to when the choice is used and applied then.
     pub fn can_player_use(
@@ -210,17 +450,38 @@ impl Choice {
         notes: &Notes,
         text_context: &TextContext,
     ) -> Result<(bool, Option<String>)> {
+        if let Some(cost) = &self.cost {
+            if !cost.is_affordable(&text_context.variables, text_context)? {
+                return Ok((false, None));
+            }
+        }
+        if let Some(condition) = &self.require_condition {
+            let condition = text_context.resources().condition(condition)?;
+            if !condition.is_met(notes, text_context)? {
+                return Ok((false, None));
+            }
+        }
         let once = 'outer: {
             if let Some(actions) = &self.notes {
                 if let Some(require) = &actions.require {
                     for requirement in require {
-                        if requirement.state.get_state(text_context)?
-                            != notes.contains(&requirement.state.name.fill(text_context)?)
-                        {
+                        if !requirement.state.is_met(notes, text_context)? {
                             return Ok((false, None));
                         }
                     }
                 }
+                if let Some(groups) = &actions.require_any {
+                    let mut any = false;
+                    for group in groups {
+                        if group.is_met(notes, text_context)? {
+                            any = true;
+                            break;
+                        }
+                    }
+                    if !any {
+                        return Ok((false, None));
+                    }
+                }
                 if let Some(once) = &actions.once {
                     let once = once.fill(text_context)?;
                     if notes.contains(&once) {
@@ -238,45 +499,236 @@ impl Choice {
     ///
     /// If [`Some`], returns `[VALUE] `, trailing space included.
     /// If [`None`], returns an empty [`String`].
+    ///
+    /// If the tag value has a style in the config's `tags` settings, the value is styled inside the brackets
+    /// unless accessible mode is enabled.
     fn tag(&self, text_context: &TextContext) -> Result<String> {
         let result = match &self.tag {
-            Some(tag) => format!("[{}] ", tag.fill(text_context)?),
+            Some(tag) => {
+                let tag = tag.fill(text_context)?;
+                let styled = match text_context.config().settings.tags.get(&tag) {
+                    Some(style) if !text_context.accessible() => style.apply(&tag)?,
+                    _ => tag,
+                };
+                format!("[{styled}] ")
+            }
             None => String::new(),
         };
         Ok(result)
     }
 
+    /// Formats the icon to display before the response tag, if any.
+    ///
+    /// Icons are omitted in accessible mode since screen readers may read them out unpredictably.
+    fn icon(&self, text_context: &TextContext) -> Result<String> {
+        let result = match &self.icon {
+            Some(icon) if !text_context.accessible() => format!("{} ", icon.fill(text_context)?),
+            _ => String::new(),
+        };
+        Ok(result)
+    }
+
+    /// Formats the marker to display after the response if this choice is the active option of its `select` group.
+    fn selected_marker(&self, text_context: &TextContext) -> Result<String> {
+        let result = match &self.select {
+            Some(select) if select.is_active(text_context)? => {
+                format!(" {}", text_context.config().settings.text.selected_marker)
+            }
+            _ => String::new(),
+        };
+        Ok(result)
+    }
+
     /// Constructs the response line for display in the game's runtime.
     ///
+    /// If `numbered` is `false`, the line is prefixed with a dash instead of its index.
+    ///
     /// ### Examples
     ///
     /// - `1) [ROGUE] "Ain't no thief."`
-    /// - `2) Put down the sword`
-    fn response_line(&self, index: usize, text_context: &TextContext) -> Result<String> {
+    /// - `2) ⚔ Put down the sword`
+    /// - `3) Fast text (selected)`
+    /// - `- Go north`
+    fn response_line(
+        &self,
+        index: usize,
+        numbered: bool,
+        text_context: &TextContext,
+    ) -> Result<String> {
+        let icon = self.icon(text_context)?;
         let tag = self.tag(text_context)?;
         let response = self.response.as_ref().unwrap().get(text_context)?;
-        Ok(format!("{index}) {tag}{response}"))
+        let marker = self.selected_marker(text_context)?;
+        let prefix = if numbered {
+            format!("{index})")
+        } else {
+            "-".to_owned()
+        };
+        Ok(format!("{prefix} {icon}{tag}{response}{marker}"))
     }
 
-    /// Constructs a [`String`] of ordered choice responses.
-    pub fn display(choices: &Vec<&Choice>, text_context: &TextContext) -> Result<String> {
-        let result = choices
+    /// Constructs the ordered choice response lines.
+    ///
+    /// Numbering begins after `offset`, such as when the choices are a later page of a prompt's choices.
+    pub fn display_lines(
+        choices: &[&Choice],
+        offset: usize,
+        numbered: bool,
+        text_context: &TextContext,
+    ) -> Result<Vec<String>> {
+        choices
             .iter()
             .enumerate()
             .filter(|(_, choice)| choice.response.is_some())
-            .map(|(index, choice)| choice.response_line(index + 1, text_context))
-            .try_collect::<Vec<String>>()?
-            .join("\n");
-        Ok(result)
+            .map(|(index, choice)| choice.response_line(offset + index + 1, numbered, text_context))
+            .try_collect()
+    }
+
+    /// Fills in this choice's keywords, if any, normalized for matching against player input.
+    pub fn keywords(&self, text_context: &TextContext) -> Result<Vec<String>> {
+        self.keywords
+            .as_ref()
+            .map(|keywords| {
+                keywords
+                    .iter()
+                    .map(|keyword| Ok(keyword.fill(text_context)?.trim().to_lowercase()))
+                    .collect()
+            })
+            .unwrap_or(Ok(Vec::new()))
     }
 
     /// Whether this choice jumps to a specific prompt.
     ///
-    /// Returns `true` if the choice has a `jump` path and [`Path::matches`] passes.
+    /// Returns `true` if the choice has a `jump` path, or any `match_jump` path, and [`Path::matches`] passes.
     pub fn has_jump_to(&self, current_file: &str, other: &PathData) -> bool {
+        if let Some(match_jump) = &self.match_jump {
+            return match_jump
+                .paths()
+                .any(|path| path.matches(current_file, other));
+        }
         match &self.jump {
             None => false,
             Some(jump) => jump.matches(current_file, other),
         }
     }
+
+    /// Returns the path this choice would jump to if it were made now, ignoring any `roll`.
+    ///
+    /// Resolves the `match_jump` section if present; otherwise returns the `jump` section.
+    pub fn jump_target(&self, text_context: &TextContext) -> Result<Option<&Path>> {
+        match &self.match_jump {
+            Some(match_jump) => Ok(Some(match_jump.resolve(text_context)?)),
+            None => Ok(self.jump.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{core::testing, text_context};
+
+    use super::*;
+
+    /// Returns the value that a choice leaves the `name` variable at once the player inputs `Ann`.
+    ///
+    /// The player's `target` variable is set to `name`, so that applications can name it through templating.
+    fn applied_name(yaml: &str) -> String {
+        let config = testing::manifest("");
+        let resources = testing::resources("{}");
+        let stc = StaticContext::new(&config, &resources, true);
+        let mut player = testing::player(&config);
+        player
+            .variables
+            .insert("target".to_owned(), "name".to_owned());
+        let text_context = text_context!(&stc, player);
+        let choice: Choice = serde_yaml::from_str(yaml).unwrap();
+        let input = NamedVariableEntry::new("name".to_owned(), "Ann".to_owned(), &player.variables);
+        let entries = choice
+            .create_variable_entries(Some(input), None, &player.variables, &text_context)
+            .unwrap()
+            .unwrap();
+        entries["name"].value.clone()
+    }
+
+    /// Returns whether a player holding `notes` can use a choice parsed from `yaml`.
+    fn usable_with(yaml: &str, notes: &[&str]) -> bool {
+        let config = testing::manifest("");
+        let resources = testing::resources("{}");
+        let stc = StaticContext::new(&config, &resources, true);
+        let mut player = testing::player(&config);
+        player
+            .notes
+            .extend(notes.iter().map(|note| note.to_string()));
+        let text_context = text_context!(&stc, player);
+        let choice: Choice = serde_yaml::from_str(yaml).unwrap();
+        choice
+            .can_player_use(&player.notes, &text_context)
+            .unwrap()
+            .0
+    }
+
+    const REQUIRE_ANY: &str = "
+notes:
+  require: [key]
+  require_any:
+    - [map, compass]
+    - guide
+jump: start
+";
+
+    #[test]
+    fn require_any_passes_with_one_group_met() {
+        assert!(usable_with(REQUIRE_ANY, &["key", "map", "compass"]));
+        assert!(usable_with(REQUIRE_ANY, &["key", "guide"]));
+    }
+
+    #[test]
+    fn require_any_fails_with_no_group_fully_met() {
+        assert!(!usable_with(REQUIRE_ANY, &["key", "map"]));
+        assert!(!usable_with(REQUIRE_ANY, &["key"]));
+    }
+
+    #[test]
+    fn require_must_pass_alongside_require_any() {
+        assert!(!usable_with(REQUIRE_ANY, &["guide"]));
+    }
+
+    #[test]
+    fn input_wins_by_default() {
+        let yaml = "
+input:
+  variable: name
+variables:
+  - name: <target>
+    value: Default
+jump: start
+";
+        assert_eq!(applied_name(yaml), "Ann");
+    }
+
+    #[test]
+    fn input_precedence_keeps_input() {
+        let yaml = "
+input:
+  variable: name
+  precedence: input
+variables:
+  name: Default
+jump: start
+";
+        assert_eq!(applied_name(yaml), "Ann");
+    }
+
+    #[test]
+    fn variables_precedence_keeps_applied_value() {
+        let yaml = "
+input:
+  variable: name
+  precedence: variables
+variables:
+  name: Default
+jump: start
+";
+        assert_eq!(applied_name(yaml), "Default");
+    }
 }
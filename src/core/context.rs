@@ -1,9 +1,11 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
 use rlua::{Context, Table};
 
 use crate::core::{
-    manifest::Manifest,
+    manifest::{Manifest, TextSettings},
     resources::Resources,
-    state::{notes::Notes, variables::Variables},
+    state::{notes::Notes, schedule::ScheduledEvents, variables::Variables},
     text::display::TranslationFile,
 };
 
@@ -16,17 +18,23 @@ use crate::core::{
 pub struct StaticContext<'a> {
     pub config: &'a Manifest,
     pub resources: &'a Resources,
+    /// Whether accessible mode is enabled, as determined by [`TextSettings::accessible`] at startup.
+    pub accessible: bool,
 }
 
 impl<'a> StaticContext<'a> {
-    pub fn new(config: &'a Manifest, resources: &'a Resources) -> Self {
-        Self { config, resources }
+    pub fn new(config: &'a Manifest, resources: &'a Resources, accessible: bool) -> Self {
+        Self {
+            config,
+            resources,
+            accessible,
+        }
     }
 }
 
 impl<'a> Clone for StaticContext<'a> {
     fn clone(&self) -> Self {
-        Self::new(self.config, self.resources)
+        Self::new(self.config, self.resources, self.accessible)
     }
 }
 
@@ -40,11 +48,36 @@ impl<'a> Clone for StaticContext<'a> {
 /// - `game_authors`: The metadata's `authors` key, represented as a sequence
 /// - `game_version`: The metadata's `version` key
 /// - `lang`: The currently loaded language key
+/// - `difficulty`: The player's selected difficulty level, if the game declares any
+/// - `random_note`: The note randomly applied by the last choice, if any; only available to its `log` and `drp`
+/// - `untried_count`: The amount of usable choices in the current prompt the player has never selected, once known
+///
+/// Scripts additionally receive the player's pending scheduled events as the `schedule` sequence.
+///
+/// The amount of times a choice with an `id` was selected is available by its full key, such as `file/prompt@id`,
+/// through the `(chose:file/prompt@id)` template and the `nage.chose("file/prompt@id")` script function.
+/// Choices without an `id` and unknown keys always count as `0`.
+///
+/// Whether an ending with an `ending_id` was reached in any playthrough is available through the
+/// `(ending_reached:id)` template and the `nage.ending_reached("id")` script function.
 pub struct TextContext<'a> {
     stc: StaticContext<'a>,
     lang: String,
+    difficulty: Option<String>,
     pub notes: Notes,
     pub variables: Variables,
+    pub scheduled: ScheduledEvents,
+    /// The amount of times each choice was selected, keyed by [`Choice::key`](crate::core::choice::Choice::key).
+    pub choice_uses: HashMap<String, u32>,
+    /// The IDs of the endings reached in any playthrough.
+    pub endings_reached: BTreeSet<String>,
+    /// The player's enabled sound channels.
+    pub channels: HashSet<String>,
+    /// The amount of usable choices in the current prompt that haven't been selected before.
+    /// Only set by the game loop after the prompt's choices have been gathered.
+    pub untried: Option<usize>,
+    /// The note randomly applied by the choice that was just made, if any.
+    pub random_note: Option<String>,
 }
 
 impl<'a> TextContext<'a> {
@@ -52,12 +85,29 @@ impl<'a> TextContext<'a> {
     ///
     /// The resulting text context does not own the provided [`StaticContext`] reference, rather a new copy based on
     /// the static context [`Clone`] implementation, which preserves the internal references.
-    pub fn new(stc: &'a StaticContext, lang: String, notes: Notes, variables: Variables) -> Self {
+    pub fn new(
+        stc: &'a StaticContext,
+        lang: String,
+        difficulty: Option<String>,
+        notes: Notes,
+        variables: Variables,
+        scheduled: ScheduledEvents,
+        choice_uses: HashMap<String, u32>,
+        endings_reached: BTreeSet<String>,
+        channels: HashSet<String>,
+    ) -> Self {
         TextContext {
             stc: stc.clone(),
             lang,
+            difficulty,
             notes,
             variables,
+            scheduled,
+            choice_uses,
+            endings_reached,
+            channels,
+            untried: None,
+            random_note: None,
         }
     }
 
@@ -69,10 +119,44 @@ impl<'a> TextContext<'a> {
         &self.stc.resources
     }
 
+    /// Whether text should be printed instantly and without styling.
+    pub fn accessible(&self) -> bool {
+        self.stc.accessible
+    }
+
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
     pub fn lang_file(&self) -> Option<&TranslationFile> {
         self.stc.resources.lang_file(&self.lang)
     }
 
+    /// The templating prefix that fills in the use count of a choice, such as `(chose:file/prompt@id)`.
+    pub const CHOSE_PREFIX: &'static str = "chose:";
+
+    /// The templating prefix that fills in whether an ending was reached, such as `(ending_reached:id)`.
+    pub const ENDING_REACHED_PREFIX: &'static str = "ending_reached:";
+
+    /// Whether the ending with some `ending_id` was reached in any playthrough.
+    pub fn ending_reached(&self, id: &str) -> bool {
+        self.endings_reached.contains(id)
+    }
+
+    /// Returns the amount of times the choice with the stable key `file/prompt@id` was selected.
+    ///
+    /// Positional keys of choices without an `id` are not accepted, since they change as the prompt is edited.
+    pub fn choice_count(&self, key: &str) -> u32 {
+        Self::count_uses(&self.choice_uses, key)
+    }
+
+    fn count_uses(choice_uses: &HashMap<String, u32>, key: &str) -> u32 {
+        if !key.contains('@') {
+            return 0;
+        }
+        choice_uses.get(key).copied().unwrap_or(0)
+    }
+
     /// Attempts to fetch a global variable for direct templating.
     /// These variables are prefixed under `nage:`.
     ///
@@ -85,6 +169,9 @@ impl<'a> TextContext<'a> {
                 "game_authors" => Some(self.stc.config.metadata.authors.join(", ")),
                 "game_version" => Some(self.stc.config.metadata.version.to_string()),
                 "lang" => Some(self.lang.to_owned()),
+                "difficulty" => self.difficulty.clone(),
+                "untried_count" => self.untried.map(|count| count.to_string()),
+                "random_note" => self.random_note.clone(),
                 _ => None,
             })
             .flatten()
@@ -104,6 +191,20 @@ impl<'a> TextContext<'a> {
         )?;
         table.set("game_version", self.stc.config.metadata.version.to_string())?;
         table.set("lang", self.lang.clone())?;
+        table.set("difficulty", self.difficulty.clone())?;
+        table.set("untried_count", self.untried)?;
+        table.set("random_note", self.random_note.clone())?;
+        let choice_uses = self.choice_uses.clone();
+        table.set(
+            "chose",
+            context
+                .create_function(move |_, key: String| Ok(Self::count_uses(&choice_uses, &key)))?,
+        )?;
+        let endings_reached = self.endings_reached.clone();
+        table.set(
+            "ending_reached",
+            context.create_function(move |_, id: String| Ok(endings_reached.contains(&id)))?,
+        )?;
         Ok(table)
     }
 }
@@ -114,8 +215,13 @@ macro_rules! text_context {
         TextContext::new(
             $stc,
             $player.lang.clone(),
+            $player.difficulty.clone(),
             $player.notes.clone(),
             $player.variables.clone(),
+            $player.scheduled.clone(),
+            $player.choice_uses.clone(),
+            $player.endings_reached.clone(),
+            $player.channels.clone(),
         )
     };
 }
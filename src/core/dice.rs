@@ -0,0 +1,178 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, Context, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    context::TextContext,
+    path::Path,
+    text::templating::{TemplatableString, TemplatableValue},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A set of dice in standard notation, such as `2d6+3`.
+pub struct Dice {
+    /// The amount of dice to roll.
+    pub count: u32,
+    /// The amount of sides on each die.
+    pub sides: u32,
+    /// A flat value added to the total.
+    pub modifier: i64,
+}
+
+impl FromStr for Dice {
+    type Err = anyhow::Error;
+
+    /// Parses dice notation in the form `[count]d<sides>[+-modifier]`.
+    fn from_str(s: &str) -> Result<Self> {
+        let notation = s.trim().to_lowercase();
+        let (count, rest) = notation
+            .split_once('d')
+            .ok_or(anyhow!("Invalid dice notation '{s}'; expected a form such as '2d6+3'"))?;
+        let (sides, modifier) = match rest.find(['+', '-']) {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+        let count = match count {
+            "" => 1,
+            count => count
+                .parse::<u32>()
+                .with_context(|| format!("Invalid dice count in '{s}'"))?,
+        };
+        let sides = sides
+            .parse::<u32>()
+            .with_context(|| format!("Invalid dice sides in '{s}'"))?;
+        let modifier = if modifier.is_empty() {
+            0
+        } else {
+            modifier
+                .trim_start_matches('+')
+                .parse::<i64>()
+                .with_context(|| format!("Invalid dice modifier in '{s}'"))?
+        };
+        if count == 0 || sides == 0 {
+            return Err(anyhow!("Dice count and sides must be non-zero in '{s}'"));
+        }
+        if count > Self::MAX_COUNT {
+            return Err(anyhow!("Dice count in '{s}' exceeds the maximum of {}", Self::MAX_COUNT));
+        }
+        Ok(Self {
+            count,
+            sides,
+            modifier,
+        })
+    }
+}
+
+impl Display for Dice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        match self.modifier {
+            0 => Ok(()),
+            m if m > 0 => write!(f, "+{m}"),
+            m => write!(f, "{m}"),
+        }
+    }
+}
+
+impl Dice {
+    /// The maximum amount of dice that can be rolled at once.
+    pub const MAX_COUNT: u32 = 1000;
+
+    /// Rolls each die and returns the modified total, saturating at the bounds of [`i64`].
+    pub fn roll<R>(&self, rng: &mut R) -> i64
+    where
+        R: Rng,
+    {
+        let rolled: i64 = (0..self.count)
+            .map(|_| rng.gen_range(1..=self.sides) as i64)
+            .sum();
+        rolled.saturating_add(self.modifier)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+/// A dice roll performed when a choice is made, branching on the result.
+///
+/// If the total meets the `threshold`, the choice jumps to `pass`. Otherwise, it uses its own `jump`.
+pub struct DiceRoll {
+    /// The dice notation to roll, such as `2d6+3`.
+    pub dice: TemplatableString,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The variable to save the total to. Defaults to [`DiceRoll::DEFAULT_VARIABLE`].
+    pub variable: Option<TemplatableString>,
+    /// The minimum total required to jump to `pass`.
+    pub threshold: TemplatableValue<i64>,
+    /// The prompt to jump to if the roll meets the threshold.
+    pub pass: Path,
+}
+
+/// The outcome of a [`DiceRoll`].
+pub struct RollResult {
+    /// The variable name the total is saved to.
+    pub variable: String,
+    /// The dice that were rolled.
+    pub dice: Dice,
+    /// The modified total of the roll.
+    pub total: i64,
+    /// Whether the total met the roll's threshold.
+    pub passed: bool,
+}
+
+impl Display for RollResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = if self.passed { "success" } else { "failure" };
+        write!(f, "Rolled {}: {} ({outcome})", self.dice, self.total)
+    }
+}
+
+impl DiceRoll {
+    /// The variable that roll totals are saved to if none is specified, making the last roll available as `<roll>`.
+    pub const DEFAULT_VARIABLE: &'static str = "roll";
+
+    /// Validates the dice notation if it is not templatable.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(dice) = self.dice.content() {
+            let _ = Dice::from_str(dice)?;
+        }
+        Ok(())
+    }
+
+    /// Rolls the dice and determines whether the total meets the threshold.
+    pub fn roll(&self, text_context: &TextContext) -> Result<RollResult> {
+        let dice = Dice::from_str(&self.dice.fill(text_context)?)?;
+        let total = text_context.resources().random(|rng| dice.roll(rng));
+        let variable = match &self.variable {
+            Some(variable) => variable.fill(text_context)?,
+            None => Self::DEFAULT_VARIABLE.to_owned(),
+        };
+        Ok(RollResult {
+            variable,
+            dice,
+            total,
+            passed: total >= self.threshold.get_value(text_context)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn dice_count_is_capped() {
+        assert!(Dice::from_str("1000d6").is_ok());
+        assert!(Dice::from_str("1001d6").is_err());
+        assert!(Dice::from_str("4000000000d6").is_err());
+    }
+
+    #[test]
+    fn large_modifier_saturates() {
+        let dice = Dice::from_str(&format!("1d6+{}", i64::MAX)).unwrap();
+        assert_eq!(dice.roll(&mut StdRng::seed_from_u64(0)), i64::MAX);
+    }
+}
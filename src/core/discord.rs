@@ -18,9 +18,22 @@ use super::{
 pub enum RichPresenceMode {
     Id,
     Custom { fallback: bool },
+    Template { content: TemplatableString },
 }
 
 impl RichPresenceMode {
+    /// The maximum amount of characters Discord accepts in a state string.
+    pub const MAX_STATE_LENGTH: usize = 128;
+
+    /// Truncates a state string to [`RichPresenceMode::MAX_STATE_LENGTH`] characters, ending it with an ellipsis if shortened.
+    fn truncate(state: String) -> String {
+        if state.chars().count() <= Self::MAX_STATE_LENGTH {
+            return state;
+        }
+        let truncated: String = state.chars().take(Self::MAX_STATE_LENGTH - 1).collect();
+        format!("{truncated}…")
+    }
+
     /// Determines the next rich presence status for the game.
     ///
     /// If the mode is [`Id`](RichPresenceMode::Id), returns the prompt ID of the latest entry destination.
@@ -28,6 +41,9 @@ impl RichPresenceMode {
     ///
     /// If the mode is [`Custom`](RichPresenceMode::Custom), attempts to use the `drp` key on a choice.
     /// If no such value is present and `fallback` is set to `true`, again attempts to use the `log` key on the same choice.
+    ///
+    /// If the mode is [`Template`](RichPresenceMode::Template), fills the content against the text context,
+    /// truncating it to fit within Discord's length limit.
     pub fn get_state(
         &self,
         latest: &HistoryEntry,
@@ -49,6 +65,10 @@ impl RichPresenceMode {
                         None
                     }
                 }),
+            Template { content } => text_context
+                .map(|context| content.fill(context))
+                .invert()?
+                .map(Self::truncate),
         };
         Ok(result)
     }
@@ -1,16 +1,29 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
     str::FromStr,
 };
 
 use anyhow::{anyhow, Context, Result};
+use crossterm::{
+    event::{self, Event, KeyCode},
+    style::{Color, Stylize},
+    terminal,
+};
+use rand::seq::SliceRandom;
+use result::OptionResultExt;
 
 use semver::{Version, VersionReq};
-use serde::Deserialize;
+use serde::{
+    de::{value::MapAccessDeserializer, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_yaml::Value;
 
 use crate::{
     core::text::{
-        display::{TextLines, TextSpeed},
+        display::{Text, TextLines, TextSpeed},
+        output::OutputLog,
         templating::{TemplatableString, TemplatableValue},
     },
     loading::loader::Loader,
@@ -23,7 +36,9 @@ use super::{
     discord::{RichPresence, RichPresenceMode},
     path::PathData,
     player::{HistoryEntry, Player},
-    state::{info::UnlockedInfoPages, notes::Notes, variables::Variables},
+    state::{
+        carryover::CarryoverKeys, info::UnlockedInfoPages, notes::Notes, variables::Variables,
+    },
 };
 
 #[derive(Deserialize, Debug)]
@@ -83,6 +98,94 @@ impl Dependencies {
     }
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+/// What to summarize of a player's playthrough after an ending.
+pub enum EndingSummary {
+    /// The player's log entries, which reflect key decisions.
+    Log,
+    /// The responses of the choices the player made, as far back as the history size allows.
+    ///
+    /// Choices without a response, such as input prompts and redirects, are left out.
+    History,
+}
+
+impl EndingSummary {
+    /// Returns the lines of the summary for a player, if there are any.
+    pub fn lines(&self, player: &Player) -> Vec<String> {
+        match self {
+            Self::Log => player.log.clone(),
+            Self::History => player
+                .history
+                .iter()
+                .filter_map(|entry| entry.response.clone())
+                .collect(),
+        }
+    }
+
+    /// Prints the summary for a player as a list, if there is anything to summarize.
+    pub fn print(&self, player: &Player) {
+        let lines = self.lines(player);
+        if lines.is_empty() {
+            return;
+        }
+        let list: Vec<String> = lines.iter().map(|line| format!("- {line}")).collect();
+        OutputLog::print(&format!("\nYour journey:\n{}", list.join("\n")));
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+/// Limits on the volatile player data written to save files.
+///
+/// A compact save keeps all notes, variables, info pages, and other state, but only retains:
+/// - The latest `history` history entries, extended back so that the oldest retained entry is not a redirect
+/// - The latest `log` log entries, extended so that every retained history entry that added a log entry can remove it
+///
+/// Going back works as usual within the retained history entries.
+pub struct CompactSaveSettings {
+    #[serde(alias = "history entries")]
+    pub history: usize,
+    #[serde(alias = "log entries")]
+    pub log: usize,
+}
+
+impl Default for CompactSaveSettings {
+    fn default() -> Self {
+        Self {
+            history: 3,
+            log: 25,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScriptSettings {
+    #[serde(alias = "max instructions")]
+    /// The maximum amount of Lua instructions a single script evaluation may execute before it is aborted.
+    /// If [`None`], scripts may run indefinitely.
+    pub max_instructions: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputSettings {
+    #[serde(alias = "arrow select")]
+    /// Whether players pick responses from a list with the arrow keys instead of typing their numbers.
+    ///
+    /// The list ends with an entry for typing a runtime command, since commands can't be typed into the list.
+    /// Numbered input is still used in accessible mode and when replaying input from a file.
+    pub arrow_select: bool,
+}
+
+impl InputSettings {
+    /// Whether responses are picked with arrow keys, which is never the case in accessible mode.
+    pub fn uses_arrow_select(&self, accessible: bool) -> bool {
+        self.arrow_select && !accessible
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(default, deny_unknown_fields)]
 pub struct HistorySettings {
@@ -90,6 +193,10 @@ pub struct HistorySettings {
     pub locked: bool,
     #[serde(alias = "max size", alias = "max entries")]
     pub size: usize,
+    #[serde(alias = "reprompt input")]
+    /// Whether going back past an input prompt clears the value the player entered.
+    /// If `false`, the value is kept and offered as the default input when the prompt is shown again.
+    pub reprompt_input: bool,
 }
 
 impl Default for HistorySettings {
@@ -97,10 +204,53 @@ impl Default for HistorySettings {
         Self {
             locked: false,
             size: 5,
+            reprompt_input: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSettings {
+    #[serde(alias = "front length", alias = "front width")]
+    /// The maximum display width of a log page front before it is truncated.
+    pub front_len: usize,
+    #[serde(alias = "fit terminal")]
+    /// Whether log page fronts fill the terminal width instead of using `front_len`.
+    pub fit_terminal: bool,
+    /// The suffix appended to a log page front that was truncated.
+    pub ellipsis: String,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            front_len: 25,
+            fit_terminal: false,
+            ellipsis: "...".to_owned(),
         }
     }
 }
 
+impl LogSettings {
+    /// The columns reserved for the log page selector's own numbering and the ellipsis
+    /// when fitting fronts to the terminal.
+    const TERMINAL_MARGIN: usize = 12;
+
+    /// The display width log page fronts are truncated to.
+    ///
+    /// If `fit_terminal` is enabled and the terminal size can be read, this is the terminal width
+    /// minus [`LogSettings::TERMINAL_MARGIN`]. Otherwise, this is `front_len`.
+    pub fn front_width(&self) -> usize {
+        if !self.fit_terminal {
+            return self.front_len;
+        }
+        terminal::size()
+            .map(|(columns, _)| (columns as usize).saturating_sub(Self::TERMINAL_MARGIN))
+            .unwrap_or(self.front_len)
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(default, deny_unknown_fields)]
 pub struct TextSettings {
@@ -108,6 +258,31 @@ pub struct TextSettings {
     pub wait: Option<u64>,
     #[serde(alias = "language")]
     lang: Option<String>,
+    #[serde(alias = "templating depth", alias = "template depth")]
+    pub depth: usize,
+    #[serde(alias = "input prompt")]
+    pub input: Option<String>,
+    #[serde(alias = "accessible mode")]
+    pub accessible_mode: bool,
+    #[serde(alias = "choice footer")]
+    pub choice_footer: Option<TemplatableString>,
+    #[serde(alias = "choices per page")]
+    /// The maximum amount of numbered choices to show at once. If [`None`], all choices are shown.
+    /// Choices keep their numbers across pages, so any choice can be selected from any page.
+    pub choices_per_page: Option<usize>,
+    #[serde(alias = "skippable delays")]
+    /// Whether the player can press a key to skip a choice's `delay`.
+    pub skippable_delays: bool,
+    #[serde(alias = "continue words")]
+    /// Words, besides empty input, that the player can type to proceed past a pause. Matched case-insensitively.
+    pub continue_words: Vec<String>,
+    #[serde(alias = "stagger choices")]
+    /// The milliseconds to wait between printing each choice response, if any.
+    /// The player can press a key to print the remaining choices at once.
+    pub stagger_choices: Option<u64>,
+    #[serde(alias = "selected marker")]
+    /// The marker shown after the response of the active option in a group of `select` choices.
+    pub selected_marker: String,
 }
 
 impl Default for TextSettings {
@@ -116,16 +291,51 @@ impl Default for TextSettings {
             speed: TextSpeed::Delay(TemplatableValue::value(5)),
             wait: None,
             lang: None,
+            depth: 1,
+            input: None,
+            accessible_mode: false,
+            choice_footer: None,
+            choices_per_page: None,
+            skippable_delays: false,
+            continue_words: vec!["continue".to_owned()],
+            stagger_choices: None,
+            selected_marker: "(selected)".to_owned(),
         }
     }
 }
 
 impl TextSettings {
     pub const DEFAULT_LANG: &'static str = "en_us";
+    /// An environment variable that enables accessible mode when set to any value.
+    pub const ACCESSIBLE_ENV: &'static str = "NO_SNAILPRINT";
 
     pub fn lang(&self) -> String {
         self.lang.clone().unwrap_or(Self::DEFAULT_LANG.to_owned())
     }
+
+    /// Whether text should be displayed in a form suitable for screen readers and other accessibility tools.
+    ///
+    /// This is enabled by either the `accessible_mode` setting or the [`TextSettings::ACCESSIBLE_ENV`] variable,
+    /// and takes precedence over any text speeds, including those on individual text objects.
+    pub fn accessible(&self) -> bool {
+        self.accessible_mode || std::env::var_os(Self::ACCESSIBLE_ENV).is_some()
+    }
+
+    /// The amount of pages needed to show some amount of choices according to `choices_per_page`.
+    pub fn choice_pages(&self, choices: usize) -> usize {
+        match self.choices_per_page {
+            Some(per_page) => choices.div_ceil(per_page).max(1),
+            None => 1,
+        }
+    }
+
+    /// The range of choice indices shown on a page, out of some amount of choices.
+    pub fn choice_page_range(&self, page: usize, choices: usize) -> Range<usize> {
+        match self.choices_per_page {
+            Some(per_page) => (page * per_page).min(choices)..((page + 1) * per_page).min(choices),
+            None => 0..choices,
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -150,6 +360,334 @@ impl RichPresenceSettings {
     pub const APP_ID: &'static str = "1086477002770489417";
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChannelSettingsContents {
+    #[serde(alias = "enabled by default")]
+    /// Whether the channel is enabled for new games.
+    /// If templated, this is resolved against the entrypoint state when a new game begins.
+    pub enabled: TemplatableValue<bool>,
+    /// Whether the channel's sounds are decoded when played rather than on startup.
+    pub stream: bool,
+}
+
+impl Default for ChannelSettingsContents {
+    fn default() -> Self {
+        Self {
+            enabled: TemplatableValue::value(true),
+            stream: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// The settings of a single sound channel.
+///
+/// Can be specified as either a boolean or templatable string representing whether the channel is enabled by default,
+/// or a full map.
+///
+/// Channels with `stream` enabled do not preload the sounds in their folder, that is, a `sounds/music` folder
+/// for a `music` channel. These sounds are instead decoded when played and released once they finish,
+/// trading a short delay on playback for a lower memory footprint while they aren't playing. This suits long music tracks.
+///
+/// Despite the name, sounds aren't streamed as they play: each one is still fully decoded before playback starts
+/// and held in memory until it finishes. Sounds inside an archive also keep their encoded content in memory throughout.
+pub struct ChannelSettings {
+    pub settings: ChannelSettingsContents,
+}
+
+struct ChannelSettingsVisitor;
+
+impl<'de> Visitor<'de> for ChannelSettingsVisitor {
+    type Value = ChannelSettingsContents;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("boolean, string, or map")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(ChannelSettingsContents {
+            enabled: TemplatableValue::value(v),
+            ..Default::default()
+        })
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(ChannelSettingsContents {
+            enabled: TemplatableValue::try_from(v.to_owned()).map_err(E::custom)?,
+            ..Default::default()
+        })
+    }
+
+    fn visit_map<A>(self, map: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        Deserialize::deserialize(MapAccessDeserializer::new(map))
+    }
+}
+
+impl<'de> Deserialize<'de> for ChannelSettings {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self {
+            settings: deserializer.deserialize_any(ChannelSettingsVisitor)?,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TagStyleContents {
+    pub color: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+#[derive(Debug)]
+/// The style to display a choice tag with.
+///
+/// Can be specified as either a color name or a full map.
+pub struct TagStyle {
+    pub style: TagStyleContents,
+}
+
+impl TagStyle {
+    /// Parses the style's color name, if any.
+    fn color(&self) -> Result<Option<Color>> {
+        self.style
+            .color
+            .as_ref()
+            .map(|color| {
+                Color::try_from(color.as_str()).map_err(|_| anyhow!("Invalid color '{color}'"))
+            })
+            .invert()
+    }
+
+    /// Styles some tag content based on this style's attributes.
+    pub fn apply(&self, content: &str) -> Result<String> {
+        let mut styled = content.stylize();
+        if let Some(color) = self.color()? {
+            styled = styled.with(color);
+        }
+        if self.style.bold {
+            styled = styled.bold();
+        }
+        if self.style.italic {
+            styled = styled.italic();
+        }
+        if self.style.underline {
+            styled = styled.underlined();
+        }
+        Ok(styled.to_string())
+    }
+}
+
+struct TagStyleVisitor;
+
+impl<'de> Visitor<'de> for TagStyleVisitor {
+    type Value = TagStyleContents;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("string or map")
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(TagStyleContents {
+            color: Some(v.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    fn visit_map<A>(self, map: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        Deserialize::deserialize(MapAccessDeserializer::new(map))
+    }
+}
+
+impl<'de> Deserialize<'de> for TagStyle {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self {
+            style: deserializer.deserialize_any(TagStyleVisitor)?,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+/// Lowers the volume of audio channels while text is printed, such as music during dialogue.
+///
+/// The channels' prior volumes are restored in the background once a line finishes printing and waiting.
+/// Consecutive ducking lines keep the channels lowered until the last of them is done.
+pub struct DuckSettings {
+    /// The channels to lower the volume of.
+    pub channels: Vec<String>,
+    /// The volume the channels are lowered to, from `0.0` to `1.0`.
+    pub volume: f32,
+    #[serde(alias = "restore time")]
+    /// How long, in milliseconds, to gradually raise the channels back to their prior volume.
+    pub restore: u64,
+    #[serde(alias = "by default")]
+    /// Whether text lines duck the channels unless they disable it with their own `duck` key.
+    pub by_default: bool,
+}
+
+impl Default for DuckSettings {
+    fn default() -> Self {
+        Self {
+            channels: Vec::new(),
+            volume: 0.3,
+            restore: 0,
+            by_default: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutoSaveSettingsContents {
+    pub enabled: bool,
+    /// The save file that autosaves are written to.
+    /// If it lacks a save file extension, the extension of `settings.save_format` is added.
+    pub file: String,
+    /// How many previous autosaves to keep as numbered backups.
+    pub backups: usize,
+}
+
+impl Default for AutoSaveSettingsContents {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            file: "autosave".to_owned(),
+            backups: 0,
+        }
+    }
+}
+
+impl AutoSaveSettingsContents {
+    /// The name of the autosave file without its save file extension.
+    pub fn name(&self) -> &str {
+        match SaveFormat::detect(&self.file) {
+            Some(format) => format.strip(&self.file),
+            None => &self.file,
+        }
+    }
+
+    /// The format of the autosave file, falling back to the format of new save files.
+    pub fn format(&self, default: SaveFormat) -> SaveFormat {
+        SaveFormat::detect(&self.file).unwrap_or(default)
+    }
+}
+
+#[derive(Debug)]
+/// Whether and how the player is saved in the background after every choice.
+///
+/// Can be specified as either a boolean representing whether autosaving is enabled, or a full map.
+///
+/// Autosaves are written to their own `file` so that they never overwrite the player's own save.
+/// With `backups` enabled, each autosave first moves the previous ones back a number,
+/// such that `autosave.1.yml` is the most recent backup and the oldest beyond `autosave.<backups>.yml` is deleted.
+pub struct AutoSaveSettings {
+    pub settings: AutoSaveSettingsContents,
+}
+
+struct AutoSaveSettingsVisitor;
+
+impl<'de> Visitor<'de> for AutoSaveSettingsVisitor {
+    type Value = AutoSaveSettingsContents;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("boolean or map")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(AutoSaveSettingsContents {
+            enabled: v,
+            ..Default::default()
+        })
+    }
+
+    fn visit_map<A>(self, map: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        Deserialize::deserialize(MapAccessDeserializer::new(map))
+    }
+}
+
+impl<'de> Deserialize<'de> for AutoSaveSettings {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self {
+            settings: deserializer.deserialize_any(AutoSaveSettingsVisitor)?,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+/// The format new save files are written in.
+///
+/// Existing save files keep the format of their extension, so changing this only affects saves created afterward.
+pub enum SaveFormat {
+    /// Plain YAML with the `.yml` extension.
+    #[default]
+    Yaml,
+    /// Gzip-compressed YAML with the `.yml.gz` extension.
+    YamlGz,
+    /// Zstandard-compressed YAML with the `.yml.zst` extension.
+    YamlZstd,
+}
+
+impl SaveFormat {
+    /// Every format, ordered such that longer extensions are checked first.
+    const ALL: [SaveFormat; 3] = [Self::YamlGz, Self::YamlZstd, Self::Yaml];
+
+    /// The file extension of save files in this format, without the leading dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Yaml => "yml",
+            Self::YamlGz => "yml.gz",
+            Self::YamlZstd => "yml.zst",
+        }
+    }
+
+    /// Detects the format of a save file from its name, if it has a save file extension.
+    pub fn detect(file: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| file.ends_with(&format!(".{}", format.extension())))
+    }
+
+    /// Removes this format's extension from a save file name.
+    pub fn strip<'a>(&self, file: &'a str) -> &'a str {
+        file.strip_suffix(&format!(".{}", self.extension()))
+            .unwrap_or(file)
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(default, deny_unknown_fields)]
 pub struct Settings {
@@ -157,9 +695,67 @@ pub struct Settings {
     pub save: bool,
     #[serde(alias = "developer mode")]
     pub debug: bool,
+    #[serde(alias = "debug password")]
+    /// A password that players can enter with the `.debug` command to enable debug commands for the session.
+    pub debug_password: Option<String>,
+    #[serde(alias = "random seed")]
+    pub seed: Option<u64>,
+    #[serde(alias = "auto save", alias = "autosave")]
+    /// Whether to save the player in the background after every choice, and where.
+    pub auto_save: AutoSaveSettings,
+    #[serde(alias = "save obfuscation", alias = "obfuscate saves")]
+    pub save_obfuscation: bool,
+    #[serde(alias = "save format")]
+    pub save_format: SaveFormat,
+    #[serde(alias = "compact saves")]
+    /// Whether to trim volatile player data when writing saves, and by how much.
+    pub compact_saves: Option<CompactSaveSettings>,
+    #[serde(alias = "dead end ending", alias = "dead end")]
+    pub dead_end_ending: Option<TextLines>,
+    #[serde(alias = "script spinner")]
+    pub script_spinner: bool,
+    #[serde(alias = "validate templates")]
+    /// Whether to check that scripts referenced by choice `log` and `drp` templates exist when loading.
+    /// Opt-in since parentheses in plain text are indistinguishable from script references.
+    pub validate_templates: bool,
+    #[serde(alias = "difficulty levels")]
+    /// The difficulty levels a player selects from when starting a new game, easiest first.
+    pub difficulties: Option<Vec<String>>,
+    #[serde(alias = "minimum terminal size", alias = "min terminal size")]
+    /// The minimum terminal size required to play, as `[columns, rows]`.
+    pub min_terminal: Option<(u16, u16)>,
+    #[serde(alias = "zero division", alias = "division by zero")]
+    /// The value a variable is set to when a `div` operation divides by zero.
+    /// If [`None`], dividing by zero is an error.
+    pub zero_division: Option<String>,
+    #[serde(alias = "ending summary")]
+    /// What to summarize of the playthrough after an ending is printed, if anything.
+    pub ending_summary: Option<EndingSummary>,
+    #[serde(alias = "resume recap")]
+    /// Whether to reprint the current prompt's text when resuming a save,
+    /// even if the choice that led to it hid the text.
+    pub resume_recap: bool,
+    #[serde(alias = "show tips")]
+    /// Whether to automatically show a tip from `entry.tips` at startup and after endings.
+    pub tips: bool,
+    #[serde(alias = "allow peek")]
+    /// Whether players may preview where a choice leads with the `.peek` command.
+    /// Always allowed when debug commands are enabled.
+    pub peek: bool,
     #[serde(alias = "sound channels", alias = "audio")]
-    pub channels: Option<HashMap<String, bool>>,
+    pub channels: Option<HashMap<String, ChannelSettings>>,
+    #[serde(alias = "master volume")]
+    /// The master volume that all channel volumes are scaled by.
+    pub volume: f32,
+    #[serde(alias = "audio ducking")]
+    /// Which channels to duck while text is printed, if any.
+    pub duck: Option<DuckSettings>,
+    #[serde(alias = "tag styles")]
+    pub tags: HashMap<String, TagStyle>,
     pub history: HistorySettings,
+    pub log: LogSettings,
+    pub scripts: ScriptSettings,
+    pub input: InputSettings,
     pub text: TextSettings,
     #[serde(alias = "discord rich presence")]
     drp: RichPresenceSettings,
@@ -170,8 +766,35 @@ impl Default for Settings {
         Self {
             save: true,
             debug: false,
+            debug_password: None,
+            seed: None,
+            auto_save: AutoSaveSettings {
+                settings: AutoSaveSettingsContents {
+                    enabled: false,
+                    ..Default::default()
+                },
+            },
+            save_obfuscation: false,
+            save_format: SaveFormat::default(),
+            compact_saves: None,
+            dead_end_ending: None,
+            script_spinner: false,
+            validate_templates: false,
+            difficulties: None,
+            min_terminal: None,
+            zero_division: None,
+            ending_summary: None,
+            resume_recap: false,
+            tips: true,
+            peek: false,
             channels: None,
+            volume: 1.0,
+            duck: None,
+            tags: HashMap::new(),
             history: HistorySettings::default(),
+            log: LogSettings::default(),
+            scripts: ScriptSettings::default(),
+            input: InputSettings::default(),
             text: TextSettings::default(),
             drp: RichPresenceSettings::default(),
         }
@@ -179,12 +802,114 @@ impl Default for Settings {
 }
 
 impl Settings {
-    pub fn enabled_audio_channels(&self) -> HashSet<String> {
+    /// If a minimum terminal size is set and the terminal is smaller, asks the player to resize it.
+    ///
+    /// Blocks until the terminal is resized to fit or the player presses enter to continue anyway.
+    pub fn await_terminal_size(&self) -> Result<()> {
+        let (min_columns, min_rows) = match self.min_terminal {
+            Some(min) => min,
+            None => return Ok(()),
+        };
+        let fits = |(columns, rows): (u16, u16)| columns >= min_columns && rows >= min_rows;
+        let (columns, rows) = terminal::size()?;
+        if fits((columns, rows)) {
+            return Ok(());
+        }
+        println!(
+            "This game needs a terminal of at least {min_columns}x{min_rows}, but yours is {columns}x{rows}."
+        );
+        println!("Please resize your terminal, or press enter to continue anyway.\n");
+        terminal::enable_raw_mode()?;
+        let result = loop {
+            match event::read() {
+                Ok(Event::Resize(columns, rows)) if fits((columns, rows)) => break Ok(()),
+                Ok(Event::Key(key)) if key.code == KeyCode::Enter => break Ok(()),
+                Ok(_) => continue,
+                Err(err) => break Err(err),
+            }
+        };
+        terminal::disable_raw_mode()?;
+        Ok(result?)
+    }
+
+    /// The difficulty a player starts with without selecting one: the first declared level, if any.
+    pub fn default_difficulty(&self) -> Option<String> {
+        self.difficulties
+            .as_ref()
+            .and_then(|levels| levels.first().cloned())
+    }
+
+    /// Asks the player which difficulty to play at if there are multiple declared levels.
+    /// Otherwise, returns the [default difficulty](Settings::default_difficulty).
+    pub fn select_difficulty(&self) -> Result<Option<String>> {
+        let levels = match &self.difficulties {
+            Some(levels) if levels.len() > 1 => levels,
+            _ => return Ok(self.default_difficulty()),
+        };
+        let prompt = requestty::Question::select("Choose a difficulty")
+            .choices(levels)
+            .build();
+        let choice = requestty::prompt_one(prompt)?.as_list_item().unwrap().index;
+        println!();
+        Ok(Some(levels[choice].clone()))
+    }
+
+    /// Validates that a player's difficulty is one of the declared levels, if any are declared.
+    pub fn validate_difficulty(&self, difficulty: Option<&String>) -> Result<()> {
+        if let Some(levels) = &self.difficulties {
+            match difficulty {
+                Some(difficulty) if levels.contains(difficulty) => {}
+                Some(difficulty) => {
+                    return Err(anyhow!(
+                        "Difficulty '{difficulty}' is not declared in `settings.difficulties`"
+                    ))
+                }
+                None => return Err(anyhow!("No difficulty is selected")),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether any audio channels are declared, regardless of whether they are enabled.
+    pub fn has_audio_channels(&self) -> bool {
+        self.channels
+            .as_ref()
+            .map(|map| !map.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// The audio channels that are enabled by default without templating.
+    ///
+    /// Channels with templated `enabled` values are resolved later with [`Settings::enabled_audio_channels`].
+    pub fn static_audio_channels(&self) -> HashSet<String> {
+        self.channels
+            .as_ref()
+            .map(|map| {
+                map.iter()
+                    .filter(|(_, channel)| channel.settings.enabled.value == Some(true))
+                    .map(|(key, _)| key.clone())
+                    .collect()
+            })
+            .unwrap_or(HashSet::new())
+    }
+
+    /// The audio channels that are enabled by default, filling any templated `enabled` values.
+    pub fn enabled_audio_channels(&self, text_context: &TextContext) -> Result<HashSet<String>> {
+        let mut result = HashSet::new();
+        for (key, channel) in self.channels.iter().flatten() {
+            if channel.settings.enabled.get_value(text_context)? {
+                result.insert(key.clone());
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn streamed_audio_channels(&self) -> HashSet<String> {
         self.channels
             .as_ref()
             .map(|map| {
                 map.iter()
-                    .filter(|(_, &enabled)| enabled)
+                    .filter(|(_, channel)| channel.settings.stream)
                     .map(|(key, _)| key.clone())
                     .collect()
             })
@@ -204,18 +929,63 @@ impl Into<SoundAction> for EntrypointSoundAction {
     fn into(self) -> SoundAction {
         SoundAction {
             name: Some(self.name.into()),
-            channel: self.channel.into(),
+            channel: Some(self.channel.into()),
             mode: TemplatableValue::value(SoundActionMode::default()),
             seek: self.seek.map(TemplatableValue::value),
             speed: self.speed.map(TemplatableValue::value),
+            volume: None,
+            fade_in: None,
+            fade_out: None,
         }
     }
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+/// A credits sequence that ending choices with `credits` enabled roll after their ending text.
+///
+/// The lines are printed one after another with a pause in between, which the player can skip with a keypress.
+pub struct Credits {
+    /// The credits lines, printed in the same way as prompt text.
+    pub lines: TextLines,
+    #[serde(default = "Credits::default_pace")]
+    /// How long, in milliseconds, to pause after each line.
+    pub pace: u64,
+}
+
+impl Credits {
+    fn default_pace() -> u64 {
+        1000
+    }
+
+    /// Rolls the credits lines.
+    ///
+    /// Pauses are skipped when text is printed instantly or in accessible mode.
+    /// If the player presses a key during a pause, the remaining lines are printed without pausing.
+    pub fn roll(&self, player: &Player, text_context: &TextContext) -> Result<()> {
+        let mut paced = !text_context.accessible() && player.speed != TextSpeed::INSTANT;
+        for (newline, line) in Text::get_separated_lines(&self.lines, text_context)? {
+            if newline {
+                OutputLog::print("");
+            }
+            line.print(player, text_context)?;
+            if paced && Text::sleep(self.pace, true)? {
+                paced = false;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct Entrypoint {
-    pub path: PathData,
+    /// The unnamed prompt path new games start at.
+    pub path: Option<PathData>,
+    /// Named prompt paths new games can start at, such as episodes of an anthology.
+    /// If there is more than one, the player selects which to start at when beginning a new game.
+    /// Continuing a save always resumes its own history instead.
+    pub paths: Option<HashMap<String, PathData>>,
     pub background: Option<TextLines>,
     pub notes: Option<Notes>,
     pub variables: Option<Variables>,
@@ -223,9 +993,122 @@ pub struct Entrypoint {
     pub info_pages: Option<UnlockedInfoPages>,
     pub log: Option<Vec<String>>,
     sounds: Option<Vec<EntrypointSoundAction>>,
+    /// A pool of tips or quotes, one of which is randomly shown at startup, after endings, and with the `.tip` command.
+    pub tips: Option<Vec<TemplatableString>>,
+    /// A credits sequence that ending choices can roll with `credits: true`.
+    pub credits: Option<Credits>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+/// A file in the `init` directory containing initial player state to merge into the [`Entrypoint`].
+pub struct InitFile {
+    notes: Option<Notes>,
+    variables: Option<Variables>,
+    #[serde(default, rename = "override")]
+    /// Whether this file's variables may replace values set by the entrypoint or earlier files.
+    overrides: bool,
 }
 
 impl Entrypoint {
+    pub const INIT_DIR: &'static str = "init";
+
+    /// Merges [`InitFile`]s into the entrypoint's initial notes and variables.
+    ///
+    /// Files are merged in order of their path after the entrypoint's own state, so later files win.
+    /// A variable that is set to a different value than before is an error unless its file specifies `override: true`.
+    fn merge_init(&mut self, files: BTreeMap<String, InitFile>) -> Result<()> {
+        for (name, file) in files {
+            if let Some(notes) = file.notes {
+                self.notes.get_or_insert_with(HashSet::new).extend(notes);
+            }
+            if let Some(vars) = file.variables {
+                let variables = self.variables.get_or_insert_with(HashMap::new);
+                for (var, value) in vars {
+                    let conflicts = variables
+                        .get(&var)
+                        .map(|previous| previous != &value)
+                        .unwrap_or(false);
+                    if conflicts && !file.overrides {
+                        return Err(anyhow!(
+                            "Variable '{var}' in init file '{name}' conflicts with an earlier value; set `override: true` to replace it"
+                        ));
+                    }
+                    variables.insert(var, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the named entrypoints ordered by name.
+    fn named_paths(&self) -> Vec<(&String, &PathData)> {
+        let mut named: Vec<(&String, &PathData)> = self
+            .paths
+            .as_ref()
+            .map(|paths| paths.iter().collect())
+            .unwrap_or(Vec::new());
+        named.sort_by(|(a, _), (b, _)| a.cmp(b));
+        named
+    }
+
+    /// Returns all entrypoint paths along with their names, if any.
+    pub fn all_paths(&self) -> Vec<(Option<&String>, &PathData)> {
+        let unnamed = self.path.iter().map(|path| (None, path));
+        let named = self
+            .named_paths()
+            .into_iter()
+            .map(|(name, path)| (Some(name), path));
+        unnamed.chain(named).collect()
+    }
+
+    /// Returns the path a new game starts at without player selection:
+    /// the unnamed `path` if present, otherwise the first named path.
+    pub fn default_path(&self) -> Result<&PathData> {
+        self.all_paths()
+            .first()
+            .map(|(_, path)| *path)
+            .ok_or(anyhow!("Entrypoint has no `path` or `paths`"))
+    }
+
+    /// Determines the path a new game starts at.
+    ///
+    /// If there is more than one named entrypoint, asks the player to select one.
+    /// Otherwise, returns the [default path](Entrypoint::default_path).
+    pub fn select_path(&self) -> Result<PathData> {
+        let named = self.named_paths();
+        if named.len() <= 1 {
+            return Ok(self.default_path()?.clone());
+        }
+        let names: Vec<&String> = named.iter().map(|(name, _)| *name).collect();
+        let prompt = requestty::Question::select("Choose where to begin")
+            .choices(names)
+            .build();
+        let choice = requestty::prompt_one(prompt)?.as_list_item().unwrap().index;
+        println!();
+        Ok(named[choice].1.clone())
+    }
+
+    /// Picks a random tip from the `tips` pool using the game's seeded random number generator, if any exist.
+    pub fn random_tip(&self, text_context: &TextContext) -> Result<Option<String>> {
+        let tip = self
+            .tips
+            .as_ref()
+            .and_then(|tips| text_context.resources().random(|rng| tips.choose(rng)));
+        tip.map(|tip| tip.fill(text_context)).invert()
+    }
+
+    /// Prints a [random tip](Entrypoint::random_tip) followed by an empty line if tips are enabled by `settings.tips`.
+    pub fn print_tip(&self, text_context: &TextContext) -> Result<()> {
+        if !text_context.config().settings.tips {
+            return Ok(());
+        }
+        if let Some(tip) = self.random_tip(text_context)? {
+            OutputLog::print(&format!("{tip}\n"));
+        }
+        Ok(())
+    }
+
     pub fn submit_sounds(
         &self,
         player: &Player,
@@ -250,13 +1133,86 @@ pub struct Manifest {
     pub settings: Settings,
     #[serde(alias = "entrypoint")]
     pub entry: Entrypoint,
+    #[serde(default)]
+    pub commands: HashMap<String, String>,
+    #[serde(default, alias = "new game plus")]
+    pub carryover: CarryoverKeys,
 }
 
 impl Manifest {
     pub const FILE: &'static str = "nage.yml";
+    /// The directory containing manifest fragments to merge into the [`Manifest::FILE`].
+    pub const FRAGMENTS_DIR: &'static str = "nage.d";
+    /// The fragment key that allows a fragment to replace values set by the manifest or earlier fragments.
+    const OVERRIDE_KEY: &'static str = "override";
+
+    /// Deeply merges a manifest fragment into a base value.
+    ///
+    /// Maps are merged key by key. Any other value that differs from the base value is a conflict,
+    /// which is an error unless `overrides` is `true`, in which case the fragment's value replaces it.
+    fn merge_fragment(base: &mut Value, fragment: Value, overrides: bool, key: &str) -> Result<()> {
+        match (base, fragment) {
+            (Value::Mapping(base), Value::Mapping(fragment)) => {
+                for (name, value) in fragment {
+                    let child_key = match name.as_str() {
+                        Some(name) if key.is_empty() => name.to_owned(),
+                        Some(name) => format!("{key}.{name}"),
+                        None => key.to_owned(),
+                    };
+                    match base.get_mut(&name) {
+                        Some(existing) => {
+                            Self::merge_fragment(existing, value, overrides, &child_key)?
+                        }
+                        None => {
+                            base.insert(name, value);
+                        }
+                    }
+                }
+            }
+            (base, fragment) => {
+                if *base != fragment && !overrides {
+                    return Err(anyhow!(
+                        "`{key}` conflicts with an earlier value; set `override: true` to replace it"
+                    ));
+                }
+                *base = fragment;
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the manifest value merged with all fragments in the [`Manifest::FRAGMENTS_DIR`], if any.
+    ///
+    /// Fragments are merged in order of their path after the manifest itself, so later fragments take precedence
+    /// when they specify `override: true`. The merged result is deserialized as a whole, so unknown keys are still rejected.
+    fn load_merged(loader: &Loader) -> Result<Self> {
+        let fragments = loader
+            .map_content(Self::FRAGMENTS_DIR, |local| loader.load::<_, Value>(local, false))?;
+        if fragments.is_empty() {
+            return loader.load(Self::FILE, true);
+        }
+        let mut merged: Value = loader.load(Self::FILE, true)?;
+        for (name, mut fragment) in fragments {
+            let overrides = match &mut fragment {
+                Value::Mapping(map) => map
+                    .remove(&Value::from(Self::OVERRIDE_KEY))
+                    .and_then(|value| value.as_bool())
+                    .unwrap_or(false),
+                _ => return Err(anyhow!("Manifest fragment '{name}' must be a map")),
+            };
+            Self::merge_fragment(&mut merged, fragment, overrides, "")
+                .with_context(|| format!("Failed to merge manifest fragment '{name}'"))?;
+        }
+        serde_yaml::from_value(merged).with_context(|| "Failed to parse merged manifest")
+    }
 
     pub fn load(loader: &Loader) -> Result<Self> {
-        let config: Self = loader.load(Self::FILE, true)?;
+        let mut config = Self::load_merged(loader)?;
+        let init = loader.map_content(Entrypoint::INIT_DIR, |local| loader.load(local, false))?;
+        config
+            .entry
+            .merge_init(init)
+            .with_context(|| "Failed to merge initial state")?;
         config
             .validate()
             .with_context(|| "Failed to validate manifest")?;
@@ -267,6 +1223,65 @@ impl Manifest {
         if self.settings.history.size == 0 {
             return Err(anyhow!("`settings.history.size` must be non-zero"));
         }
+        if self.settings.text.depth == 0 {
+            return Err(anyhow!("`settings.text.depth` must be non-zero"));
+        }
+        let autosave = &self.settings.auto_save.settings;
+        if autosave.name().is_empty() || !sanitize_filename::is_sanitized(&autosave.file) {
+            return Err(anyhow!(
+                "`settings.auto_save.file` must be a valid file name, but is '{}'",
+                autosave.file
+            ));
+        }
+        if self.settings.scripts.max_instructions == Some(0) {
+            return Err(anyhow!("`settings.scripts.max_instructions` must be non-zero"));
+        }
+        if self.settings.text.choices_per_page == Some(0) {
+            return Err(anyhow!("`settings.text.choices_per_page` must be non-zero"));
+        }
+        if let Some(levels) = &self.settings.difficulties {
+            if levels.is_empty() {
+                return Err(anyhow!("`settings.difficulties` must not be empty"));
+            }
+        }
+        self.entry.default_path()?;
+        for (tag, style) in &self.settings.tags {
+            style
+                .color()
+                .with_context(|| format!("Failed to validate style for tag '{tag}'"))?;
+        }
+        let entry_sounds = self
+            .entry
+            .sounds
+            .as_ref()
+            .map(|s| !s.is_empty())
+            .unwrap_or(false);
+        if entry_sounds && !self.settings.has_audio_channels() {
+            return Err(anyhow!(
+                "`entry.sounds` is present, but no channels are declared in `settings.channels`"
+            ));
+        }
+        if self.settings.volume < 0.0 {
+            return Err(anyhow!("`settings.volume` must not be negative"));
+        }
+        if let Some(duck) = &self.settings.duck {
+            if !(0.0..=1.0).contains(&duck.volume) {
+                return Err(anyhow!("`settings.duck.volume` must be between 0 and 1"));
+            }
+            for channel in &duck.channels {
+                let declared = self
+                    .settings
+                    .channels
+                    .as_ref()
+                    .map(|channels| channels.contains_key(channel))
+                    .unwrap_or(false);
+                if !declared {
+                    return Err(anyhow!(
+                        "`settings.duck` channel '{channel}' is not declared in `settings.channels`"
+                    ));
+                }
+            }
+        }
         let nage_version = Version::from_str(NAGE_VERSION)?;
         self.dependencies.check(nage_version)?;
         Ok(())
@@ -1,6 +1,6 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use result::OptionResultExt;
 use serde::{
     de::{value::MapAccessDeserializer, Visitor},
@@ -9,7 +9,10 @@ use serde::{
 
 use crate::core::text::templating::TemplatableString;
 
-use super::context::TextContext;
+use super::{
+    choice::Choice,
+    context::{StaticContext, TextContext},
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PathContents {
@@ -121,7 +124,8 @@ impl Path {
         Some(result.to_owned())
     }
 
-    fn static_data(&self, current_file: &str) -> Option<PathData> {
+    /// Returns the full path this path points to if it **is not templatable**.
+    pub fn static_data(&self, current_file: &str) -> Option<PathData> {
         self.static_file(&current_file).and_then(|file| {
             let path = PathData {
                 file,
@@ -139,7 +143,45 @@ impl Path {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+/// A jump target selected by matching a value against a table of cases.
+///
+/// The `on` template is filled when the choice is made; the path of the case with that exact value is used,
+/// or the `default` path if no case matches.
+pub struct MatchJump {
+    /// The value to match, such as a variable template.
+    pub on: TemplatableString,
+    /// Values mapped to the path to jump to when `on` fills to them.
+    pub cases: HashMap<String, Path>,
+    /// The path to jump to when no case matches.
+    pub default: Path,
+}
+
+impl MatchJump {
+    /// Fills the `on` template and returns the matching case's path, or the `default` path.
+    pub fn resolve(&self, text_context: &TextContext) -> Result<&Path> {
+        let value = self.on.fill(text_context)?;
+        Ok(self.cases.get(&value).unwrap_or(&self.default))
+    }
+
+    /// Returns every path this jump may resolve to, starting with the `default` path.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        std::iter::once(&self.default).chain(self.cases.values())
+    }
+
+    /// Validates that each case path and the `default` path point to a valid prompt if they **are not templatable**.
+    pub fn validate(&self, local_file: &str, stc: &StaticContext) -> Result<()> {
+        for (value, path) in &self.cases {
+            Choice::validate_path(path, local_file, stc)
+                .with_context(|| format!("Case '{value}' points to invalid prompt"))?;
+        }
+        Choice::validate_path(&self.default, local_file, stc)
+            .with_context(|| "`default` section points to invalid prompt")
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PathData {
     pub file: String,
     pub prompt: String,
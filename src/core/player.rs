@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
     vec,
 };
 
@@ -8,19 +8,26 @@ use result::OptionResultExt;
 use serde::{Deserialize, Serialize};
 use unicode_truncate::UnicodeTruncateStr;
 
-use crate::text_context;
+use crate::{loading::saves::SaveManager, text_context};
 
 use super::{
-    choice::Choice,
+    choice::{Choice, SessionChoices, UsableChoices},
     context::{StaticContext, TextContext},
     discord::RichPresence,
-    manifest::Manifest,
+    manifest::{CompactSaveSettings, LogSettings, Manifest},
     path::PathData,
-    prompt::PromptModel,
+    prompt::{Prompt, PromptModel},
+    scripts::ScriptDirective,
     state::{
+        carryover::Carryover,
         info::UnlockedInfoPages,
-        notes::{NoteEntries, Notes},
-        variables::{NamedVariableEntry, VariableEntries, Variables},
+        notes::{NoteEntries, NoteEntry, Notes},
+        schedule::ScheduledEvents,
+        variables::{NamedVariableEntry, VariableEntries, VariableEntry, Variables},
+    },
+    text::{
+        display::{Text, TextSpeed},
+        output::OutputLog,
     },
 };
 
@@ -41,6 +48,15 @@ pub struct HistoryEntry {
     pub variables: Option<VariableEntries>,
     /// Whether a log entry was gained during this entry.
     pub log: bool,
+    #[serde(default)]
+    /// The name of the variable the player gave input for during this entry, if any.
+    pub input: Option<String>,
+    #[serde(default)]
+    /// The scheduled events prior to this entry, if it modified them.
+    pub scheduled: Option<ScheduledEvents>,
+    #[serde(default)]
+    /// The filled response of the choice that led to this entry, if it had one.
+    pub response: Option<String>,
 }
 
 impl HistoryEntry {
@@ -54,6 +70,9 @@ impl HistoryEntry {
             notes: None,
             variables: None,
             log: false,
+            input: None,
+            scheduled: None,
+            response: None,
         }
     }
 }
@@ -65,6 +84,9 @@ pub struct Player {
     pub began: bool,
     /// The player's display language.
     pub lang: String,
+    #[serde(default = "Player::default_speed")]
+    /// The player's text speed multiplier, applied on top of any configured [`TextSpeed`].
+    pub speed: f32,
     /// The player's enabled sound channels.
     pub channels: HashSet<String>,
     /// The player's current notes.
@@ -77,24 +99,86 @@ pub struct Player {
     pub log: Vec<String>,
     /// Recordings of each prompt jump and their associated value changes.
     pub history: VecDeque<HistoryEntry>,
+    #[serde(default)]
+    /// The difficulty level selected when the game was started, if the game declares any.
+    pub difficulty: Option<String>,
+    #[serde(default)]
+    /// The player's pending forced jumps.
+    pub scheduled: ScheduledEvents,
+    #[serde(skip)]
+    /// The `session_once` choices used since the game was started. Never saved.
+    pub session: SessionChoices,
+    #[serde(skip)]
+    /// Whether debug commands were unlocked with the `.debug` command for this session. Never saved.
+    pub debug_unlocked: bool,
+    #[serde(default)]
+    /// The amount of times each choice has been selected, keyed by [`Choice::key`].
+    /// Reversing a choice does not reduce its count.
+    pub choice_uses: HashMap<String, u32>,
+    #[serde(default)]
+    /// The total amount of choices made over the playthrough.
+    /// Like `choice_uses`, reversing a choice does not reduce it.
+    pub total_choices: usize,
+    #[serde(skip)]
+    /// The IDs of the endings reached in any playthrough, read from the game's profile at startup. Never saved.
+    pub endings_reached: BTreeSet<String>,
+    #[serde(skip)]
+    /// Whether this player was constructed with [`Player::preview`] and must never be saved.
+    pub preview: bool,
 }
 
 impl Player {
-    /// Constructs a player based on a [`Manifest`].
-    pub fn new(config: &Manifest) -> Self {
-        let entry = HistoryEntry::new(&config.entry.path);
+    /// Constructs a player based on a [`Manifest`] that begins at the given entrypoint path.
+    pub fn new(config: &Manifest, path: &PathData) -> Self {
+        let entry = HistoryEntry::new(path);
         Self {
             began: false,
             lang: config.settings.text.lang(),
-            channels: config.settings.enabled_audio_channels(),
+            speed: Self::default_speed(),
+            channels: config.settings.static_audio_channels(),
             notes: config.entry.notes.clone().unwrap_or(HashSet::new()),
             variables: config.entry.variables.clone().unwrap_or(HashMap::new()),
             info_pages: config.entry.info_pages.clone().unwrap_or(Vec::new()),
             log: config.entry.log.clone().unwrap_or(Vec::new()),
             history: VecDeque::from(vec![entry]),
+            difficulty: config.settings.default_difficulty(),
+            scheduled: Vec::new(),
+            session: HashSet::new(),
+            debug_unlocked: false,
+            choice_uses: HashMap::new(),
+            total_choices: 0,
+            endings_reached: BTreeSet::new(),
+            preview: false,
+        }
+    }
+
+    /// Constructs a read-only player positioned at an arbitrary prompt with the given state.
+    ///
+    /// Unlike [`Player::new`], no entrypoint notes, variables, or info pages are applied;
+    /// the player holds only the supplied state so that a prompt can be rendered under it.
+    /// Preview players are never written by [`SaveManager`](crate::loading::saves::SaveManager).
+    pub fn preview(
+        config: &Manifest,
+        path: &PathData,
+        notes: Notes,
+        variables: Variables,
+        info_pages: UnlockedInfoPages,
+    ) -> Self {
+        Self {
+            began: true,
+            notes,
+            variables,
+            info_pages,
+            log: Vec::new(),
+            preview: true,
+            ..Self::new(config, path)
         }
     }
 
+    fn default_speed() -> f32 {
+        1.0
+    }
+
     /// Accepts a single [`NoteApplication`].
     ///
     /// If `take` is `true`, attempts to remove the note.
@@ -109,6 +193,42 @@ impl Player {
         Ok(())
     }
 
+    /// Seeds this player's state with the [`Carryover`] of a finished playthrough.
+    pub fn carry_over(&mut self, carryover: Carryover) {
+        self.notes.extend(carryover.notes);
+        self.variables.extend(carryover.variables);
+    }
+
+    /// Applies the state changes of a [`ScriptDirective`] through [`Player::debug_edit`]
+    /// so that they can be reversed with [`Player::back`].
+    ///
+    /// Notes that the player already has, or lacks, as requested are left out of the recorded entry.
+    pub fn apply_directive(
+        &mut self,
+        directive: &ScriptDirective,
+        stc: &StaticContext,
+    ) -> Result<()> {
+        let notes: NoteEntries = directive
+            .notes
+            .iter()
+            .filter(|(note, give)| self.notes.contains(*note) != **give)
+            .map(|(note, give)| NoteEntry::new(note.clone(), !give))
+            .collect();
+        let variables: VariableEntries = directive
+            .variables
+            .iter()
+            .map(|(name, value)| {
+                (name.clone(), VariableEntry::new(name, value.clone(), &self.variables))
+            })
+            .collect();
+        if notes.is_empty() && variables.is_empty() {
+            return Ok(());
+        }
+        let notes = (!notes.is_empty()).then_some(notes);
+        let variables = (!variables.is_empty()).then_some(variables);
+        self.debug_edit(notes, variables, stc)
+    }
+
     /// Returns the latest history entry, if any.
     pub fn latest_entry(&self) -> Result<&HistoryEntry> {
         self.history.back().ok_or(anyhow!("History empty"))
@@ -124,7 +244,10 @@ impl Player {
     }
 
     /// Pops the latest [`HistoryEntry`] off the stack using [`Player::pop_latest_entry`] and reverses its effects.
-    pub fn back(&mut self) -> Result<()> {
+    ///
+    /// If `reprompt_input` is `false`, a variable the player gave input for keeps its value
+    /// so that it can be offered as the default when the input prompt is shown again.
+    pub fn back(&mut self, reprompt_input: bool) -> Result<()> {
         loop {
             let latest = Self::pop_latest_entry(self)?;
             if let Some(apps) = &latest.notes {
@@ -134,6 +257,9 @@ impl Player {
             }
             if let Some(vars) = latest.variables {
                 for (name, variable_entry) in vars {
+                    if !reprompt_input && latest.input.as_ref() == Some(&name) {
+                        continue;
+                    }
                     match variable_entry.previous {
                         Some(previous) => self.variables.insert(name, previous),
                         None => self.variables.remove(&name),
@@ -143,6 +269,9 @@ impl Player {
             if latest.log {
                 self.log.pop();
             }
+            if let Some(scheduled) = latest.scheduled {
+                self.scheduled = scheduled;
+            }
             if !latest.redirect {
                 break;
             }
@@ -150,6 +279,54 @@ impl Player {
         Ok(())
     }
 
+    /// Returns the amount of the oldest history and log entries, respectively, that a compact save omits.
+    ///
+    /// See [`CompactSaveSettings`] for which entries are retained.
+    pub fn compact_trim(&self, settings: &CompactSaveSettings) -> (usize, usize) {
+        let retained = settings.history.max(1).min(self.history.len());
+        let mut history_start = self.history.len() - retained;
+        while history_start > 0 && self.history[history_start].redirect {
+            history_start -= 1;
+        }
+        let logged = self
+            .history
+            .iter()
+            .skip(history_start)
+            .filter(|entry| entry.log)
+            .count();
+        let log_start = self.log.len().saturating_sub(settings.log.max(logged));
+        (history_start, log_start)
+    }
+
+    /// Returns the indices of the history entries that can be [rewound](Player::rewind) to, most recent first.
+    ///
+    /// The latest entry is excluded, as are prompts that redirected the player onward, since going back
+    /// to the entry a redirect leads to also reverses the redirect itself.
+    pub fn rewind_targets(&self) -> Vec<usize> {
+        (0..self.history.len().saturating_sub(1))
+            .rev()
+            .filter(|&index| !self.history[index + 1].redirect)
+            .collect()
+    }
+
+    /// Reverses history entries using [`Player::back`] until the entry at `index` is the latest.
+    ///
+    /// If any entry after the target is locked, fails before any entries are reversed.
+    pub fn rewind(&mut self, index: usize, reprompt_input: bool) -> Result<()> {
+        if self
+            .history
+            .iter()
+            .skip(index + 1)
+            .any(|entry| entry.locked)
+        {
+            return Err(anyhow!("Can't go back that far right now!"));
+        }
+        while self.history.len() > index + 1 {
+            self.back(reprompt_input)?;
+        }
+        Ok(())
+    }
+
     /// Whether a specified info page ID has already been unlocked.
     fn is_page_unlocked(&self, page: &str) -> bool {
         for unlocked in &self.info_pages {
@@ -176,6 +353,21 @@ impl Player {
         choice: &Choice,
         text_context: &TextContext,
     ) -> Result<()> {
+        self.apply_entry_state(entry)?;
+        // Info pages are not stored in history entries, so we can fill the name here
+        if let Some(pages) = &choice.info_pages {
+            for page in pages {
+                let unlocked = page.to_unlocked(text_context)?;
+                if !self.is_page_unlocked(&unlocked.name) {
+                    self.info_pages.push(unlocked);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the `notes` actions and `variables` map of a history entry.
+    fn apply_entry_state(&mut self, entry: &HistoryEntry) -> Result<()> {
         if let Some(entries) = &entry.notes {
             for entry in entries {
                 self.apply_note(&entry.value, entry.take, false)?;
@@ -188,16 +380,93 @@ impl Player {
                 .collect();
             self.variables.extend(values);
         }
-        // Info pages are not stored in history entries, so we can fill the name here
-        if let Some(pages) = &choice.info_pages {
-            for page in pages {
-                let unlocked = page.to_unlocked(text_context)?;
-                if !self.is_page_unlocked(&unlocked.name) {
-                    self.info_pages.push(unlocked);
-                }
+        Ok(())
+    }
+
+    /// Records a state edit made by a debug or custom command as a history entry on the current prompt.
+    ///
+    /// The edit is applied the same way as a choice's would be, so it can be reversed with [`Player::back`].
+    /// The current prompt's introduction text is not displayed again.
+    pub fn debug_edit(
+        &mut self,
+        notes: Option<NoteEntries>,
+        variables: Option<VariableEntries>,
+        stc: &StaticContext,
+    ) -> Result<()> {
+        let mut entry = HistoryEntry::new(&self.latest_entry()?.path);
+        entry.display = false;
+        entry.notes = notes;
+        entry.variables = variables;
+        self.apply_entry_state(&entry)?;
+        self.push_entry(entry, stc);
+        Ok(())
+    }
+
+    /// Pushes a history entry, removing the oldest entry if the history exceeds its maximum size.
+    fn push_entry(&mut self, entry: HistoryEntry, stc: &StaticContext) {
+        self.history.push_back(entry);
+        if self.history.len() > stc.config.settings.history.size {
+            self.history.pop_front();
+        }
+    }
+
+    /// Counts down the player's scheduled events and schedules a new one if the choice specifies it.
+    ///
+    /// Redirects are not made by the player, so they do not count down events.
+    /// Returns the previous scheduled events if any were modified so that they can be restored with [`Player::back`].
+    fn advance_schedule(
+        &mut self,
+        choice: &Choice,
+        redirect: bool,
+        current: &PathData,
+        text_context: &TextContext,
+    ) -> Result<Option<ScheduledEvents>> {
+        let previous = self.scheduled.clone();
+        if !redirect {
+            for event in &mut self.scheduled {
+                event.turns = event.turns.saturating_sub(1);
             }
         }
-        Ok(())
+        if let Some(schedule) = &choice.schedule {
+            self.scheduled
+                .push(schedule.to_event(current, text_context)?);
+        }
+        let result = if previous == self.scheduled {
+            None
+        } else {
+            Some(previous)
+        };
+        Ok(result)
+    }
+
+    /// If any scheduled event's countdown has finished, removes it and jumps to its prompt.
+    ///
+    /// If multiple events finish on the same turn, only the earliest scheduled event is fired;
+    /// the rest are fired one at a time in the following game loop iterations.
+    /// The jump is recorded as a redirect, so reversing it also reverses the choice that finished the countdown.
+    ///
+    /// Returns whether a jump occurred.
+    pub fn interrupt(&mut self, stc: &StaticContext) -> bool {
+        let index = match self.scheduled.iter().position(|event| event.turns == 0) {
+            Some(index) => index,
+            None => return false,
+        };
+        let previous = self.scheduled.clone();
+        let event = self.scheduled.remove(index);
+        let mut entry = HistoryEntry::new(&event.path);
+        entry.redirect = true;
+        entry.scheduled = Some(previous);
+        self.push_entry(entry, stc);
+        true
+    }
+
+    /// Jumps to a prompt without a choice, such as when a prompt's `guard` turns the player away.
+    ///
+    /// The jump is recorded as a redirect, so reversing it also reverses the choice that led to the guarded prompt.
+    pub fn redirect(&mut self, path: &PathData, stc: &StaticContext) {
+        let mut entry = HistoryEntry::new(path);
+        entry.redirect = true;
+        self.push_entry(entry, stc);
     }
 
     pub fn choose(
@@ -209,23 +478,98 @@ impl Player {
         stc: &StaticContext,
         text_context: &TextContext,
     ) -> Result<()> {
+        let roll = choice
+            .roll
+            .as_ref()
+            .map(|roll| roll.roll(text_context))
+            .invert()?;
+        if let Some(result) = &roll {
+            OutputLog::print(&format!("{result}\n"));
+        }
         let latest = self.latest_entry()?;
-        if let Some(result) =
-            choice.to_history_entry(&latest, input, &self.variables, model, once, stc, text_context)
-        {
-            let entry = result?;
+        let latest_path = latest.path.clone();
+        // Input to a choice that doesn't record an entry is still kept
+        let unrecorded = input
+            .as_ref()
+            .map(|named| (named.name.clone(), named.entry.value.clone()));
+        if let Some(result) = choice.to_history_entry(
+            &latest,
+            input,
+            roll.as_ref(),
+            &self.variables,
+            model,
+            once,
+            stc,
+            text_context,
+        ) {
+            let mut entry = result?;
             self.apply_entry(&entry, choice, text_context)?;
-            self.history.push_back(entry);
-            if self.history.len() > stc.config.settings.history.size {
-                self.history.pop_front();
-            }
+            entry.scheduled =
+                self.advance_schedule(choice, entry.redirect, &latest_path, text_context)?;
+            self.push_entry(entry, stc);
+        } else if let Some((name, value)) = unrecorded {
+            self.variables.insert(name, value);
         }
+        self.record_choice_use(choice, &latest_path, stc)?;
         if let Some(sounds) = &choice.sounds {
             stc.resources.submit_audio(&self, sounds, text_context)?;
         }
         Ok(())
     }
 
+    /// Increments the use count of a choice within the prompt at `path`.
+    /// If the choice is `session_once`, also marks it as used for the rest of the session.
+    fn record_choice_use(
+        &mut self,
+        choice: &Choice,
+        path: &PathData,
+        stc: &StaticContext,
+    ) -> Result<()> {
+        let prompt = Prompt::get(&stc.resources.prompts, path)?;
+        if let Some(index) = prompt.choice_index(choice) {
+            let key = choice.key(path, index);
+            if choice.session_once {
+                self.session.insert(key.clone());
+            }
+            *self.choice_uses.entry(key).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    /// Counts the usable choices within the prompt at `path` that have never been selected.
+    pub fn untried_count(
+        &self,
+        prompt: &Prompt,
+        path: &PathData,
+        choices: &UsableChoices,
+    ) -> usize {
+        choices
+            .iter()
+            .filter_map(|(choice, _)| Some(choice.key(path, prompt.choice_index(choice)?)))
+            .filter(|key| !self.choice_uses.contains_key(key))
+            .count()
+    }
+
+    /// Returns the note randomly applied by a choice that was just made, if it has a `random` note action.
+    fn random_note(&self, choice: &Choice) -> Result<Option<String>> {
+        let random = choice
+            .notes
+            .as_ref()
+            .map(|actions| actions.random.is_some())
+            .unwrap_or(false);
+        if !random {
+            return Ok(None);
+        }
+        let note = self
+            .latest_entry()?
+            .notes
+            .iter()
+            .flatten()
+            .find(|entry| entry.random)
+            .map(|entry| entry.value.clone());
+        Ok(note)
+    }
+
     pub fn after_choice(
         &mut self,
         choice: &Choice,
@@ -234,8 +578,11 @@ impl Player {
     ) -> Result<()> {
         // Create a new text context using the new variable and note values for the logs
         // Log page names are not stored in history entries, just whether they were given, so we can fill the name here
-        let text_context = if choice.log.is_some() || choice.drp.is_some() {
-            Some(text_context!(stc, self))
+        // Templated rich presence states may also depend on any state, so a connected client always needs a context
+        let text_context = if choice.log.is_some() || choice.drp.is_some() || drpc.is_some() {
+            let mut context = text_context!(stc, self);
+            context.random_note = self.random_note(choice)?;
+            Some(context)
         } else {
             None
         };
@@ -266,12 +613,53 @@ impl Player {
         once: &Option<String>,
         input: Option<NamedVariableEntry>,
         drpc: &mut Option<RichPresence>,
+        saves: &SaveManager,
         model: &PromptModel,
         stc: &StaticContext,
         text_context: &TextContext,
     ) -> Result<()> {
         self.choose(choice, once, input, model, stc, text_context)?;
-        self.after_choice(choice, stc, drpc)
+        self.increment_choices();
+        self.after_choice(choice, stc, drpc)?;
+        self.checkpoint(choice, saves, stc)?;
+        self.delay(choice, text_context)
+    }
+
+    /// Counts a choice toward the player's `total_choices`.
+    fn increment_choices(&mut self) {
+        self.total_choices += 1;
+    }
+
+    /// Writes a checkpoint save if a choice has the `save` flag and saving is enabled.
+    ///
+    /// Otherwise, if `settings.auto_save` is enabled, the player is autosaved in the background.
+    pub fn checkpoint(
+        &self,
+        choice: &Choice,
+        saves: &SaveManager,
+        stc: &StaticContext,
+    ) -> Result<()> {
+        if !stc.config.settings.save {
+            return Ok(());
+        }
+        if choice.save {
+            saves.write_checkpoint(self)?;
+        } else if stc.config.settings.auto_save.settings.enabled {
+            saves.write_autosave(self);
+        }
+        Ok(())
+    }
+
+    /// Pauses for a choice's `delay`, if any, unless text is being printed instantly or in accessible mode.
+    fn delay(&self, choice: &Choice, text_context: &TextContext) -> Result<()> {
+        if text_context.accessible() || self.speed == TextSpeed::INSTANT {
+            return Ok(());
+        }
+        if let Some(delay) = &choice.delay {
+            let skippable = text_context.config().settings.text.skippable_delays;
+            Text::sleep(delay.get_value(text_context)?, skippable)?;
+        }
+        Ok(())
     }
 
     /// Returns the player's log entries split into readable chunks of five entries maximum.
@@ -279,13 +667,179 @@ impl Player {
         self.log.chunks(5).collect()
     }
 
+    /// Truncates a log entry to `width` columns, appending the configured ellipsis only if anything was cut.
+    ///
+    /// Truncation is based on display width and always ends on a character boundary,
+    /// so multibyte entries such as emoji never cause a panic.
+    fn log_front(entry: &str, width: usize, ellipsis: &str) -> String {
+        let (truncated, _) = entry.unicode_truncate(width);
+        if truncated.len() == entry.len() {
+            return entry.to_owned();
+        }
+        format!("{truncated}{ellipsis}")
+    }
+
     /// Gets the "front" of each page in a collection of [`Player::log_pages`]; that is, the first entry
-    /// in each page truncated to a readable length.
-    pub fn log_page_fronts(pages: &Vec<&[String]>) -> Vec<String> {
+    /// in each page truncated to a readable length according to the [`LogSettings`].
+    pub fn log_page_fronts(pages: &Vec<&[String]>, settings: &LogSettings) -> Vec<String> {
+        let width = settings.front_width();
         pages
             .iter()
-            .map(|chunk| chunk[0].unicode_truncate(25).0.to_owned())
-            .map(|line| format!("{line}..."))
+            .map(|chunk| Self::log_front(&chunk[0], width, &settings.ellipsis))
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{resources::Resources, testing};
+
+    use super::*;
+
+    /// Records the player answering an input prompt for the variable `name`, as [`Player::choose`] would.
+    fn give_input(player: &mut Player, name: &str, value: &str) {
+        let mut entry = HistoryEntry::new(&testing::path("next"));
+        let recorded = VariableEntry::new(name, value.to_owned(), &player.variables);
+        entry.variables = Some(VariableEntries::from([(name.to_owned(), recorded)]));
+        entry.input = Some(name.to_owned());
+        player.apply_entry_state(&entry).unwrap();
+        player.history.push_back(entry);
+    }
+
+    fn variable<'a>(player: &'a Player, name: &str) -> Option<&'a str> {
+        player.variables.get(name).map(String::as_str)
+    }
+
+    #[test]
+    fn back_reprompting_input_removes_new_variable() {
+        let config = testing::manifest("");
+        let mut player = testing::player(&config);
+        give_input(&mut player, "name", "Ann");
+        player.back(true).unwrap();
+        assert_eq!(variable(&player, "name"), None);
+        assert_eq!(player.history.len(), 1);
+    }
+
+    #[test]
+    fn back_reprompting_input_restores_previous_value() {
+        let config = testing::manifest("");
+        let mut player = testing::player(&config);
+        player.variables.insert("name".to_owned(), "Bob".to_owned());
+        give_input(&mut player, "name", "Ann");
+        player.back(true).unwrap();
+        assert_eq!(variable(&player, "name"), Some("Bob"));
+    }
+
+    #[test]
+    fn back_without_reprompting_keeps_input() {
+        let config = testing::manifest("");
+        let mut player = testing::player(&config);
+        player.variables.insert("name".to_owned(), "Bob".to_owned());
+        give_input(&mut player, "name", "Ann");
+        player.back(false).unwrap();
+        assert_eq!(variable(&player, "name"), Some("Ann"));
+        assert_eq!(player.history.len(), 1);
+    }
+
+    #[test]
+    fn back_without_reprompting_reverses_other_variables() {
+        let config = testing::manifest("");
+        let mut player = testing::player(&config);
+        give_input(&mut player, "name", "Ann");
+        let gold = VariableEntry::new("gold", "10".to_owned(), &player.variables);
+        let entry = player.history.back_mut().unwrap();
+        entry
+            .variables
+            .as_mut()
+            .unwrap()
+            .insert("gold".to_owned(), gold);
+        player.variables.insert("gold".to_owned(), "10".to_owned());
+        player.back(false).unwrap();
+        assert_eq!(variable(&player, "name"), Some("Ann"));
+        assert_eq!(variable(&player, "gold"), None);
+    }
+
+    /// Applies a directive that swaps the `old` note for `new` and sets `gold` to `10`.
+    fn apply_swap(player: &mut Player, config: &Manifest, resources: &Resources) {
+        let directive = ScriptDirective {
+            output: None,
+            notes: HashMap::from([("old".to_owned(), false), ("new".to_owned(), true)]),
+            variables: HashMap::from([("gold".to_owned(), "10".to_owned())]),
+        };
+        let stc = StaticContext::new(config, resources, true);
+        player.apply_directive(&directive, &stc).unwrap();
+    }
+
+    #[test]
+    fn back_reverses_directive() {
+        let config = testing::manifest("");
+        let resources = testing::resources("{}");
+        let mut player = testing::player(&config);
+        player.notes.insert("old".to_owned());
+        player.variables.insert("gold".to_owned(), "5".to_owned());
+        apply_swap(&mut player, &config, &resources);
+        assert!(player.notes.contains("new") && !player.notes.contains("old"));
+        assert_eq!(variable(&player, "gold"), Some("10"));
+
+        player.back(true).unwrap();
+        assert!(player.notes.contains("old") && !player.notes.contains("new"));
+        assert_eq!(variable(&player, "gold"), Some("5"));
+        assert_eq!(player.history.len(), 1);
+    }
+
+    #[test]
+    fn directive_without_changes_is_not_recorded() {
+        let config = testing::manifest("");
+        let resources = testing::resources("{}");
+        let stc = StaticContext::new(&config, &resources, true);
+        let mut player = testing::player(&config);
+        let directive = ScriptDirective {
+            notes: HashMap::from([("missing".to_owned(), false)]),
+            ..Default::default()
+        };
+        player.apply_directive(&directive, &stc).unwrap();
+        assert_eq!(player.history.len(), 1);
+    }
+
+    /// Pushes a history entry at a prompt, marked as a redirect if the player didn't choose it.
+    fn visit(player: &mut Player, prompt: &str, redirect: bool) {
+        let mut entry = HistoryEntry::new(&testing::path(prompt));
+        entry.redirect = redirect;
+        player.history.push_back(entry);
+    }
+
+    #[test]
+    fn rewind_targets_skip_prompts_that_redirected() {
+        let config = testing::manifest("");
+        let mut player = testing::player(&config);
+        visit(&mut player, "hall", false);
+        visit(&mut player, "gate", true);
+        visit(&mut player, "yard", true);
+        visit(&mut player, "tower", false);
+        // `hall` and `gate` redirected onward, so only `start` and `yard`, where the chain ended, can be rewound to
+        assert_eq!(player.rewind_targets(), [3, 0]);
+
+        player.rewind(3, true).unwrap();
+        assert_eq!(player.latest_entry().unwrap().path, testing::path("yard"));
+    }
+
+    #[test]
+    fn log_front_truncates_emoji_by_width() {
+        // Each dragon is two columns wide, so only two fit within five columns
+        assert_eq!(Player::log_front("🐉🐉🐉🐉", 5, "..."), "🐉🐉...");
+    }
+
+    #[test]
+    fn log_front_keeps_combining_characters_with_their_base() {
+        let entry = "e\u{301}e\u{301}e\u{301}";
+        assert_eq!(Player::log_front(entry, 2, "..."), "e\u{301}e\u{301}...");
+    }
+
+    #[test]
+    fn log_front_leaves_short_entries_untouched() {
+        assert_eq!(Player::log_front("Short", 20, "..."), "Short");
+        assert_eq!(Player::log_front("Exact", 5, "..."), "Exact");
+        assert_eq!(Player::log_front("🐉🐉", 4, "..."), "🐉🐉");
+        assert_eq!(Player::log_front("", 5, "..."), "");
+    }
+}
@@ -1,20 +1,25 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
 
 use anyhow::{anyhow, Context, Result};
+use result::OptionResultExt;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     core::text::{
-        display::{Text, TextLines},
-        templating::TemplatableString,
+        display::{Text, TextLines, TextSpeed, TextVariant},
+        output::OutputLog,
+        templating::{TemplatableString, TemplatableValue},
     },
     loading::loader::{ContentFile, Contents},
 };
 
 use super::{
-    choice::{Choice, Choices, UsableChoices},
+    choice::{Choice, Choices, SessionChoices, UsableChoices},
     context::{StaticContext, TextContext},
-    path::{PathData, PathLookup},
+    path::{Path, PathData, PathLookup},
     player::Player,
     state::notes::Notes,
 };
@@ -28,7 +33,32 @@ use super::{
 pub struct Prompt {
     #[serde(rename = "prompt", skip_serializing_if = "Option::is_none")]
     pub text: Option<TextLines>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Blocks of text of which the first whose condition passes is displayed instead of `prompt`.
+    /// Mutually exclusive with `prompt`.
+    pub variants: Option<Vec<TextVariant>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// A custom label to display when taking choice input. Defaults to the config, if any.
+    pub input: Option<TemplatableString>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// A condition checked whenever a player arrives at this prompt, no matter how they got there.
+    pub guard: Option<PromptGuard>,
     pub choices: Choices,
+    #[serde(default, rename = "_note", alias = "#comment", skip_serializing)]
+    /// An author note that is never displayed to players. See [`Prompt::comment_string`].
+    pub comment: Option<serde_yaml::Value>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+/// A condition that a player must pass to enter a prompt, otherwise being redirected to another one.
+pub struct PromptGuard {
+    #[serde(alias = "if")]
+    /// Whether the player may enter the prompt.
+    pub condition: TemplatableValue<bool>,
+    #[serde(rename = "else")]
+    /// The prompt to redirect to if the condition fails.
+    pub jump: Path,
 }
 
 #[derive(Debug)]
@@ -38,8 +68,12 @@ pub enum PromptModel<'a> {
     Input(String, Option<&'a TemplatableString>),
     /// A normal prompt-choice container model.
     Response,
+    /// A prompt-choice container model where every choice has keywords that the player types to use it.
+    Keyword,
     /// Has one choice. This choice lacks response or input; immediately jumps to another prompt.
     Redirect(&'a Choice),
+    /// Has one choice. This choice lacks response or input, but has `pause` enabled; waits for the player to press enter before jumping.
+    Continue(&'a Choice),
     /// Has one choice. This choice ends the game.
     Ending(&'a TextLines),
 }
@@ -59,7 +93,9 @@ impl<'a> PromptModel<'a> {
         match self {
             Input(name, _) => format!("Input; takes user input for the variable '{name}'"),
             Response => "Response; standard prompt-choice model".to_owned(),
+            Keyword => "Keyword; prompt-choice model using typed keywords".to_owned(),
             Redirect(_) => "Redirect; jumps to another prompt without input".to_owned(),
+            Continue(_) => "Continue; jumps to another prompt after pressing enter".to_owned(),
             Ending(_) => "Ending; the game is forced to end".to_owned(),
         }
     }
@@ -92,34 +128,126 @@ impl Prompt {
     /// Validates this prompt's choices using [`Choice::validate`].
     pub fn validate(&self, file: &str, stc: &StaticContext) -> Result<()> {
         let has_company = self.choices.len() > 1;
+        let keywords = self
+            .choices
+            .iter()
+            .filter(|choice| choice.keywords.is_some())
+            .count();
+        if keywords != 0 && keywords != self.choices.len() {
+            return Err(anyhow!("Either all or none of the choices must have a `match` section"));
+        }
+        // Sound actions can't be validated or played without channels, so catch a missing config early
+        if self.has_sounds() && !stc.config.settings.has_audio_channels() {
+            return Err(anyhow!(
+                "Uses sound actions, but no channels are declared in `settings.channels`"
+            ));
+        }
+        // Stable choice IDs key tracked state, so two choices sharing one would share that state
+        let mut ids = HashSet::new();
+        for choice in &self.choices {
+            if let Some(id) = &choice.id {
+                if !ids.insert(id) {
+                    return Err(anyhow!("Choice ID '{id}' is used more than once"));
+                }
+            }
+        }
         // Validate all independent choices
         for (index, choice) in self.choices.iter().enumerate() {
             choice
                 .validate(file, has_company, stc)
                 .with_context(|| format!("Failed to validate choice #{}", index + 1))?;
         }
-        // Validate text objects' sound keys, if any
-        if let Some(audio) = &stc.resources.audio {
-            if let Some(lines) = &self.text {
-                Text::validate_all(lines, audio)?;
+        if let Some(guard) = &self.guard {
+            Choice::validate_path(&guard.jump, file, stc)
+                .with_context(|| "`guard.else` section points to invalid prompt")?;
+        }
+        if let Some(variants) = &self.variants {
+            if self.text.is_some() {
+                return Err(anyhow!("'prompt' and 'variants' are mutually exclusive"));
+            }
+            if variants.is_empty() {
+                return Err(anyhow!("`variants` section has no variants"));
             }
         }
+        // Validate text objects, including their sound keys if audio is loaded
+        for lines in self.all_lines() {
+            Text::validate_all(lines, stc.resources.audio.as_ref())?;
+        }
         Ok(())
     }
 
+    /// Returns the prompt text and the lines of every text variant.
+    fn all_lines(&self) -> Vec<&TextLines> {
+        let variants = self.variants.iter().flatten().map(|variant| &variant.lines);
+        self.text.iter().chain(variants).collect()
+    }
+
+    /// Returns the text lines to display for this prompt: either its `prompt` text
+    /// or the first of its `variants` whose condition passes, if any.
+    pub fn lines(&self, text_context: &TextContext) -> Result<Option<&TextLines>> {
+        match &self.variants {
+            Some(variants) => TextVariant::select(variants, text_context),
+            None => Ok(self.text.as_ref()),
+        }
+    }
+
+    /// Whether this prompt's text or any of its choices submit sound actions.
+    fn has_sounds(&self) -> bool {
+        let lines_have_sounds = |lines: &TextLines| lines.iter().any(|line| line.sounds.is_some());
+        self.all_lines().into_iter().any(lines_have_sounds)
+            || self.choices.iter().any(|choice| {
+                choice.sounds.is_some()
+                    || choice
+                        .ending
+                        .as_ref()
+                        .map(lines_have_sounds)
+                        .unwrap_or(false)
+            })
+    }
+
     /// Validates all prompts in a [`Prompts`] map.
+    ///
+    /// Errors name the prompt and, if known, the file and line it is declared on.
     pub fn validate_all(stc: &StaticContext) -> Result<()> {
         for (file_name, prompt_file) in &stc.resources.prompts {
+            let source = stc.resources.prompt_sources.get(file_name);
             for (name, prompt) in prompt_file {
                 let path: PathData = PathLookup::new(&file_name, &name).into();
                 prompt
                     .validate(file_name, stc)
-                    .with_context(|| format!("Failed to validate prompt {path}"))?;
+                    .with_context(|| match source {
+                        Some(source) => {
+                            format!("Failed to validate prompt {path} ({})", source.locate(name))
+                        }
+                        None => format!("Failed to validate prompt {path}"),
+                    })?;
             }
         }
         Ok(())
     }
 
+    /// Evaluates this prompt's `guard`, if any, for a player arriving at `path`.
+    ///
+    /// Returns the path to redirect the player to if the guard's condition fails.
+    pub fn guard_redirect(
+        &self,
+        path: &PathData,
+        text_context: &TextContext,
+    ) -> Result<Option<PathData>> {
+        let result = self
+            .guard
+            .as_ref()
+            .map(|guard| -> Result<Option<PathData>> {
+                if guard.condition.get_value(text_context)? {
+                    return Ok(None);
+                }
+                Ok(Some(guard.jump.fill(path, text_context)?))
+            })
+            .invert()?
+            .flatten();
+        Ok(result)
+    }
+
     /// Returns the [`PromptModel`] based on this prompt's choices. See the enum's fields for criteria.
     pub fn model(&self, text_context: &TextContext) -> Result<PromptModel> {
         use PromptModel::*;
@@ -131,20 +259,46 @@ impl Prompt {
                 if let Some(ending) = &choice.ending {
                     return Ok(Ending(ending));
                 }
+                if let Some(pause) = &choice.pause {
+                    if pause.get_value(text_context)? {
+                        return Ok(Continue(choice));
+                    }
+                }
                 return Ok(Redirect(choice));
             }
         }
+        if self.choices.iter().all(|choice| choice.keywords.is_some()) {
+            return Ok(Keyword);
+        }
         Ok(Response)
     }
 
+    /// Fills the label to display when taking choice input, if any.
+    ///
+    /// Uses this prompt's `input` key, otherwise defaulting to the config.
+    pub fn input_label(&self, text_context: &TextContext) -> Result<Option<String>> {
+        let result = match &self.input {
+            Some(input) => Some(input.fill(text_context)?),
+            None => text_context.config().settings.text.input.clone(),
+        };
+        Ok(result)
+    }
+
     /// Gathers all choices that a player can use based on the note context.
+    ///
+    /// Choices marked `session_once` are skipped if their [`Choice::key`] is present in the `session` set.
     pub fn usable_choices(
         &self,
+        path: &PathData,
         notes: &Notes,
+        session: &SessionChoices,
         text_context: &TextContext,
     ) -> Result<UsableChoices> {
         let mut result = Vec::new();
-        for choice in &self.choices {
+        for (index, choice) in self.choices.iter().enumerate() {
+            if choice.session_once && session.contains(&choice.key(path, index)) {
+                continue;
+            }
             let (usable, once) = choice.can_player_use(notes, text_context)?;
             if usable {
                 result.push((choice, once))
@@ -153,6 +307,13 @@ impl Prompt {
         Ok(result)
     }
 
+    /// Returns the index of a choice within this prompt, if it belongs to it.
+    pub fn choice_index(&self, choice: &Choice) -> Option<usize> {
+        self.choices
+            .iter()
+            .position(|other| std::ptr::eq(other, choice))
+    }
+
     /// Prints the prompt text, if any, and the choices display, if any are responses.
     pub fn print(
         &self,
@@ -163,12 +324,90 @@ impl Prompt {
         text_context: &TextContext,
     ) -> Result<()> {
         if display {
-            if let Some(lines) = &self.text {
+            if let Some(lines) = self.lines(text_context)? {
                 Text::print_lines_nl(lines, player, text_context)?;
             }
         }
-        let result = if let PromptModel::Response = model {
-            println!("{}\n", Choice::display(usable_choices, text_context)?);
+        Self::print_choices(player, model, usable_choices, 0, text_context)
+    }
+
+    /// Prints choice response lines, waiting `settings.text.stagger_choices` milliseconds between each.
+    ///
+    /// Lines are printed at once if no stagger is set, in accessible mode, or if text is printed instantly.
+    /// Pressing a key during a wait prints the remaining lines at once.
+    fn print_choice_lines(
+        lines: Vec<String>,
+        player: &Player,
+        text_context: &TextContext,
+    ) -> Result<()> {
+        let stagger = text_context
+            .config()
+            .settings
+            .text
+            .stagger_choices
+            .filter(|_| !text_context.accessible() && player.speed != TextSpeed::INSTANT);
+        let millis = match stagger {
+            Some(millis) => millis,
+            None => {
+                OutputLog::print(&lines.join("\n"));
+                return Ok(());
+            }
+        };
+        let mut skipped = false;
+        for (index, line) in lines.iter().enumerate() {
+            if index > 0 && !skipped {
+                skipped = Text::sleep(millis, true)?;
+            }
+            OutputLog::print(line);
+        }
+        Ok(())
+    }
+
+    /// Prints the choices display, if any are responses.
+    ///
+    /// Numbered choices are shown a page at a time according to `settings.text.choices_per_page`,
+    /// keeping the numbers they would have if all choices were shown.
+    ///
+    /// If responses are picked with arrow keys, they are shown by the selection list instead.
+    pub fn print_choices(
+        player: &Player,
+        model: &PromptModel,
+        usable_choices: &Vec<&Choice>,
+        page: usize,
+        text_context: &TextContext,
+    ) -> Result<()> {
+        let result = match model {
+            PromptModel::Response
+                if text_context
+                    .config()
+                    .settings
+                    .input
+                    .uses_arrow_select(text_context.accessible()) => {}
+            PromptModel::Response => {
+                let settings = &text_context.config().settings.text;
+                let range = settings.choice_page_range(page, usable_choices.len());
+                let offset = range.start;
+                let lines =
+                    Choice::display_lines(&usable_choices[range], offset, true, text_context)?;
+                Self::print_choice_lines(lines, player, text_context)?;
+                let pages = settings.choice_pages(usable_choices.len());
+                if pages > 1 {
+                    OutputLog::print(&format!(
+                        "Page {}/{pages} ('n' for next, 'p' for previous)",
+                        page + 1
+                    ));
+                }
+                if let Some(footer) = &settings.choice_footer {
+                    OutputLog::print(&footer.fill(text_context)?);
+                }
+                OutputLog::print("");
+            }
+            PromptModel::Keyword => {
+                let lines = Choice::display_lines(usable_choices, 0, false, text_context)?;
+                Self::print_choice_lines(lines, player, text_context)?;
+                OutputLog::print("");
+            }
+            _ => (),
         };
         Ok(result)
     }
@@ -208,18 +447,51 @@ impl Prompt {
             .collect()
     }
 
+    /// Formats an author note for display in debug information.
+    ///
+    /// Author notes can be any YAML value to allow for structured metadata; non-string values are displayed as YAML.
+    pub fn comment_string(comment: &serde_yaml::Value) -> String {
+        match comment {
+            serde_yaml::Value::String(string) => string.clone(),
+            other => serde_yaml::to_string(other)
+                .map(|yaml| yaml.trim_start_matches("---").trim().to_owned())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns a block of author notes on this prompt and its choices, if any are present.
+    fn comments(&self) -> Option<String> {
+        let mut lines: Vec<String> = self
+            .comment
+            .iter()
+            .map(|comment| format!("- Prompt: {}", Self::comment_string(comment)))
+            .collect();
+        for (index, choice) in self.choices.iter().enumerate() {
+            if let Some(comment) = &choice.comment {
+                lines.push(format!("- Choice #{}: {}", index + 1, Self::comment_string(comment)));
+            }
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        Some(lines.join("\n"))
+    }
+
     /// Returns a block of debug information about this prompt,
-    /// including the ID, type, choices configuration, and other prompts that jump to this one.
+    /// including the ID, type, choices configuration, other prompts that jump to this one, and author notes.
     pub fn debug_info(
         &self,
         path: &PathData,
         prompts: &Prompts,
         notes: &Notes,
+        session: &SessionChoices,
         text_context: &TextContext,
     ) -> Result<String> {
         let model = self.model(text_context)?;
         let choices_amt = self.choices.len();
-        let usable_choices = self.usable_choices(notes, text_context)?.len();
+        let usable_choices = self
+            .usable_choices(path, notes, session, text_context)?
+            .len();
         let external_jumps: Vec<String> = Self::external_jumps(path, prompts)
             .iter()
             .map(|(other_id, choices)| {
@@ -234,6 +506,10 @@ impl Prompt {
         } else {
             format!("\n\nPrompts that jump here:\n{}", external_jumps.join("\n"))
         };
-        Ok(format!("\n{id_and_model}\n\n{choices}{jumps}"))
+        let comments = self
+            .comments()
+            .map(|comments| format!("\n\nAuthor notes:\n{comments}"))
+            .unwrap_or(String::new());
+        Ok(format!("\n{id_and_model}\n\n{choices}{jumps}{comments}"))
     }
 }
@@ -1,47 +1,330 @@
-use anyhow::Result;
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashSet, VecDeque},
+};
+
+use anyhow::{anyhow, Context, Result};
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
 
-use crate::loading::loader::Loader;
+use crate::loading::loader::{Loader, SourceMaps};
 
 use super::{
     audio::{Audio, SoundActions},
+    choice::Choice,
     context::{StaticContext, TextContext},
-    manifest::Manifest,
+    manifest::{DuckSettings, Manifest},
+    path::{Path, PathData},
     player::Player,
     prompt::{Prompt, Prompts},
     scripts::Scripts,
-    state::info::InfoPages,
+    state::{
+        info::InfoPages,
+        notes::{Conditions, NoteRequirementGroup, Notes},
+    },
     text::display::{TranslationFile, Translations},
+    warnings::Warnings,
 };
 
 pub struct Resources {
     pub prompts: Prompts,
+    /// Where each prompt is declared, used to point validation errors at their source.
+    pub prompt_sources: SourceMaps,
     pub translations: Translations,
     pub info_pages: InfoPages,
+    /// Named note requirement groups loaded from the `conditions` directory.
+    pub conditions: Conditions,
     pub scripts: Scripts,
     pub audio: Option<Audio>,
+    rng: RefCell<StdRng>,
 }
 
 impl Resources {
-    pub fn load(loader: &Loader, config: &Manifest) -> Result<Self> {
+    pub fn load(loader: &Loader, config: &Manifest, seed: u64) -> Result<Self> {
         let result = Resources {
             prompts: loader.load_content("prompts")?,
+            prompt_sources: loader.load_sources("prompts")?,
             translations: loader.load_content("lang")?,
             info_pages: loader.load_raw_content("info")?,
+            conditions: loader.load_content("conditions")?,
             scripts: Scripts::load(loader)?,
             audio: Audio::load(loader, config)?,
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
         };
         Ok(result)
     }
 
-    pub fn validate(&self, stc: &StaticContext) -> Result<()> {
+    #[cfg(test)]
+    /// Creates resources holding only some prompts, with no audio and a fixed seed.
+    pub fn from_prompts(prompts: Prompts) -> Self {
+        Resources {
+            prompts,
+            prompt_sources: SourceMaps::new(),
+            translations: Translations::new(),
+            info_pages: InfoPages::new(),
+            conditions: Conditions::new(),
+            scripts: Scripts {
+                files: Default::default(),
+                libs: Default::default(),
+                lua: rlua::Lua::new(),
+            },
+            audio: None,
+            rng: RefCell::new(StdRng::seed_from_u64(0)),
+        }
+    }
+
+    /// The seed used by replays when neither the command line nor the config provides one,
+    /// so that replaying the same script always produces the same transcript.
+    pub const REPLAY_SEED: u64 = 0;
+
+    /// Resolves the seed for the game's random number generator.
+    ///
+    /// Prefers the `override_seed`, such as one passed from the command line, then the config's `seed` setting.
+    /// If neither is present, generates a seed from entropy.
+    pub fn seed(config: &Manifest, override_seed: Option<u64>) -> u64 {
+        override_seed
+            .or(config.settings.seed)
+            .unwrap_or_else(|| thread_rng().gen())
+    }
+
+    /// Calls a function with the game's seeded random number generator.
+    pub fn random<T, F>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut StdRng) -> T,
+    {
+        f(&mut self.rng.borrow_mut())
+    }
+
+    /// Retrieves a named condition from any file in the `conditions` directory.
+    pub fn condition(&self, name: &str) -> Result<&NoteRequirementGroup> {
+        self.conditions
+            .values()
+            .find_map(|file| file.get(name))
+            .ok_or(anyhow!("Unknown condition '{name}'"))
+    }
+
+    /// Validates that condition names are unique across files and that no condition is empty.
+    fn validate_conditions(&self) -> Result<()> {
+        let mut seen = HashSet::new();
+        for (file, conditions) in &self.conditions {
+            for (name, condition) in conditions {
+                if !seen.insert(name) {
+                    return Err(anyhow!(
+                        "Condition '{name}' in 'conditions/{file}' is declared in multiple files"
+                    ));
+                }
+                if condition.states.is_empty() {
+                    return Err(anyhow!(
+                        "Condition '{name}' in 'conditions/{file}' has no requirements"
+                    ));
+                }
+                for requirement in &condition.states {
+                    requirement.state.validate().with_context(|| {
+                        format!("Failed to validate condition '{name}' in 'conditions/{file}'")
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates all resources against the config, failing on the first fatal issue.
+    ///
+    /// Advisory issues are reported to the `warnings` collector instead.
+    pub fn validate(&self, stc: &StaticContext, warnings: &mut Warnings) -> Result<()> {
+        self.validate_conditions()?;
         let _ = Prompt::validate_all(stc)?;
+        for (name, path) in stc.config.entry.all_paths() {
+            Prompt::get(&self.prompts, path).with_context(|| match name {
+                Some(name) => format!("Entrypoint '{name}' points to invalid prompt"),
+                None => "Entrypoint points to invalid prompt".to_owned(),
+            })?;
+        }
+        for (name, file) in &stc.config.commands {
+            self.scripts
+                .validate(file)
+                .with_context(|| format!("Failed to validate command '{name}'"))?;
+        }
+        for (name, channel) in stc.config.settings.channels.iter().flatten() {
+            if let Some(template) = channel.settings.enabled.as_template() {
+                for script in template.script_references() {
+                    self.scripts.validate(script).with_context(|| {
+                        format!("Channel '{name}' `enabled` section references a missing script")
+                    })?;
+                }
+            }
+        }
+        self.check_translations(stc, warnings);
+        self.detect_dead_ends(stc, warnings);
         Ok(())
     }
 
+    /// Collects the notes given by a choice into `obtainable` and its static jump targets into `targets`.
+    ///
+    /// Returns `false` if the choice gives a templated note or jumps to a templated path,
+    /// in which case any note may be obtainable.
+    fn walk_choice<'a>(
+        choice: &'a Choice,
+        obtainable: &mut Notes,
+        targets: &mut Vec<&'a Path>,
+    ) -> bool {
+        targets.extend(choice.jump.iter());
+        targets.extend(choice.roll.iter().map(|roll| &roll.pass));
+        targets.extend(choice.schedule.iter().map(|schedule| &schedule.jump));
+        if let Some(match_jump) = &choice.match_jump {
+            targets.extend(match_jump.paths());
+        }
+        let notes = match &choice.notes {
+            Some(notes) => notes,
+            None => return true,
+        };
+        let given = notes
+            .apply
+            .iter()
+            .flatten()
+            .filter(|app| app.state.static_state() != Some(false))
+            .map(|app| &app.state.name)
+            .chain(notes.once.iter())
+            .chain(notes.random.iter().flatten().map(|note| &note.name));
+        for name in given {
+            match name.content() {
+                Some(name) => obtainable.insert(name.to_owned()),
+                None => return false,
+            };
+        }
+        true
+    }
+
+    /// Returns the notes a choice requires the player to have that aren't `obtainable`.
+    fn unobtainable_requirements(&self, choice: &Choice, obtainable: &Notes) -> BTreeSet<String> {
+        let condition = choice
+            .require_condition
+            .as_ref()
+            .and_then(|name| self.condition(name).ok());
+        choice
+            .notes
+            .iter()
+            .flat_map(|notes| notes.require.iter().flatten())
+            .chain(condition.iter().flat_map(|group| &group.states))
+            .map(|requirement| &requirement.state)
+            .filter(|state| state.kind.is_note() && state.static_state() == Some(true))
+            .filter_map(|state| state.name.content())
+            .filter(|name| !obtainable.contains(*name))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    /// Warns about prompts reachable from the entrypoint where every choice requires a note that can never be given.
+    ///
+    /// Prompts are reached through static `jump`, `roll.pass`, `match_jump`, `schedule.jump`, and `guard.jump` paths.
+    /// A note can be given if it is an entrypoint note or is applied by any reachable choice.
+    ///
+    /// Since scripts and templates may lead anywhere or give any note, nothing is reported if custom commands
+    /// are declared, or if a reachable choice gives a templated note or jumps to a templated path.
+    fn detect_dead_ends(&self, stc: &StaticContext, warnings: &mut Warnings) {
+        if !stc.config.commands.is_empty() {
+            return;
+        }
+        let mut obtainable: Notes = stc.config.entry.notes.clone().unwrap_or_default();
+        let mut queue: VecDeque<PathData> = stc
+            .config
+            .entry
+            .all_paths()
+            .into_iter()
+            .map(|(_, path)| path.clone())
+            .collect();
+        let mut visited: HashSet<PathData> = HashSet::new();
+        let mut reached: Vec<(PathData, &Prompt)> = Vec::new();
+        while let Some(path) = queue.pop_front() {
+            if !visited.insert(path.clone()) {
+                continue;
+            }
+            let prompt = match Prompt::get(&self.prompts, &path) {
+                Ok(prompt) => prompt,
+                Err(_) => continue,
+            };
+            let mut targets: Vec<&Path> = prompt.guard.iter().map(|guard| &guard.jump).collect();
+            for choice in &prompt.choices {
+                if !Self::walk_choice(choice, &mut obtainable, &mut targets) {
+                    return;
+                }
+            }
+            for target in targets {
+                match target.static_data(&path.file) {
+                    Some(target) => queue.push_back(target),
+                    None => return,
+                }
+            }
+            reached.push((path, prompt));
+        }
+        for (path, prompt) in reached {
+            if prompt.choices.is_empty() {
+                continue;
+            }
+            let missing: Vec<BTreeSet<String>> = prompt
+                .choices
+                .iter()
+                .map(|choice| self.unobtainable_requirements(choice, &obtainable))
+                .collect();
+            if missing.iter().any(BTreeSet::is_empty) {
+                continue;
+            }
+            let notes: BTreeSet<String> = missing.into_iter().flatten().collect();
+            let notes: Vec<String> = notes.iter().map(|note| format!("'{note}'")).collect();
+            warnings.report(
+                path.to_string(),
+                format!(
+                    "Dead end; every choice requires a note that is never given ({})",
+                    notes.join(", ")
+                ),
+            );
+        }
+    }
+
+    /// Warns about translation keys of the default language that other languages lack.
+    fn check_translations(&self, stc: &StaticContext, warnings: &mut Warnings) {
+        let default = stc.config.settings.text.lang();
+        let default_file = match self.translations.get(&default) {
+            Some(file) => file,
+            None => return,
+        };
+        for (lang, file) in self
+            .translations
+            .iter()
+            .filter(|(lang, _)| **lang != default)
+        {
+            let missing = default_file
+                .keys()
+                .filter(|key| !file.contains_key(*key))
+                .count();
+            if missing > 0 {
+                warnings.report(
+                    format!("lang/{lang}"),
+                    format!("Missing {missing} key(s) present in '{default}'"),
+                );
+            }
+        }
+    }
+
     pub fn lang_file(&self, lang: &str) -> Option<&TranslationFile> {
         self.translations.get(lang)
     }
 
+    /// If the [`Audio`] resource exists, ducks the channels specified by some [`DuckSettings`].
+    pub fn duck_audio(&self, player: &Player, settings: &DuckSettings) -> Result<()> {
+        if let Some(audio) = &self.audio {
+            audio.duck(player, settings)?;
+        }
+        Ok(())
+    }
+
+    /// If the [`Audio`] resource exists, restores any ducked channels over `millis` milliseconds.
+    pub fn restore_audio(&self, millis: u64) -> Result<()> {
+        if let Some(audio) = &self.audio {
+            audio.restore(millis)?;
+        }
+        Ok(())
+    }
+
     /// If the [`Audio`] resource exists, submits a collection of [`SoundActions`] to it.
     pub fn submit_audio(
         &self,
@@ -4,34 +4,102 @@ use std::{
 };
 
 use anyhow::{anyhow, Context as ContextTrait, Result};
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use result::OptionResultExt;
-use rlua::{Chunk, Context, Function, Lua, Table};
+use rlua::{Chunk, Context, FromLuaMulti, Function, HookTriggers, Lua, Table, Value};
 
 use crate::loading::loader::{Loader, RawContents};
 
-use super::context::TextContext;
+use super::{context::TextContext, text::spinner::Spinner};
+
+#[derive(Debug, Default)]
+/// The result of a custom command script.
+///
+/// Command scripts may return a string, which is output to the player, or a table with the following optional keys:
+/// - `output`: Text to output to the player
+/// - `notes`: A table mapping note names to whether they should be given (`true`) or taken (`false`)
+/// - `variables`: A table mapping variable names to their new values
+pub struct ScriptDirective {
+    pub output: Option<String>,
+    pub notes: HashMap<String, bool>,
+    pub variables: HashMap<String, String>,
+}
+
+impl ScriptDirective {
+    /// Reads a directive from a value returned by a Lua script.
+    fn from_value(value: Value) -> Result<Self> {
+        let result = match value {
+            Value::Nil => Self::default(),
+            Value::String(output) => Self {
+                output: Some(output.to_str()?.to_owned()),
+                ..Default::default()
+            },
+            Value::Table(table) => Self {
+                output: table.get("output")?,
+                notes: table.get::<_, Option<_>>("notes")?.unwrap_or_default(),
+                variables: table.get::<_, Option<_>>("variables")?.unwrap_or_default(),
+            },
+            _ => return Err(anyhow!("Command scripts must return a string, a table, or nothing")),
+        };
+        Ok(result)
+    }
+
+    /// Whether this directive modifies any player state.
+    pub fn changes_state(&self) -> bool {
+        !self.notes.is_empty() || !self.variables.is_empty()
+    }
+}
 
 #[derive(Debug)]
 /// A container for script files and script running context.
 pub struct Scripts {
     pub files: RawContents,
+    /// Shared libraries from the `scripts/lib` directory, keyed by their path within it.
+    pub libs: RawContents,
     pub lua: Lua,
 }
 
 impl Scripts {
+    /// The directory within `scripts` that holds shared libraries.
+    const LIB_PREFIX: &'static str = "lib/";
+
     /// Loads all scripts from the `scripts` directory and creates a new [`Lua`] object.
+    ///
+    /// Files within `scripts/lib` are separated out as shared libraries and cannot be evaluated as scripts themselves.
+    /// Each library is compiled once here so that syntax errors are reported when loading.
     pub fn load(loader: &Loader) -> Result<Self> {
-        let result = Scripts {
-            files: loader.load_raw_content("scripts")?,
-            lua: Lua::new(),
-        };
+        let (libs, files): (RawContents, RawContents) = loader
+            .load_raw_content("scripts")?
+            .into_iter()
+            .partition(|(name, _)| name.starts_with(Self::LIB_PREFIX));
+        let libs: RawContents = libs
+            .into_iter()
+            .map(|(name, content)| (name[Self::LIB_PREFIX.len()..].to_owned(), content))
+            .collect();
+        let lua = Lua::new();
+        lua.context(|lua_ctx| {
+            for (name, content) in &libs {
+                lua_ctx
+                    .load(content)
+                    .set_name(name)?
+                    .into_function()
+                    .with_context(|| format!("Failed to load script library '{name}'"))?;
+            }
+            Ok::<_, anyhow::Error>(())
+        })?;
+        let result = Scripts { files, libs, lua };
         Ok(result)
     }
 
     /// Modifies a Lua [`Context`] to ensure stateful randomness between different loaded contexts.
-    fn random_seed(&self, context: &Context) -> Result<(), rlua::Error> {
-        let fake_time: u32 = thread_rng().gen();
+    ///
+    /// The Lua seed is drawn from the game's seeded random number generator so that runs are reproducible.
+    fn random_seed(
+        &self,
+        context: &Context,
+        text_context: &TextContext,
+    ) -> Result<(), rlua::Error> {
+        let fake_time: u32 = text_context.resources().random(|rng| rng.gen());
         context
             .load(&format!("math.randomseed({fake_time})"))
             .exec()
@@ -62,17 +130,41 @@ impl Scripts {
         let vars_table = context.create_table_from(text_context.variables.clone())?;
         context.globals().set("notes", notes_seq)?;
         context.globals().set("variables", vars_table)?;
+        context
+            .globals()
+            .set("schedule", Self::create_schedule_table(context, text_context)?)?;
         context
             .globals()
             .set("nage", text_context.create_variable_table(context)?)?;
         if let Some(audio) = &text_context.resources().audio {
             context
                 .globals()
-                .set("audio", audio.create_audio_table(context)?)?;
+                .set("audio", audio.create_audio_table(context, text_context)?)?;
         }
         Ok(())
     }
 
+    /// Creates a sequence of the player's scheduled events in the order they were scheduled.
+    ///
+    /// Each event is a table containing its remaining `turns` and the `file` and `prompt` it will jump to.
+    fn create_schedule_table<'a>(
+        context: &Context<'a>,
+        text_context: &TextContext,
+    ) -> Result<Table<'a>, rlua::Error> {
+        let events = text_context
+            .scheduled
+            .iter()
+            .map(|event| {
+                let table = context.create_table()?;
+                table.set("turns", event.turns)?;
+                table.set("file", event.path.file.clone())?;
+                table.set("prompt", event.path.prompt.clone())?;
+                Ok(table)
+            })
+            .collect::<Result<Vec<Table>, rlua::Error>>()?;
+        context.create_sequence_from(events)
+    }
+
     /// Adds helpful functions for scripts that don't have access to external libraries.
     fn add_fns(&self, context: &Context) -> Result<(), rlua::Error> {
         let time = context.create_function(|_, ()| {
@@ -85,6 +177,61 @@ impl Scripts {
         context.globals().set("time", time)
     }
 
+    /// Executes every shared library in the specified [`Context`] in order of their paths.
+    ///
+    /// A library that returns a table is made available as a global named after its file,
+    /// such that `scripts/lib/strings.lua` becomes `strings`; otherwise, the globals it defines are used directly.
+    ///
+    /// Libraries are executed again before every evaluation, after the random seed and other globals are set.
+    /// This means any state a library holds is reset between evaluations, so a script can't depend on
+    /// what previous scripts did to it, and seeded runs stay reproducible.
+    fn add_libs(&self, context: &Context) -> Result<(), rlua::Error> {
+        for (name, content) in &self.libs {
+            let value: Value = context.load(content).set_name(name)?.eval()?;
+            if let Value::Table(table) = value {
+                let global = name.rsplit('/').next().unwrap_or(name);
+                context.globals().set(global, table)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prepares a [`Context`] for evaluating a script.
+    fn prepare(&self, context: &Context, text_context: &TextContext) -> Result<(), rlua::Error> {
+        self.random_seed(context, text_context)?;
+        self.add_globals(context, text_context)?;
+        self.add_fns(context)?;
+        self.add_libs(context)
+    }
+
+    /// Limits the amount of instructions the next evaluation may execute according to `settings.scripts.max_instructions`.
+    ///
+    /// Setting the hook again resets its instruction count, so this should be called before every evaluation.
+    fn limit_instructions(&self, text_context: &TextContext) {
+        match text_context.config().settings.scripts.max_instructions {
+            Some(max) => self.lua.set_hook(
+                HookTriggers {
+                    every_nth_instruction: Some(max),
+                    ..Default::default()
+                },
+                move |_, _| {
+                    Err(rlua::Error::RuntimeError(format!(
+                        "script exceeded the limit of {max} instructions"
+                    )))
+                },
+            ),
+            None => self.lua.remove_hook(),
+        }
+    }
+
+    /// Starts a [`Spinner`] if enabled by the config, unless accessible mode is enabled.
+    ///
+    /// The spinner is erased once the returned value is dropped.
+    fn spinner(text_context: &TextContext) -> Option<Spinner> {
+        let enabled = text_context.config().settings.script_spinner && !text_context.accessible();
+        enabled.then(Spinner::start)
+    }
+
     /// Given a file string, splits it based on the function delimiter character `:`.
     /// If there is no function delimiter, returns only the file name.
     fn file_components(file: &str) -> (&str, Option<&str>) {
@@ -96,7 +243,10 @@ impl Scripts {
     }
 
     /// Given a loaded Lua chunk, and an optional function name, evaluates the result.
-    fn eval(loaded: Chunk, func: Option<&str>) -> Result<String, rlua::Error> {
+    fn eval<'lua, R>(loaded: Chunk<'lua, '_>, func: Option<&str>) -> Result<R, rlua::Error>
+    where
+        R: FromLuaMulti<'lua>,
+    {
         match func {
             Some(func) => {
                 let table: Table = loaded.eval()?;
@@ -111,10 +261,10 @@ impl Scripts {
     pub fn get(&self, file: &str, text_context: &TextContext) -> Result<Option<String>> {
         let components = Self::file_components(file);
         let result = self.files.get(components.0).map(|script| {
+            let _spinner = Self::spinner(text_context);
+            self.limit_instructions(text_context);
             self.lua.context(|lua_ctx| {
-                self.random_seed(&lua_ctx)?;
-                self.add_globals(&lua_ctx, text_context)?;
-                self.add_fns(&lua_ctx)?;
+                self.prepare(&lua_ctx, text_context)?;
                 let loaded = lua_ctx.load(script);
                 Self::eval(loaded, components.1)
                     .with_context(|| anyhow!("failed to evaluate script component {file}"))
@@ -122,4 +272,43 @@ impl Scripts {
         });
         Ok(result.invert()?)
     }
+
+    /// Validates that a script file string points to a loaded script.
+    pub fn validate(&self, file: &str) -> Result<()> {
+        let (name, _) = Self::file_components(file);
+        if !self.files.contains_key(name) {
+            return Err(anyhow!("Invalid script file '{name}'"));
+        }
+        Ok(())
+    }
+
+    /// Evaluates a custom command script given a filename, the command arguments, and a text context.
+    ///
+    /// The arguments are provided to the script as a global `args` sequence.
+    /// The returned value is read as a [`ScriptDirective`].
+    pub fn command(
+        &self,
+        file: &str,
+        args: &[String],
+        text_context: &TextContext,
+    ) -> Result<ScriptDirective> {
+        let components = Self::file_components(file);
+        let script = self
+            .files
+            .get(components.0)
+            .ok_or(anyhow!("Invalid script file '{}'", components.0))?;
+        let _spinner = Self::spinner(text_context);
+        self.limit_instructions(text_context);
+        self.lua
+            .context(|lua_ctx| {
+                self.prepare(&lua_ctx, text_context)?;
+                lua_ctx
+                    .globals()
+                    .set("args", lua_ctx.create_sequence_from(args.to_vec())?)?;
+                let loaded = lua_ctx.load(script);
+                let value: Value = Self::eval(loaded, components.1)?;
+                ScriptDirective::from_value(value)
+            })
+            .with_context(|| anyhow!("failed to evaluate command script {file}"))
+    }
 }
@@ -1,3 +1,7 @@
+pub mod carryover;
+pub mod cost;
 pub mod info;
 pub mod notes;
+pub mod profile;
+pub mod schedule;
 pub mod variables;
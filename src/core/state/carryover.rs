@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::player::Player;
+
+use super::{notes::Notes, variables::Variables};
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+/// The names of notes and variables to carry over from a finished playthrough into a New Game+.
+pub struct CarryoverKeys {
+    pub notes: Vec<String>,
+    pub variables: Vec<String>,
+}
+
+impl CarryoverKeys {
+    /// Whether no state is carried over, disabling New Game+.
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty() && self.variables.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+/// Player state recorded when a playthrough ends, used to seed a New Game+.
+pub struct Carryover {
+    pub notes: Notes,
+    pub variables: Variables,
+}
+
+impl Carryover {
+    /// Collects the notes and variables specified by the [`CarryoverKeys`] that a player has.
+    pub fn new(keys: &CarryoverKeys, player: &Player) -> Self {
+        Self {
+            notes: keys
+                .notes
+                .iter()
+                .filter(|note| player.notes.contains(*note))
+                .cloned()
+                .collect(),
+            variables: keys
+                .variables
+                .iter()
+                .filter_map(|name| {
+                    let value = player.variables.get(name)?;
+                    Some((name.clone(), value.clone()))
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{context::TextContext, text::templating::TemplatableValue};
+
+use super::variables::{VariableEntries, VariableEntry, Variables};
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(transparent)]
+/// Amounts of numeric variables that a choice requires a player to have and deducts from them upon use.
+pub struct Cost {
+    pub amounts: HashMap<String, TemplatableValue<i64>>,
+}
+
+impl Cost {
+    /// Reads the numeric value of a variable. A variable that isn't set has a balance of zero.
+    fn balance(name: &str, value: Option<&String>) -> Result<i64> {
+        match value {
+            Some(value) => value.trim().parse::<i64>().map_err(|_| {
+                anyhow!("Variable '{name}' has non-numeric value '{value}' and can't pay a cost")
+            }),
+            None => Ok(0),
+        }
+    }
+
+    /// Validates that any non-templated amounts are not negative.
+    pub fn validate(&self) -> Result<()> {
+        for (name, amount) in &self.amounts {
+            if let Some(value) = amount.value {
+                if value < 0 {
+                    return Err(anyhow!("`cost` amount for '{name}' must not be negative"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the player's variables are enough to pay every amount.
+    pub fn is_affordable(&self, variables: &Variables, text_context: &TextContext) -> Result<bool> {
+        for (name, amount) in &self.amounts {
+            if Self::balance(name, variables.get(name))? < amount.get_value(text_context)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Deducts every amount from the player's variables, recording the results in `entries`.
+    ///
+    /// If a variable was already set by the same choice, the amount is deducted from that new value,
+    /// while the recorded previous value is still the player's original one so that the choice can be reversed.
+    pub fn apply(
+        &self,
+        entries: &mut VariableEntries,
+        variables: &Variables,
+        text_context: &TextContext,
+    ) -> Result<()> {
+        for (name, amount) in &self.amounts {
+            let current = entries
+                .get(name)
+                .map(|entry| &entry.value)
+                .or(variables.get(name));
+            let value = Self::balance(name, current)? - amount.get_value(text_context)?;
+            entries.insert(name.clone(), VariableEntry::new(name, value.to_string(), variables));
+        }
+        Ok(())
+    }
+}
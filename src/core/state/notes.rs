@@ -1,11 +1,16 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use rand::distributions::{Distribution, WeightedIndex};
 use result::OptionResultExt;
 use serde::{
-    de::{value::MapAccessDeserializer, Visitor},
+    de::{
+        value::{MapAccessDeserializer, SeqAccessDeserializer},
+        Visitor,
+    },
     Deserialize, Serialize,
 };
+use strum::Display;
 
 use crate::core::{
     context::TextContext,
@@ -30,6 +35,71 @@ pub struct NoteStateContents {
     #[serde(default, rename = "take", alias = "deny")]
     /// The **non-aligned** value that is the inverse of the [`NoteAction`] type.
     pub inverse: Option<TemplatableValue<bool>>,
+    #[serde(default, skip_serializing_if = "RequirementKind::is_note")]
+    /// Whether `name` refers to a note or a player variable.
+    /// Variable requirements can only be checked, not applied.
+    pub kind: RequirementKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// How a variable requirement compares the variable against `value`.
+    pub compare: Option<Comparison>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The value a variable requirement compares the variable against.
+    pub value: Option<TemplatableString>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+/// What the name of a [`NoteStateContents`] refers to.
+pub enum RequirementKind {
+    #[default]
+    Note,
+    Variable,
+}
+
+impl RequirementKind {
+    pub fn is_note(&self) -> bool {
+        *self == RequirementKind::Note
+    }
+}
+
+#[derive(Deserialize, Serialize, Display, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+/// A comparison between a variable and a value in a variable requirement.
+///
+/// Both sides are compared as numbers. [`Eq`](Comparison::Eq) falls back to comparing them as text
+/// if either side isn't a number.
+pub enum Comparison {
+    Eq,
+    Lt,
+    Gt,
+    Lte,
+    Gte,
+}
+
+impl Comparison {
+    /// Compares a variable's value against a required value.
+    pub fn compare(&self, variable: &str, value: &str) -> Result<bool> {
+        use Comparison::*;
+        let numbers = (variable.trim().parse::<f64>(), value.trim().parse::<f64>());
+        let (lhs, rhs) = match numbers {
+            (Ok(lhs), Ok(rhs)) => (lhs, rhs),
+            _ if *self == Eq => return Ok(variable == value),
+            _ => {
+                return Err(anyhow!(
+                    "Can't compare non-numeric values '{variable}' and '{value}' with '{self}'"
+                ))
+            }
+        };
+        let result = match self {
+            Eq => lhs == rhs,
+            Lt => lhs < rhs,
+            Gt => lhs > rhs,
+            Lte => lhs <= rhs,
+            Gte => lhs >= rhs,
+        };
+        Ok(result)
+    }
 }
 
 #[derive(Debug)]
@@ -61,6 +131,9 @@ impl<'de> Visitor<'de> for NoteStateVisitor {
             name: name.to_owned().into(),
             state: Some(TemplatableValue::value(state)),
             inverse: None,
+            kind: RequirementKind::Note,
+            compare: None,
+            value: None,
         })
     }
 
@@ -103,6 +176,162 @@ impl NoteStateContents {
         }
         Ok(true)
     }
+
+    /// Returns the aligned state if it **is not templatable**, like [`NoteStateContents::get_state`].
+    pub fn static_state(&self) -> Option<bool> {
+        match (&self.state, &self.inverse) {
+            (Some(state), _) => state.value,
+            (None, Some(inverse)) => inverse.value.map(|inv| !inv),
+            (None, None) => Some(true),
+        }
+    }
+
+    /// Whether the presence of this note in the provided [`Notes`] matches the required state.
+    ///
+    /// If this is a variable requirement, whether the variable's comparison against `value` matches the required state instead.
+    /// A variable that isn't set never passes its comparison.
+    pub fn is_met(&self, notes: &Notes, text_context: &TextContext) -> Result<bool> {
+        let name = self.name.fill(text_context)?;
+        let present = match self.kind {
+            RequirementKind::Note => notes.contains(&name),
+            RequirementKind::Variable => match text_context.variables.get(&name) {
+                Some(variable) => {
+                    let compare = self.compare.unwrap_or(Comparison::Eq);
+                    let value = self
+                        .value
+                        .as_ref()
+                        .map(|value| value.fill(text_context))
+                        .invert()?
+                        .unwrap_or_default();
+                    compare
+                        .compare(variable, &value)
+                        .with_context(|| format!("Failed to check variable '{name}'"))?
+                }
+                None => false,
+            },
+        };
+        Ok(self.get_state(text_context)? == present)
+    }
+
+    /// Validates that variable requirement fields are only used with `kind: variable`, which requires a `value`.
+    pub fn validate(&self) -> Result<()> {
+        match self.kind {
+            RequirementKind::Note if self.compare.is_some() || self.value.is_some() => {
+                Err(anyhow!(
+                "`compare` and `value` are only available on requirements with `kind: variable`"
+            ))
+            }
+            RequirementKind::Variable if self.value.is_none() => {
+                Err(anyhow!("Variable requirement lacks a `value` to compare against"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// A group of note requirements that must all be met, written as either a single note state or a list of them.
+pub struct NoteRequirementGroup {
+    pub states: NoteStates,
+}
+
+impl NoteRequirementGroup {
+    /// Whether all note requirements in this group are met.
+    pub fn is_met(&self, notes: &Notes, text_context: &TextContext) -> Result<bool> {
+        for requirement in &self.states {
+            if !requirement.state.is_met(notes, text_context)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// A map of file names to named [`NoteRequirementGroup`]s that choices can share with `require_condition`.
+pub type Conditions = BTreeMap<String, BTreeMap<String, NoteRequirementGroup>>;
+
+struct NoteRequirementGroupVisitor;
+
+impl<'de> Visitor<'de> for NoteRequirementGroupVisitor {
+    type Value = NoteStates;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("string, map, or sequence")
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        let state = NoteStateVisitor.visit_str(v)?;
+        Ok(vec![NoteState { state }])
+    }
+
+    fn visit_map<A>(self, map: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        let state = NoteStateVisitor.visit_map(map)?;
+        Ok(vec![NoteState { state }])
+    }
+
+    fn visit_seq<A>(self, seq: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        Deserialize::deserialize(SeqAccessDeserializer::new(seq))
+    }
+}
+
+impl<'de> Deserialize<'de> for NoteRequirementGroup {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Self {
+            states: deserializer.deserialize_any(NoteRequirementGroupVisitor)?,
+        })
+    }
+}
+
+impl Serialize for NoteRequirementGroup {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        Serialize::serialize(&self.states, serializer)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+/// A candidate note in a weighted random note action.
+pub struct WeightedNote {
+    /// The note name to apply if this candidate is picked.
+    pub name: TemplatableString,
+    #[serde(default = "WeightedNote::default_weight")]
+    /// The chance of this candidate being picked relative to the others' weights.
+    pub weight: TemplatableValue<u32>,
+}
+
+impl WeightedNote {
+    fn default_weight() -> TemplatableValue<u32> {
+        TemplatableValue::value(1)
+    }
+
+    /// Picks a note name from a list of candidates using the game's seeded random number generator.
+    pub fn pick(candidates: &[WeightedNote], text_context: &TextContext) -> Result<String> {
+        let weights = candidates
+            .iter()
+            .map(|candidate| candidate.weight.get_value(text_context))
+            .collect::<Result<Vec<u32>>>()?;
+        let distribution = WeightedIndex::new(&weights)
+            .map_err(|_| anyhow!("Random note weights must not all be zero"))?;
+        let index = text_context
+            .resources()
+            .random(|rng| distribution.sample(rng));
+        candidates[index].name.fill(text_context)
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -113,17 +342,58 @@ pub struct NoteActions {
     /// Actions that apply state to the player.
     pub apply: Option<NoteStates>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    /// Actions that check the player's state.
+    /// Actions that check the player's state. All of them must pass.
     pub require: Option<NoteStates>,
+    #[serde(alias = "require any", skip_serializing_if = "Option::is_none")]
+    /// Groups of actions that check the player's state, at least one of which must pass in addition to `require`.
+    /// Each group is either a single note state or a list of note states that must all pass.
+    pub require_any: Option<Vec<NoteRequirementGroup>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Passes state check if the player **does not** have the specified note name.
     /// Afterwards, applies this note name.
     /// Allows easy creation of one-off choices.
     pub once: Option<TemplatableString>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Weighted candidates of which one is randomly picked and applied to the player.
+    pub random: Option<Vec<WeightedNote>>,
 }
 
 impl NoteActions {
+    /// Validates that a `random` section, if any, has candidates to pick from,
+    /// that a `require_any` section, if any, has no empty groups that could never be checked,
+    /// and that variable requirements are well-formed and only used to check state.
+    pub fn validate(&self) -> Result<()> {
+        if let Some(random) = &self.random {
+            if random.is_empty() {
+                return Err(anyhow!("`notes.random` section has no candidates"));
+            }
+        }
+        if let Some(groups) = &self.require_any {
+            if groups.is_empty() || groups.iter().any(|group| group.states.is_empty()) {
+                return Err(anyhow!("`notes.require_any` section has an empty group"));
+            }
+        }
+        for app in self.apply.iter().flatten() {
+            if !app.state.kind.is_note() {
+                return Err(anyhow!("`notes.apply` can't apply variable requirements"));
+            }
+        }
+        let requirements = self.require.iter().flatten().chain(
+            self.require_any
+                .iter()
+                .flatten()
+                .flat_map(|group| &group.states),
+        );
+        for requirement in requirements {
+            requirement.state.validate()?;
+        }
+        Ok(())
+    }
+
     /// Creates a list of [`NoteEntries`] from the note actions' [`apply`](NoteAction::apply) and [`once`](NoteAction::once) fields.
+    ///
+    /// If a [`random`](NoteAction::random) section is present, a candidate is picked here and recorded
+    /// as a [random](NoteEntry::random) entry so that the outcome is fixed once applied.
     pub fn to_note_entries(
         &self,
         once: &Option<String>,
@@ -143,6 +413,13 @@ impl NoteActions {
         if let Some(once_value) = once {
             entries.push(NoteEntry::new(once_value.clone(), false));
         }
+        if let Some(random) = &self.random {
+            entries.push(NoteEntry {
+                value: WeightedNote::pick(random, text_context)?,
+                take: false,
+                random: true,
+            });
+        }
         Ok(entries)
     }
 }
@@ -154,13 +431,20 @@ pub type Notes = HashSet<String>;
 pub struct NoteEntry {
     pub value: String,
     pub take: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    /// Whether this note was randomly picked from weighted candidates.
+    pub random: bool,
 }
 
 pub type NoteEntries = Vec<NoteEntry>;
 
 impl NoteEntry {
     pub fn new(value: String, take: bool) -> Self {
-        NoteEntry { value, take }
+        NoteEntry {
+            value,
+            take,
+            random: false,
+        }
     }
 
     pub fn from_application(app: &NoteStateContents, text_context: &TextContext) -> Result<Self> {
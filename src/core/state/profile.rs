@@ -0,0 +1,37 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+/// Player data for a game that persists across every save file and new game.
+pub struct Profile {
+    /// The IDs of the endings reached in any playthrough.
+    pub endings: BTreeSet<String>,
+    /// Whether each sound channel was last enabled or disabled by the player.
+    pub channels: BTreeMap<String, bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// The text speed multiplier the player last chose, if they ever changed it.
+    pub speed: Option<f32>,
+}
+
+impl Profile {
+    /// Applies the player's last channel toggles on top of a set of enabled channels.
+    ///
+    /// Toggles for channels that are no longer declared in `declared` are ignored.
+    pub fn apply_channels<F>(&self, channels: &mut HashSet<String>, declared: F)
+    where
+        F: Fn(&str) -> bool,
+    {
+        for (channel, enabled) in &self.channels {
+            if !declared(channel) {
+                continue;
+            }
+            if *enabled {
+                channels.insert(channel.clone());
+            } else {
+                channels.remove(channel);
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{
+    context::TextContext,
+    path::{Path, PathData},
+    text::templating::TemplatableValue,
+};
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+/// A container specifying a forced jump to occur after a number of choices.
+pub struct Schedule {
+    /// The amount of choices to be made before the jump occurs.
+    pub turns: TemplatableValue<u32>,
+    /// The prompt to jump to once the countdown finishes.
+    pub jump: Path,
+}
+
+impl Schedule {
+    /// Creates a [`ScheduledEvent`] to track on a player.
+    pub fn to_event(
+        &self,
+        current: &PathData,
+        text_context: &TextContext,
+    ) -> Result<ScheduledEvent> {
+        Ok(ScheduledEvent {
+            turns: self.turns.get_value(text_context)?,
+            path: self.jump.fill(current, text_context)?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// A pending forced jump.
+pub struct ScheduledEvent {
+    /// The amount of choices remaining before the jump occurs.
+    pub turns: u32,
+    /// The prompt to jump to.
+    pub path: PathData,
+}
+
+/// A list of scheduled events in the order they were scheduled.
+pub type ScheduledEvents = Vec<ScheduledEvent>;
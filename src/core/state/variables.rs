@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use result::OptionResultExt;
 use serde::{
     de::{
         value::{MapAccessDeserializer, SeqAccessDeserializer},
@@ -8,6 +9,7 @@ use serde::{
     },
     Deserialize, Serialize,
 };
+use strum::Display;
 
 use crate::core::{context::TextContext, text::templating::TemplatableString};
 
@@ -21,6 +23,79 @@ pub struct VariableInput {
     #[serde(rename = "variable")]
     /// The variable name to save the user input to.
     pub name: TemplatableString,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Which value to keep if the choice's `variables` also sets the input variable.
+    /// Required if both name the variable without templating; otherwise, defaults to [`Input`](InputPrecedence::Input).
+    pub precedence: Option<InputPrecedence>,
+}
+
+#[derive(Deserialize, Serialize, Display, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+/// The value kept when a choice's input and `variables` set the same variable.
+pub enum InputPrecedence {
+    #[default]
+    /// The player's input replaces the applied value.
+    Input,
+    /// The applied value replaces the player's input.
+    Variables,
+}
+
+impl VariableInput {
+    /// Whether the player's input replaces a value applied to the same variable by the choice's `variables`.
+    pub fn input_wins(&self) -> bool {
+        self.precedence.unwrap_or_default() == InputPrecedence::Input
+    }
+
+    /// Validates that the input variable doesn't ambiguously collide with a static variable application.
+    pub fn validate(&self, applying: Option<&VariableApplications>) -> Result<()> {
+        if let (Some(name), Some(applying)) = (self.name.content(), applying) {
+            if self.precedence.is_none() && applying.applies_static(name) {
+                return Err(anyhow!(
+                    "Variable '{name}' is set by both `input` and `variables`; set `input.precedence` to 'input' or 'variables'"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+/// An option in a group of choices that each set the same variable, such as a settings prompt.
+///
+/// Choices in a prompt that set the same `variable` form a group in which only one option is active:
+/// the one whose `value` matches the variable's current value.
+pub struct ChoiceSelection {
+    /// The variable shared by the group's options.
+    pub variable: TemplatableString,
+    /// The value this option sets the variable to.
+    pub value: TemplatableString,
+}
+
+impl ChoiceSelection {
+    /// Whether this option is the group's current selection.
+    pub fn is_active(&self, text_context: &TextContext) -> Result<bool> {
+        let variable = self.variable.fill(text_context)?;
+        let result = match text_context.variables.get(&variable) {
+            Some(current) => *current == self.value.fill(text_context)?,
+            None => false,
+        };
+        Ok(result)
+    }
+
+    /// Records the variable value of this option.
+    pub fn to_entry(
+        &self,
+        globals: &Variables,
+        text_context: &TextContext,
+    ) -> Result<NamedVariableEntry> {
+        Ok(NamedVariableEntry::new(
+            self.variable.fill(text_context)?,
+            self.value.fill(text_context)?,
+            globals,
+        ))
+    }
 }
 
 /// A map of display variables wherein the key is the variable name and the value is the variable's display.
@@ -29,6 +104,90 @@ pub type Variables = HashMap<String, String>;
 /// Variable applications whose name values are non-templatable keys.
 pub type StaticVariableApplications = HashMap<String, TemplatableString>;
 
+#[derive(Deserialize, Serialize, Display, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+/// How a [`VariableApplicationContents`] value is combined with the variable's current value.
+///
+/// Numeric operations treat a variable that isn't set as `0`, and [`Append`](VariableOperation::Append)
+/// treats it as an empty string.
+pub enum VariableOperation {
+    #[default]
+    /// Replaces the current value.
+    Set,
+    /// Adds the value to the current number.
+    Add,
+    /// Subtracts the value from the current number.
+    Sub,
+    /// Multiplies the current number by the value.
+    Mul,
+    /// Divides the current number by the value.
+    /// Dividing by zero results in `settings.zero_division` if set, and is an error otherwise.
+    Div,
+    /// Appends the value to the end of the current value.
+    Append,
+}
+
+impl VariableOperation {
+    fn is_set(&self) -> bool {
+        *self == VariableOperation::Set
+    }
+
+    /// Parses a variable value as a number for a numeric operation.
+    fn parse(value: &str, what: &str) -> Result<f64> {
+        value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("Can't apply a numeric operation to non-numeric {what} '{value}'"))
+    }
+
+    /// Formats the result of a numeric operation, omitting the fractional part of whole numbers.
+    fn format(number: f64) -> String {
+        if number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+            (number as i64).to_string()
+        } else {
+            number.to_string()
+        }
+    }
+
+    /// Combines the `current` value of a variable, if any, with an applied `value`.
+    pub fn apply(
+        &self,
+        current: Option<&String>,
+        value: String,
+        text_context: &TextContext,
+    ) -> Result<String> {
+        use VariableOperation::*;
+        if *self == Set {
+            return Ok(value);
+        }
+        if *self == Append {
+            return Ok(format!("{}{value}", current.map(String::as_str).unwrap_or("")));
+        }
+        let lhs = current
+            .map(|current| Self::parse(current, "variable"))
+            .invert()?
+            .unwrap_or(0.0);
+        let rhs = Self::parse(&value, "value")?;
+        let result = match self {
+            Add => lhs + rhs,
+            Sub => lhs - rhs,
+            Mul => lhs * rhs,
+            Div if rhs == 0.0 => {
+                return text_context
+                    .config()
+                    .settings
+                    .zero_division
+                    .clone()
+                    .ok_or(anyhow!("Can't divide by zero"))
+            }
+            Div => lhs / rhs,
+            Set | Append => unreachable!(),
+        };
+        Ok(Self::format(result))
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(deny_unknown_fields)]
 /// A variable application that preserves the key-static value-templatable model.
@@ -36,8 +195,11 @@ pub struct VariableApplicationContents {
     #[serde(alias = "variable")]
     /// The name of the variable.
     name: TemplatableString,
-    /// The value to set variable to.
+    /// The value to set variable to, or to combine with its current value according to `op`.
     value: TemplatableString,
+    #[serde(default, alias = "operation", skip_serializing_if = "VariableOperation::is_set")]
+    /// How the value is combined with the variable's current value.
+    op: VariableOperation,
 }
 
 pub type VariableApplicationsInner = Vec<VariableApplicationContents>;
@@ -49,6 +211,7 @@ impl VariableApplicationContents {
             .map(|(name, value)| VariableApplicationContents {
                 name: name.into(),
                 value,
+                op: VariableOperation::Set,
             })
             .collect()
     }
@@ -108,12 +271,23 @@ impl Serialize for VariableApplications {
 }
 
 impl VariableApplications {
+    /// Whether any of these applications sets a variable with a non-templatable `name`.
+    pub fn applies_static(&self, name: &str) -> bool {
+        self.applications
+            .iter()
+            .any(|app| app.name.content() == Some(name))
+    }
+
     fn into_static(&self) -> Option<StaticVariableApplications> {
         let result = self
             .applications
             .iter()
+            .filter(|app| app.op.is_set())
             .map(|app| Some((app.name.content()?.to_owned(), app.value.clone())))
             .try_collect()?;
+        if result.len() != self.applications.len() {
+            return None;
+        }
         Some(result)
     }
 }
@@ -138,23 +312,30 @@ impl VariableEntry {
         }
     }
 
+    /// Records the values of some variable applications, each applied in order.
+    ///
+    /// Operations on a variable that an earlier application in the same map changed use the changed value,
+    /// while the recorded `previous` value is always the one before any of the applications.
     pub fn from_map(
         applying: &VariableApplications,
         globals: &Variables,
         text_context: &TextContext,
     ) -> Result<VariableEntries> {
-        applying
-            .applications
-            .iter()
-            .map(|app| {
-                let named = NamedVariableEntry::new(
-                    app.name.fill(text_context)?,
-                    app.value.fill(text_context)?,
-                    globals,
-                );
-                Ok(named.into())
-            })
-            .collect()
+        let mut entries = VariableEntries::new();
+        for app in &applying.applications {
+            let name = app.name.fill(text_context)?;
+            let current = entries
+                .get(&name)
+                .map(|entry| &entry.value)
+                .or(globals.get(&name));
+            let value = app
+                .op
+                .apply(current, app.value.fill(text_context)?, text_context)
+                .with_context(|| format!("Failed to apply variable '{name}'"))?;
+            let named = NamedVariableEntry::new(name, value, globals);
+            entries.insert(named.name, named.entry);
+        }
+        Ok(entries)
     }
 }
 
@@ -177,3 +358,39 @@ impl NamedVariableEntry {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(yaml: &str) -> VariableInput {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn applications(yaml: &str) -> VariableApplications {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn static_collision_without_precedence_is_rejected() {
+        let applying = applications("name: Default");
+        assert!(input("variable: name").validate(Some(&applying)).is_err());
+    }
+
+    #[test]
+    fn static_collision_with_precedence_is_accepted() {
+        let applying = applications("name: Default");
+        for precedence in ["input", "variables"] {
+            let input = input(&format!("variable: name\nprecedence: {precedence}"));
+            assert!(input.validate(Some(&applying)).is_ok());
+        }
+    }
+
+    #[test]
+    fn templated_or_distinct_names_are_accepted() {
+        let applying = applications("- name: <target>\n  value: Default");
+        assert!(input("variable: name").validate(Some(&applying)).is_ok());
+        let applying = applications("gold: '10'");
+        assert!(input("variable: name").validate(Some(&applying)).is_ok());
+    }
+}
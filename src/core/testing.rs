@@ -0,0 +1,47 @@
+//! Game content for unit tests, parsed from YAML instead of loaded from a game directory.
+
+use std::collections::BTreeMap;
+
+use camino::Utf8Path;
+
+use crate::loading::saves::SaveManager;
+
+use super::{
+    manifest::Manifest,
+    path::{PathData, PathLookup},
+    player::Player,
+    prompt::PromptFile,
+    resources::Resources,
+};
+
+/// The prompt file that test prompts are loaded into.
+pub const FILE: &str = "test";
+
+/// Parses the manifest of a test game starting at the `start` prompt.
+///
+/// Any other top-level keys, such as a `settings` map, are appended from `yaml`.
+pub fn manifest(yaml: &str) -> Manifest {
+    let base = "metadata:\n  name: Test\n  authors: [Tester]\n  version: 1.0.0\nentry:\n  path: { file: test, prompt: start }\n";
+    serde_yaml::from_str(&format!("{base}{yaml}")).expect("invalid test manifest")
+}
+
+/// Creates resources whose only prompt file, [`FILE`], is parsed from `yaml`.
+pub fn resources(yaml: &str) -> Resources {
+    let file: PromptFile = serde_yaml::from_str(yaml).expect("invalid test prompts");
+    Resources::from_prompts(BTreeMap::from([(FILE.to_owned(), file)]))
+}
+
+/// Returns the path of a prompt within [`FILE`].
+pub fn path(prompt: &str) -> PathData {
+    PathLookup::new(FILE, prompt).into()
+}
+
+/// Creates a read-only save manager that never touches the real save directory.
+pub fn saves(config: &Manifest) -> SaveManager {
+    SaveManager::headless(config, Some(Utf8Path::new("test-saves"))).unwrap()
+}
+
+/// Creates a new player at the `start` prompt.
+pub fn player(config: &Manifest) -> Player {
+    Player::new(config, &path("start"))
+}
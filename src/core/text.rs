@@ -1,2 +1,5 @@
 pub mod display;
+pub mod locale;
+pub mod output;
+pub mod spinner;
 pub mod templating;
@@ -1,10 +1,14 @@
 use std::{
     fmt::{Debug, Display},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use anyhow::{Context, Result};
-use crossterm::style::Stylize;
+use anyhow::{anyhow, Context, Result};
+use crossterm::{
+    event::{self, Event, KeyEventKind},
+    style::Stylize,
+    terminal,
+};
 use result::OptionResultExt;
 use serde::{de, Deserialize, Deserializer, Serialize};
 use snailshell::{snailprint_d, snailprint_s};
@@ -14,12 +18,16 @@ use crate::{
     core::{
         audio::{Audio, SoundAction, SoundActions},
         context::TextContext,
+        manifest::DuckSettings,
         player::Player,
     },
     loading::loader::{ContentFile, Contents},
 };
 
-use super::templating::{TemplatableString, TemplatableValue};
+use super::{
+    output::OutputLog,
+    templating::{TemplatableString, TemplatableValue},
+};
 
 #[derive(Deserialize, Serialize, Display, Debug, PartialEq, Clone, EnumString, EnumIter)]
 #[serde(rename_all = "snake_case")]
@@ -45,11 +53,13 @@ impl TextMode {
     /// Formats a [`String`] based on the selected text mode.
     ///
     /// See [`Mode`] types to view how a text mode will format content.
-    pub fn format(&self, text: &str) -> String {
+    /// If `accessible` is true, the [`System`](TextMode::System) glyph is omitted.
+    pub fn format(&self, text: &str, accessible: bool) -> String {
         use TextMode::*;
         match self {
             Dialogue => format!("\"{text}\""),
             Action => text.to_owned(),
+            System if accessible => text.to_owned(),
             System => format!("{} {text}", "▐".dark_grey()),
         }
     }
@@ -74,6 +84,9 @@ impl Default for TextSpeed {
 }
 
 impl TextSpeed {
+    /// A speed multiplier that causes content to be printed instantly.
+    pub const INSTANT: f32 = f32::INFINITY;
+
     /// Calculates or returns the rate in charatcers per second
     /// to be used in [`snailprint_s`].
     ///
@@ -96,15 +109,35 @@ impl TextSpeed {
     ///
     /// Otherwise, if the object is [`Duration`](TextSpeed::Duration), uses [`snailprint_d`] with the
     /// specified length of time.
-    pub fn print<T>(&self, content: &T, context: &TextContext) -> Result<()>
+    ///
+    /// In both cases, the speed is scaled by the player's `multiplier`.
+    /// If accessible mode is enabled or the multiplier is [`TextSpeed::INSTANT`], the content is printed instantly regardless of speed.
+    pub fn print<T>(&self, content: &T, multiplier: f32, context: &TextContext) -> Result<()>
     where
         T: Display,
     {
+        if context.accessible() || multiplier == Self::INSTANT {
+            OutputLog::print(&content.to_string());
+            return Ok(());
+        }
+        match &self {
+            TextSpeed::Duration(duration) => {
+                snailprint_d(content, duration.get_value(context)? as f32 / 1000.0 / multiplier)
+            }
+            _ => snailprint_s(content, self.rate(context)? * multiplier),
+        };
+        OutputLog::write(&content.to_string());
+        Ok(())
+    }
+
+    /// Returns a readable description of this speed after being scaled by a `multiplier`.
+    pub fn describe(&self, multiplier: f32, context: &TextContext) -> Result<String> {
         let result = match &self {
             TextSpeed::Duration(duration) => {
-                snailprint_d(content, duration.get_value(context)? as f32 / 1000.0)
+                let ms = duration.get_value(context)? as f32 / multiplier;
+                format!("{ms:.0} milliseconds per line")
             }
-            _ => snailprint_s(content, self.rate(context)?),
+            _ => format!("{:.0} characters per second", self.rate(context)? * multiplier),
         };
         Ok(result)
     }
@@ -123,18 +156,62 @@ pub struct Text {
     #[serde(skip_serializing_if = "Option::is_none")]
     /// The speed at which the text should be printed.
     pub speed: Option<TextSpeed>,
+    #[serde(alias = "show if", skip_serializing_if = "Option::is_none")]
+    /// Whether the text is displayed at all. If [`None`], the text is always displayed.
+    pub show_if: Option<TemplatableValue<bool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// Whether to print a newline before the text.
     pub newline: Option<TemplatableValue<bool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     /// How long, in milliseconds, to wait aftet the text is printed.
     pub wait: Option<TemplatableValue<u64>>,
+    #[serde(alias = "wait for key", skip_serializing_if = "Option::is_none")]
+    /// Whether to wait for the player to press a key after the text is printed.
+    /// Mutually exclusive with `wait`.
+    pub wait_for_key: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     /// Ordered sound actions to submit to the game's [`Audio`] resource as this text is displayed.
     pub sounds: Option<SoundActions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    /// Whether to duck the channels in `settings.duck` while this text is displayed.
+    /// Defaults to the setting's `by_default` value.
+    pub duck: Option<bool>,
 }
 
 /// An ordered list of text objects.
 pub type TextLines = Vec<Text>;
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+/// A block of text lines that is displayed only if its condition passes.
+pub struct TextVariant {
+    #[serde(rename = "if", alias = "when", skip_serializing_if = "Option::is_none")]
+    /// Whether this variant can be displayed. If [`None`], the variant always passes, acting as a default.
+    pub condition: Option<TemplatableValue<bool>>,
+    /// The text lines to display.
+    pub lines: TextLines,
+}
+
+impl TextVariant {
+    /// Returns the lines of the first variant whose condition passes, if any.
+    pub fn select<'a>(
+        variants: &'a [TextVariant],
+        context: &TextContext,
+    ) -> Result<Option<&'a TextLines>> {
+        for variant in variants {
+            let passes = variant
+                .condition
+                .as_ref()
+                .map(|condition| condition.get_value(context))
+                .invert()?
+                .unwrap_or(true);
+            if passes {
+                return Ok(Some(&variant.lines));
+            }
+        }
+        Ok(None)
+    }
+}
+
 /// An ordered list of text objects with a flag representing whether the last entry was of the same [`TextMode`].
 pub type SeparatedTextLines<'a> = Vec<(bool, &'a Text)>;
 
@@ -147,6 +224,7 @@ where
         if text.speed.is_some()
             || text.newline.is_some()
             || text.wait.is_some()
+            || text.wait_for_key.is_some()
             || text.sounds.is_some()
         {
             return Err(de::Error::custom(
@@ -160,13 +238,54 @@ where
 pub type TranslationFile = ContentFile<String>;
 pub type Translations = Contents<String>;
 
+/// Creates a report of how well each translation file covers the keys of the `default` language file.
+///
+/// For each other language, lists the keys that are missing from it as well as the keys
+/// that are absent from the default language, which are likely to be typos.
+pub fn translation_coverage(translations: &Translations, default: &str) -> Result<String> {
+    let default_file = translations
+        .get(default)
+        .ok_or(anyhow!("Default language '{default}' has no translation file"))?;
+    let mut report = vec![format!("{default}: {} keys (default)", default_file.len())];
+    for (lang, file) in translations.iter().filter(|(lang, _)| *lang != default) {
+        let missing: Vec<&String> = default_file
+            .keys()
+            .filter(|key| !file.contains_key(*key))
+            .collect();
+        let unknown: Vec<&String> = file
+            .keys()
+            .filter(|key| !default_file.contains_key(*key))
+            .collect();
+        let covered = default_file.len() - missing.len();
+        let percent = if default_file.is_empty() {
+            100.0
+        } else {
+            covered as f32 / default_file.len() as f32 * 100.0
+        };
+        let mut section = format!("{lang}: {covered}/{} keys ({percent:.0}%)", default_file.len());
+        for (label, keys) in [("Missing", missing), ("Not in default", unknown)] {
+            if !keys.is_empty() {
+                let lines: Vec<String> = keys.iter().map(|key| format!("  - {key}")).collect();
+                section.push_str(&format!("\n{label}:\n{}", lines.join("\n")));
+            }
+        }
+        report.push(section);
+    }
+    Ok(report.join("\n\n"))
+}
+
 impl Text {
     /// Retrieves text content with [`TemplatableString::fill`] and formats it based on the [`TextMode`].
+    ///
+    /// Markdown styling is applied unless accessible mode is enabled.
     pub fn get(&self, context: &TextContext) -> Result<String> {
         let string = self
             .mode
             .get_value(context)?
-            .format(&self.content.fill(context)?);
+            .format(&self.content.fill(context)?, context.accessible());
+        if context.accessible() {
+            return Ok(string);
+        }
         Ok(termimad::inline(&string).to_string())
     }
 
@@ -180,21 +299,122 @@ impl Text {
         Ok(result)
     }
 
+    /// The [`DuckSettings`] to apply while this text is displayed, if any.
+    ///
+    /// Ducking is skipped when text is printed instantly, since there is no speech to duck under.
+    fn duck_settings<'a>(
+        &self,
+        player: &Player,
+        context: &'a TextContext,
+    ) -> Option<&'a DuckSettings> {
+        if player.speed == TextSpeed::INSTANT {
+            return None;
+        }
+        context
+            .config()
+            .settings
+            .duck
+            .as_ref()
+            .filter(|duck| self.duck.unwrap_or(duck.by_default))
+    }
+
+    /// Discards any terminal events that occurred before now, such as keys pressed while text was printing.
+    fn discard_events() -> Result<()> {
+        while event::poll(Duration::ZERO)? {
+            let _ = event::read()?;
+        }
+        Ok(())
+    }
+
+    /// Blocks for an amount of milliseconds.
+    ///
+    /// If `skippable` is `true`, the player may press a key to stop blocking early.
+    /// Returns whether the player skipped the rest of the wait.
+    pub fn sleep(millis: u64, skippable: bool) -> Result<bool> {
+        let duration = Duration::from_millis(millis);
+        if !skippable {
+            std::thread::sleep(duration);
+            return Ok(false);
+        }
+        Self::discard_events()?;
+        terminal::enable_raw_mode()?;
+        let start = Instant::now();
+        let result = loop {
+            let remaining = duration.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                break Ok(false);
+            }
+            match event::poll(remaining) {
+                Ok(true) => match event::read() {
+                    Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => break Ok(true),
+                    Ok(_) => continue,
+                    Err(err) => break Err(err),
+                },
+                Ok(false) => break Ok(false),
+                Err(err) => break Err(err),
+            }
+        };
+        terminal::disable_raw_mode()?;
+        Ok(result?)
+    }
+
+    /// Blocks until the player presses a key, discarding any keys pressed beforehand.
+    fn wait_for_key() -> Result<()> {
+        Self::discard_events()?;
+        terminal::enable_raw_mode()?;
+        let result = loop {
+            match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => break Ok(()),
+                Ok(_) => continue,
+                Err(err) => break Err(err),
+            }
+        };
+        terminal::disable_raw_mode()?;
+        Ok(result?)
+    }
+
     /// Formats and snailprints text based on its [`TextSpeed`].
     ///
     /// If the text object does not contain a `speed` field, defaults to the provided config settings.
+    ///
+    /// If `wait_for_key` is enabled, waits for a keypress afterward unless the text is being printed
+    /// instantly or in accessible mode, in which case there is nobody to wait on.
+    ///
+    /// Any ducked channels are restored once the text is done.
     pub fn print(&self, player: &Player, context: &TextContext) -> Result<()> {
+        let duck = self.duck_settings(player, context);
+        self.print_ducked(player, duck, context)?;
+        if let Some(duck) = duck {
+            context.resources().restore_audio(duck.restore)?;
+        }
+        Ok(())
+    }
+
+    /// Prints text as in [`Text::print`], ducking channels by `duck`, if any, but leaving them ducked afterward.
+    fn print_ducked(
+        &self,
+        player: &Player,
+        duck: Option<&DuckSettings>,
+        context: &TextContext,
+    ) -> Result<()> {
         if let Some(sounds) = &self.sounds {
             context.resources().submit_audio(player, sounds, context)?;
         }
+        if let Some(duck) = duck {
+            context.resources().duck_audio(player, duck)?;
+        }
         let speed = self
             .speed
             .as_ref()
             .unwrap_or(&context.config().settings.text.speed);
-        speed.print(&self.get(context)?, context)?;
+        speed.print(&self.get(context)?, player.speed, context)?;
         if let &Some(wait) = &self.wait(context)? {
             std::thread::sleep(Duration::from_millis(wait));
         }
+        let interactive = !context.accessible() && player.speed != TextSpeed::INSTANT;
+        if self.wait_for_key.unwrap_or(false) && interactive {
+            Self::wait_for_key()?;
+        }
         Ok(())
     }
 
@@ -211,27 +431,56 @@ impl Text {
             )
     }
 
-    /// Calculates some [`SeparatedTextLines`] based on some text lines.
-    fn get_separated_lines<'a>(
+    /// Whether this text passes its `show_if` condition, if any.
+    fn is_shown(&self, context: &TextContext) -> Result<bool> {
+        let result = self
+            .show_if
+            .as_ref()
+            .map(|condition| condition.get_value(context))
+            .invert()?
+            .unwrap_or(true);
+        Ok(result)
+    }
+
+    /// Calculates some [`SeparatedTextLines`] based on some text lines, omitting lines that aren't shown.
+    ///
+    /// Newlines are determined against the previously shown line rather than the previous line in the list,
+    /// so that hidden lines don't affect the spacing the player sees.
+    pub fn get_separated_lines<'a>(
         lines: &'a TextLines,
         context: &TextContext,
     ) -> Result<SeparatedTextLines<'a>> {
-        lines
-            .iter()
-            .enumerate()
-            .map(|(index, line)| {
-                Ok((line.is_newline(index.checked_sub(1).map(|i| &lines[i]), context)?, line))
-            })
-            .collect()
+        let mut result = Vec::new();
+        let mut previous: Option<&Text> = None;
+        for line in lines {
+            if !line.is_shown(context)? {
+                continue;
+            }
+            result.push((line.is_newline(previous, context)?, line));
+            previous = Some(line);
+        }
+        Ok(result)
     }
 
     /// Formats and separates text lines and prints them sequentially.
+    ///
+    /// Channels stay ducked across consecutive ducking lines, and are only restored before a line
+    /// that doesn't duck them and once all lines are printed.
     pub fn print_lines(lines: &TextLines, player: &Player, context: &TextContext) -> Result<()> {
+        let mut ducked: Option<&DuckSettings> = None;
         for (newline, line) in Self::get_separated_lines(lines, context)? {
             if newline {
-                println!();
+                OutputLog::print("");
             }
-            line.print(player, context)?;
+            let duck = line.duck_settings(player, context);
+            if let (None, Some(previous)) = (duck, ducked) {
+                context.resources().restore_audio(previous.restore)?;
+            }
+            line.print_ducked(player, duck, context)?;
+            ducked = duck;
+        }
+        if let Some(duck) = ducked {
+            context.resources().restore_audio(duck.restore)?;
         }
         Ok(())
     }
@@ -239,15 +488,21 @@ impl Text {
     /// Calls [`Text::print_lines`] and prints a newline at the end.
     pub fn print_lines_nl(lines: &TextLines, player: &Player, context: &TextContext) -> Result<()> {
         Self::print_lines(lines, player, context)?;
-        println!();
+        OutputLog::print("");
         Ok(())
     }
 
     /// Validates a list of [`TextLines`] in order.
-    /// Delegates validation to [`SoundAction::validate_all`] if sounds are present.
-    pub fn validate_all(lines: &TextLines, audio: &Audio) -> Result<()> {
+    /// Delegates validation to [`SoundAction::validate_all`] if sounds are present and `audio` is loaded.
+    pub fn validate_all(lines: &TextLines, audio: Option<&Audio>) -> Result<()> {
         for (index, line) in lines.iter().enumerate() {
-            if let Some(sounds) = &line.sounds {
+            if line.wait.is_some() && line.wait_for_key.unwrap_or(false) {
+                return Err(anyhow!(
+                    "Text object #{} has both `wait` and `wait_for_key`, which are mutually exclusive",
+                    index + 1
+                ));
+            }
+            if let (Some(sounds), Some(audio)) = (&line.sounds, audio) {
                 SoundAction::validate_all(sounds, audio)
                     .with_context(|| format!("Failed to validate text object #{}", index + 1))?;
             }
@@ -255,3 +510,90 @@ impl Text {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::de::DeserializeOwned;
+
+    use crate::{
+        core::{context::StaticContext, testing},
+        text_context,
+    };
+
+    use super::*;
+
+    /// Deserializes a document and checks that it serializes back to an equivalent document.
+    fn assert_round_trip<T>(yaml: &str)
+    where
+        T: DeserializeOwned + Serialize,
+    {
+        let value: T = serde_yaml::from_str(yaml).unwrap();
+        let expected: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(serde_yaml::to_value(&value).unwrap(), expected, "in:\n{yaml}");
+    }
+
+    #[test]
+    fn text_speeds_round_trip() {
+        let speeds = [
+            "delay: 30",
+            "delay: '<delay>'",
+            "rate: 12.5",
+            "rate: '<rate>'",
+            "duration: 1500",
+            "duration: '<duration>'",
+        ];
+        for speed in speeds {
+            assert_round_trip::<Text>(&format!("text: Hello\nmode: action\nspeed:\n  {speed}\n"));
+        }
+    }
+
+    #[test]
+    fn text_modes_round_trip() {
+        for mode in ["dialogue", "action", "system", "'<mode>'"] {
+            assert_round_trip::<Text>(&format!("text: Hello\nmode: {mode}\n"));
+        }
+    }
+
+    /// Returns the newline flags of the shown lines parsed from `yaml`.
+    fn newlines(yaml: &str) -> Vec<bool> {
+        let config = testing::manifest("");
+        let resources = testing::resources("{}");
+        let stc = StaticContext::new(&config, &resources, true);
+        let player = testing::player(&config);
+        let text_context = text_context!(&stc, player);
+        let lines: TextLines = serde_yaml::from_str(yaml).unwrap();
+        Text::get_separated_lines(&lines, &text_context)
+            .unwrap()
+            .into_iter()
+            .map(|(newline, _)| newline)
+            .collect()
+    }
+
+    #[test]
+    fn hidden_line_between_same_modes_adds_no_newline() {
+        let yaml = "
+- text: First
+  mode: dialogue
+- text: Hidden
+  mode: action
+  show_if: false
+- text: Second
+  mode: dialogue
+";
+        assert_eq!(newlines(yaml), [false, false]);
+    }
+
+    #[test]
+    fn hidden_line_between_different_modes_keeps_newline() {
+        let yaml = "
+- text: First
+  mode: dialogue
+- text: Hidden
+  mode: action
+  show_if: false
+- text: Second
+  mode: action
+";
+        assert_eq!(newlines(yaml), [false, true]);
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::{anyhow, Result};
+
+/// The separators used to write numbers in a language.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberFormat {
+    /// The separator placed between each group of three integer digits.
+    pub grouping: char,
+    /// The separator placed between the integer and fractional digits.
+    pub decimal: char,
+}
+
+impl NumberFormat {
+    /// The conventions used by `en_us`, which also apply to any unknown language.
+    pub const DEFAULT: NumberFormat = NumberFormat::new(',', '.');
+
+    /// Languages that group with periods and use a decimal comma, such as `1.000,5`.
+    const PERIOD_GROUPED: [&'static str; 10] =
+        ["de", "es", "it", "nl", "pt", "id", "tr", "da", "el", "ro"];
+    /// Languages that group with spaces and use a decimal comma, such as `1 000,5`.
+    const SPACE_GROUPED: [&'static str; 10] =
+        ["fr", "ru", "pl", "sv", "cs", "fi", "nb", "uk", "hu", "sk"];
+
+    pub const fn new(grouping: char, decimal: char) -> Self {
+        Self { grouping, decimal }
+    }
+
+    /// Looks up the number conventions for a language code such as `de_de`.
+    ///
+    /// Region-specific conventions are checked first, followed by those of the language as a whole.
+    /// Falls back to [`NumberFormat::DEFAULT`] for unknown languages.
+    pub fn of(lang: &str) -> Self {
+        let lang = lang.to_lowercase().replace('-', "_");
+        match lang.as_str() {
+            "de_ch" => return Self::new('\'', '.'),
+            "es_mx" => return Self::DEFAULT,
+            "pt_pt" => return Self::new('\u{a0}', ','),
+            _ => (),
+        }
+        let language = lang.split('_').next().unwrap_or_default();
+        if Self::PERIOD_GROUPED.contains(&language) {
+            Self::new('.', ',')
+        } else if Self::SPACE_GROUPED.contains(&language) {
+            Self::new('\u{a0}', ',')
+        } else {
+            Self::DEFAULT
+        }
+    }
+
+    /// Formats a number written in plain form, such as `-1000.5`, using these separators.
+    ///
+    /// The amount of fractional digits is kept as written.
+    pub fn format(&self, number: &str) -> Result<String> {
+        let number = number.trim();
+        if number.parse::<f64>().is_err() {
+            return Err(anyhow!("Invalid number '{number}'"));
+        }
+        let (sign, digits) = match number.strip_prefix(['-', '+']) {
+            Some(digits) => (&number[..1], digits),
+            None => ("", number),
+        };
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (digits, None),
+        };
+        if !integer.chars().all(|c| c.is_ascii_digit()) {
+            return Err(anyhow!("Invalid number '{number}'"));
+        }
+        let mut result = String::from(sign);
+        for (index, c) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                result.push(self.grouping);
+            }
+            result.push(c);
+        }
+        if let Some(fraction) = fraction {
+            result.push(self.decimal);
+            result.push_str(fraction);
+        }
+        Ok(result)
+    }
+}
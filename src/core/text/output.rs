@@ -0,0 +1,107 @@
+use std::{
+    fs::File,
+    io::{LineWriter, Write},
+    sync::{Mutex, OnceLock},
+};
+
+use anyhow::{anyhow, Context, Result};
+use camino::Utf8Path;
+
+/// The output log of the current session, if one was opened with [`OutputLog::open`].
+static OUTPUT_LOG: OnceLock<Mutex<OutputLog>> = OnceLock::new();
+
+/// Output copied in memory since [`OutputLog::capture`] was called, if it was.
+static CAPTURED: Mutex<Option<String>> = Mutex::new(None);
+
+/// A plain-text file that player-facing output is copied to as the game is played.
+///
+/// Output printed instantly should go through [`OutputLog::print`] so that it's never left out of the file.
+///
+/// Text is written once it is fully printed, so snailprinted content appears in the file as a whole line.
+/// Styling is stripped from all written text.
+///
+/// Player input is excluded unless `inputs` is enabled, in which case each line is written after a `> ` marker.
+pub struct OutputLog {
+    writer: LineWriter<File>,
+    inputs: bool,
+}
+
+impl OutputLog {
+    /// Creates the output log file, replacing any existing file, and starts copying output to it.
+    pub fn open(path: &Utf8Path, inputs: bool) -> Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Failed to create output log '{path}'"))?;
+        let log = OutputLog {
+            writer: LineWriter::new(file),
+            inputs,
+        };
+        OUTPUT_LOG
+            .set(Mutex::new(log))
+            .map_err(|_| anyhow!("Output log is already open"))
+    }
+
+    /// Starts copying player-facing output in memory, such as to compare a replay against a transcript.
+    pub fn capture() {
+        *CAPTURED.lock().unwrap() = Some(String::new());
+    }
+
+    /// Returns the output copied since [`OutputLog::capture`] was called, with styling stripped.
+    pub fn captured() -> String {
+        CAPTURED.lock().unwrap().clone().unwrap_or_default()
+    }
+
+    /// Removes ANSI escape sequences, such as colors and text styles, from some content.
+    fn strip_styling(content: &str) -> String {
+        let mut result = String::with_capacity(content.len());
+        let mut chars = content.chars();
+        while let Some(c) = chars.next() {
+            if c != '\x1b' {
+                result.push(c);
+                continue;
+            }
+            // Control sequences end at the first character in the '@'..='~' range
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn write_line(&mut self, line: &str) {
+        // The game shouldn't stop over a failed copy of its output
+        let _ = writeln!(self.writer, "{}", Self::strip_styling(line));
+    }
+
+    /// Prints some player-facing output followed by a newline, and copies it to the output log if one is open.
+    pub fn print(content: &str) {
+        println!("{content}");
+        Self::write(content);
+    }
+
+    /// Writes some player-facing output to the output log, if one is open.
+    ///
+    /// Only needed for content that was already printed some other way, such as by snailprinting.
+    pub fn write(content: &str) {
+        if let Some(log) = OUTPUT_LOG.get() {
+            log.lock().unwrap().write_line(content);
+        }
+        if let Some(captured) = CAPTURED.lock().unwrap().as_mut() {
+            captured.push_str(&Self::strip_styling(content));
+            captured.push('\n');
+        }
+    }
+
+    /// Writes a line of player input to the output log, if one is open and records inputs.
+    pub fn write_input(line: &str) {
+        if let Some(log) = OUTPUT_LOG.get() {
+            let mut log = log.lock().unwrap();
+            if log.inputs {
+                log.write_line(&format!("> {line}"));
+            }
+        }
+    }
+}
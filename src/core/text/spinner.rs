@@ -0,0 +1,64 @@
+use std::{
+    io::{stdout, Write},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor::MoveToColumn,
+    execute,
+    terminal::{Clear, ClearType},
+};
+
+/// An indicator drawn on the current line while a long-running task is in progress.
+///
+/// The spinner only appears once the task exceeds [`Spinner::THRESHOLD`], so quick tasks produce no output.
+/// It is stopped and erased when dropped.
+pub struct Spinner {
+    sender: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    /// How long to wait before drawing the spinner.
+    const THRESHOLD: Duration = Duration::from_millis(250);
+    /// How long each frame is displayed.
+    const FRAME_LENGTH: Duration = Duration::from_millis(100);
+    const FRAMES: [&'static str; 4] = ["|", "/", "-", "\\"];
+    const LABEL: &'static str = "Thinking...";
+
+    /// Starts a spinner on a background thread.
+    pub fn start() -> Self {
+        let (sender, receiver) = mpsc::channel::<()>();
+        let handle = thread::spawn(move || {
+            if receiver.recv_timeout(Self::THRESHOLD) != Err(RecvTimeoutError::Timeout) {
+                return;
+            }
+            let mut out = stdout();
+            for frame in Self::FRAMES.iter().cycle() {
+                let _ = write!(out, "\r{frame} {}", Self::LABEL);
+                let _ = out.flush();
+                if receiver.recv_timeout(Self::FRAME_LENGTH) != Err(RecvTimeoutError::Timeout) {
+                    break;
+                }
+            }
+            let _ = execute!(out, Clear(ClearType::CurrentLine), MoveToColumn(0));
+        });
+        Self {
+            sender: Some(sender),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Spinner {
+    /// Signals the spinner thread to stop and waits for it to erase the spinner
+    /// so that subsequent output is not interleaved with it.
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt::Display, str::FromStr};
+use std::{collections::HashSet, fmt::Display, str::FromStr};
 
 use anyhow::{anyhow, Context, Result};
 use serde::{
@@ -8,7 +8,7 @@ use serde::{
 
 use crate::core::{context::TextContext, state::variables::Variables};
 
-use super::display::TranslationFile;
+use super::{display::TranslationFile, locale::NumberFormat};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(transparent)]
@@ -27,6 +27,15 @@ impl From<String> for TemplatableString {
 impl TemplatableString {
     /// The default value for an undefined interpolation component.
     pub const DEFAULT_VALUE: &'static str = "UNDEFINED";
+    /// The templating prefix that formats a number for the player's language, such as `(fmt_loc:<gold>)`.
+    pub const FMT_LOC_PREFIX: &'static str = "fmt_loc:";
+
+    /// Whether a parenthesized template is filled by a builtin rather than a script.
+    fn is_builtin(reference: &str) -> bool {
+        reference.starts_with(TextContext::CHOSE_PREFIX)
+            || reference.starts_with(TextContext::ENDING_REACHED_PREFIX)
+            || reference.starts_with(Self::FMT_LOC_PREFIX)
+    }
 
     /// Whether this string's content can be **templated** by variables or scripts.
     /// This does not check for language file matching.
@@ -52,12 +61,42 @@ impl TemplatableString {
         None
     }
 
+    /// Returns the script references within this string's raw content, such as `file:component` in `(file:component)`.
+    ///
+    /// Only the content as written is checked; references produced by filling variables or scripts are not included.
+    /// Builtin `(chose:...)`, `(ending_reached:...)`, and `(fmt_loc:...)` templates are not script references.
+    pub fn script_references(&self) -> Vec<&str> {
+        let mut result = Vec::new();
+        let mut last_opener: Option<usize> = None;
+        for (index, c) in self.content.char_indices() {
+            if c == '(' {
+                last_opener = Some(index);
+            } else if c == ')' {
+                if let Some(lb) = last_opener {
+                    let reference = &self.content[(lb + 1)..index];
+                    if !Self::is_builtin(reference) {
+                        result.push(reference);
+                    }
+                    last_opener = None;
+                }
+            }
+        }
+        result
+    }
+
     /// Fills a templatable string based on the input delimiter characters and a filler function.
     ///
-    /// If the filler function returns [`None`], yields [`TemplatableString::DEFAULT_VARIABLE`].
+    /// If the filler function returns [`None`], yields [`TemplatableString::DEFAULT_VALUE`], or leaves the
+    /// delimited text untouched if `keep_unknown` is enabled.
     ///
     /// If no templating characters exist, returns the input string.
-    fn template<'a, F>(content: &str, before: char, after: char, filler: F) -> Result<String>
+    fn template<'a, F>(
+        content: &str,
+        before: char,
+        after: char,
+        keep_unknown: bool,
+        filler: F,
+    ) -> Result<String>
     where
         F: Fn(&str) -> Result<Option<String>>,
     {
@@ -68,19 +107,26 @@ impl TemplatableString {
         let mut last_opener: Option<usize> = None;
         for (index, c) in content.char_indices() {
             if c == before {
-                last_opener = Some(index);
-            } else if c == after {
-                if let Some(lb) = last_opener {
-                    let var = &content[(lb + 1)..index];
-                    result.push_str(&filler(var)?.unwrap_or(Self::DEFAULT_VALUE.to_owned()));
-                    last_opener = None;
-                }
-            } else {
-                if last_opener.is_none() {
-                    result.push(c);
+                if let (Some(lb), true) = (last_opener, keep_unknown) {
+                    result.push_str(&content[lb..index]);
                 }
+                last_opener = Some(index);
+            } else if let (true, Some(lb)) = (c == after, last_opener) {
+                let var = &content[(lb + 1)..index];
+                let filled = match filler(var)? {
+                    Some(value) => value,
+                    None if keep_unknown => content[lb..=index].to_owned(),
+                    None => Self::DEFAULT_VALUE.to_owned(),
+                };
+                result.push_str(&filled);
+                last_opener = None;
+            } else if last_opener.is_none() && (c != after || keep_unknown) {
+                result.push(c);
             }
         }
+        if let (Some(lb), true) = (last_opener, keep_unknown) {
+            result.push_str(&content[lb..]);
+        }
         Ok(result)
     }
 
@@ -104,17 +150,73 @@ impl TemplatableString {
         context.global_variable(var).or(variables.get(var).cloned())
     }
 
-    /// Fills all templating areas with the proper context values provided by the [`TextContext`].
-    pub fn fill(&self, context: &TextContext) -> Result<String> {
-        let content = self.lang_file_content(context.lang_file());
-        let scripted = Self::template(content, '(', ')', move |var| {
+    /// Performs a single templating pass over some content, filling scripts and then variables.
+    ///
+    /// A `(chose:file/prompt@id)` template is filled with the choice's use count instead of evaluating a script.
+    ///
+    /// An `(ending_reached:id)` template is filled with `true` or `false`.
+    ///
+    /// A `(fmt_loc:number)` template is filled with the number written using the separators of the player's language.
+    /// The number may itself contain variable templates, such as `(fmt_loc:<gold>)`.
+    ///
+    /// If `keep_unknown` is enabled, templates that don't name a builtin, loaded script, or set variable are left as written.
+    fn fill_pass(content: &str, context: &TextContext, keep_unknown: bool) -> Result<String> {
+        let scripted = Self::template(content, '(', ')', keep_unknown, move |var| {
+            if let Some(key) = var.strip_prefix(TextContext::CHOSE_PREFIX) {
+                return Ok(Some(context.choice_count(key).to_string()));
+            }
+            if let Some(id) = var.strip_prefix(TextContext::ENDING_REACHED_PREFIX) {
+                return Ok(Some(context.ending_reached(id).to_string()));
+            }
+            if let Some(number) = var.strip_prefix(Self::FMT_LOC_PREFIX) {
+                let number = Self::template(number, '<', '>', false, |var| {
+                    Ok(Self::fill_variable(var, &context.variables, context))
+                })?;
+                let formatted = NumberFormat::of(context.lang())
+                    .format(&number)
+                    .with_context(|| format!("Failed to format number in '({var})'"))?;
+                return Ok(Some(formatted));
+            }
             context.resources().scripts.get(var, context)
         })?;
-        Self::template(&scripted, '<', '>', move |var| {
+        Self::template(&scripted, '<', '>', keep_unknown, move |var| {
             let filled = Self::fill_variable(var, &context.variables, &context).map(|s| s.clone());
             Ok(filled)
         })
     }
+
+    /// Fills all templating areas with the proper context values provided by the [`TextContext`].
+    ///
+    /// Templating passes are repeated while the result is still [templatable](TemplatableString::is_str_templatable),
+    /// up to a maximum of `settings.text.depth` passes. This allows variable values and script results to
+    /// themselves contain templates.
+    ///
+    /// Resolution always terminates: besides the depth limit, it stops early once a pass yields a result
+    /// that a previous pass has already produced, such as with self-referential variables.
+    pub fn fill(&self, context: &TextContext) -> Result<String> {
+        self.fill_with(context, false)
+    }
+
+    /// Fills templating areas like [`TemplatableString::fill`], but only those that name a builtin,
+    /// a loaded script, or a set variable; any other delimited text is left as written.
+    ///
+    /// This suits content where delimiters are often meant literally, such as markdown links and parentheticals.
+    pub fn fill_known(&self, context: &TextContext) -> Result<String> {
+        self.fill_with(context, true)
+    }
+
+    fn fill_with(&self, context: &TextContext, keep_unknown: bool) -> Result<String> {
+        let content = self.lang_file_content(context.lang_file());
+        let mut result = Self::fill_pass(content, context, keep_unknown)?;
+        let mut seen = HashSet::from([content.clone()]);
+        for _ in 1..context.config().settings.text.depth {
+            if !Self::is_str_templatable(&result) || !seen.insert(result.clone()) {
+                break;
+            }
+            result = Self::fill_pass(&result, context, keep_unknown)?;
+        }
+        Ok(result)
+    }
 }
 
 impl PartialEq<String> for TemplatableString {
@@ -205,6 +307,11 @@ impl<T> TemplatableValue<T> {
         }
     }
 
+    /// Returns the templatable string if the value must be filled rather than provided as-is.
+    pub fn as_template(&self) -> Option<&TemplatableString> {
+        self.template.as_ref()
+    }
+
     /// Gets the value of type `T` from the templatable value.
     ///
     /// If the value is provided as-is, returns a clone of that value.
@@ -230,3 +337,69 @@ impl<T> TemplatableValue<T> {
         unreachable!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::{context::StaticContext, testing},
+        text_context,
+    };
+
+    use super::*;
+
+    /// Fills `content` with the given player variables and `settings.text.depth`.
+    fn fill(content: &str, variables: &[(&str, &str)], depth: usize) -> String {
+        fill_as(content, variables, depth, false)
+    }
+
+    /// Fills `content` like [`fill`], only filling known templates if `known` is enabled.
+    fn fill_as(content: &str, variables: &[(&str, &str)], depth: usize, known: bool) -> String {
+        let config = testing::manifest(&format!("settings:\n  text:\n    depth: {depth}\n"));
+        let resources = testing::resources("{}");
+        let stc = StaticContext::new(&config, &resources, true);
+        let mut player = testing::player(&config);
+        for (name, value) in variables {
+            player.variables.insert(name.to_string(), value.to_string());
+        }
+        let text_context = text_context!(&stc, player);
+        let string = TemplatableString::from(content.to_owned());
+        let result = if known {
+            string.fill_known(&text_context)
+        } else {
+            string.fill(&text_context)
+        };
+        result.unwrap()
+    }
+
+    #[test]
+    fn nested_templates_resolve_within_depth() {
+        let variables = [("a", "<b>"), ("b", "value")];
+        assert_eq!(fill("<a>", &variables, 2), "value");
+        assert_eq!(fill("<a>", &variables, 1), "<b>");
+    }
+
+    #[test]
+    fn self_referential_variable_terminates() {
+        assert_eq!(fill("<a>", &[("a", "<a>")], 100), "<a>");
+    }
+
+    #[test]
+    fn mutually_referential_variables_terminate() {
+        let variables = [("a", "<b>"), ("b", "<a>")];
+        assert_eq!(fill("<a>", &variables, 100), "<a>");
+    }
+
+    #[test]
+    fn growing_variable_stops_at_depth() {
+        assert_eq!(fill("<a>", &[("a", "x<a>")], 3), "xxx<a>");
+    }
+
+    #[test]
+    fn fill_known_keeps_unknown_templates() {
+        let content = "Gold: <gold> (see [map](maps/north.md)) <br> (ending_reached:good) (open";
+        assert_eq!(
+            fill_as(content, &[("gold", "5")], 1, true),
+            "Gold: 5 (see [map](maps/north.md)) <br> false (open"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+/// A non-fatal issue with a game's content, such as a missing translation.
+pub struct Warning {
+    /// Where the issue was found, such as a prompt path or language key.
+    pub source: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+/// A collector of [`Warning`]s found while loading and validating content.
+///
+/// Modules report advisory issues into the collector rather than printing them,
+/// so that they can be summarized once at startup or suppressed entirely.
+pub struct Warnings(Vec<Warning>);
+
+impl Warnings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a warning about some content.
+    pub fn report<S, M>(&mut self, source: S, message: M)
+    where
+        S: Into<String>,
+        M: Into<String>,
+    {
+        self.0.push(Warning {
+            source: source.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Prints the amount of warnings followed by each warning's details, if there are any.
+    pub fn print_summary(&self) {
+        if self.is_empty() {
+            return;
+        }
+        let plural = if self.0.len() == 1 { "" } else { "s" };
+        println!("{} warning{plural} found while loading:", self.0.len());
+        for warning in &self.0 {
+            println!("- {}: {}", warning.source, warning.message);
+        }
+        println!();
+    }
+}
@@ -4,13 +4,16 @@ use result::OptionResultExt;
 use crate::{
     cmd::runtime::CommandResult,
     core::{
-        choice::UsableChoices,
+        choice::{Choice, UsableChoices},
         context::{StaticContext, TextContext},
         discord::RichPresence,
         player::Player,
-        prompt::PromptModel,
+        prompt::{Prompt, PromptModel},
         state::variables::NamedVariableEntry,
-        text::display::Text,
+        text::{
+            display::{Text, TextLines},
+            output::OutputLog,
+        },
     },
     game::input::{InputContext, InputResult},
     loading::saves::SaveManager,
@@ -19,24 +22,108 @@ use crate::{
 use super::input::InputController;
 
 pub fn next_input_context(
+    prompt: &Prompt,
     model: &PromptModel,
     choices: &UsableChoices,
     text_context: &TextContext,
 ) -> Result<Option<InputContext>> {
     use PromptModel::*;
+    let arrow_select = text_context
+        .config()
+        .settings
+        .input
+        .uses_arrow_select(text_context.accessible());
     let result = match &model {
-        Response => Some(InputContext::Choices(choices.len())),
+        Response if arrow_select => {
+            let usable: Vec<&Choice> = choices.iter().map(|(choice, _)| *choice).collect();
+            Some(InputContext::Select(
+                Choice::display_lines(&usable, 0, false, text_context)?,
+                prompt.input_label(text_context)?,
+            ))
+        }
+        Response => Some(InputContext::Choices(
+            choices.len(),
+            prompt.input_label(text_context)?,
+            text_context
+                .config()
+                .settings
+                .text
+                .choice_pages(choices.len()),
+        )),
+        Keyword => Some(InputContext::Keywords(
+            choices
+                .iter()
+                .map(|(choice, _)| choice.keywords(text_context))
+                .collect::<Result<_>>()?,
+            prompt.input_label(text_context)?,
+        )),
+        // Only the prompt's own label applies since the configured label is meant for choices
+        Continue(_) => Some(InputContext::Continue(
+            prompt
+                .input
+                .as_ref()
+                .map(|s| s.fill(text_context))
+                .invert()?,
+            text_context
+                .config()
+                .settings
+                .text
+                .continue_words
+                .iter()
+                .map(|word| word.trim().to_lowercase())
+                .collect(),
+        )),
+        // Values kept by going back are offered again when input isn't re-prompted from scratch
         &Input(name, prompt) => Some(InputContext::Variable(
             name.clone(),
             prompt.map(|s| s.fill(text_context)).invert()?,
+            if text_context.config().settings.history.reprompt_input {
+                None
+            } else {
+                text_context.variables.get(name).cloned()
+            },
         )),
         _ => None,
     };
     Ok(result)
 }
 
+/// Prints the lines of an ending, a summary and tip if enabled, and records the player's carryover for a New Game+.
+///
+/// If the ending choice has an `ending_id`, it is recorded in the game's profile.
+/// If it has `credits` enabled, the manifest's credits are rolled after the ending lines.
+pub fn end_game(
+    lines: &TextLines,
+    choice: Option<&Choice>,
+    player: &Player,
+    saves: &SaveManager,
+    stc: &StaticContext,
+    text_context: &TextContext,
+) -> Result<()> {
+    Text::print_lines(lines, player, text_context)?;
+    if let Some(credits) = &stc.config.entry.credits {
+        if choice.map(|choice| choice.credits).unwrap_or(false) {
+            OutputLog::print("");
+            credits.roll(player, text_context)?;
+        }
+    }
+    if let Some(summary) = &stc.config.settings.ending_summary {
+        summary.print(player);
+    }
+    if stc.config.settings.tips && stc.config.entry.tips.is_some() {
+        OutputLog::print("");
+        stc.config.entry.print_tip(text_context)?;
+    }
+    if let Some(id) = choice.and_then(|choice| choice.ending_id.as_ref()) {
+        saves.record_ending(stc.config, id)?;
+    }
+    saves.write_carryover(stc.config, player)
+}
+
 pub enum GameLoopResult {
     Retry(bool),
+    /// Shows the next page of choices if `true`, otherwise the previous one.
+    Page(bool),
     Continue,
     Shutdown(bool),
 }
@@ -46,7 +133,7 @@ pub fn handle_quit(shutdown: bool) -> GameLoopResult {
     if shutdown {
         Shutdown(false)
     } else {
-        println!("Signal quit again or use '.quit' to exit");
+        OutputLog::print("Signal quit again or use '.quit' to exit");
         Retry(true)
     }
 }
@@ -65,42 +152,52 @@ pub fn take_input(
     use GameLoopResult::*;
     let result = match input.take(context) {
         Err(err) => {
-            println!("{err}");
+            OutputLog::print(&err.to_string());
             Retry(true)
         }
         Ok(result) => match result {
             InputResult::Quit(shutdown) => handle_quit(shutdown),
+            InputResult::Page(next) => Page(next),
             InputResult::Choice(i) => {
                 let (choice, once) = &choices[i - 1];
-                player.choose_full(choice, once, None, drpc, model, stc, text_context)?;
+                player.choose_full(choice, once, None, drpc, saves, model, stc, text_context)?;
 
                 match &choice.ending {
                     Some(ending) => {
-                        println!();
-                        Text::print_lines(ending, player, text_context)?;
+                        OutputLog::print("");
+                        end_game(ending, Some(choice), player, saves, stc, text_context)?;
                         Shutdown(true)
                     }
                     None => Continue,
                 }
             }
             InputResult::Variable { name, value } => {
-                // Modify variables after the choose call since history entries are sensitive to this order
-                let entry = NamedVariableEntry::new(name.clone(), value.clone(), &player.variables);
+                // The input is applied through the history entry so that the choice's `precedence` is respected
+                let entry = NamedVariableEntry::new(name, value, &player.variables);
                 let (choice, once) = &choices[0];
-                player.choose(choice, once, Some(entry), model, stc, text_context)?;
-                player.variables.insert(name, value);
-                player.after_choice(choice, stc, drpc)?;
+                player.choose_full(
+                    choice,
+                    once,
+                    Some(entry),
+                    drpc,
+                    saves,
+                    model,
+                    stc,
+                    text_context,
+                )?;
                 Continue
             }
             InputResult::Command(parse) => {
                 match &parse {
-                    Err(err) => println!("\n{err}"), // Clap error
+                    Err(err) => OutputLog::print(&format!("\n{err}")), // Clap error
                     Ok(command) => {
-                        match command.run(player, saves, stc, text_context) {
-                            Err(err) => println!("Error: {err}"), // Command runtime error
+                        match command.run(player, saves, choices, stc, text_context) {
+                            Err(err) => OutputLog::print(&format!("Error: {err}")), // Command runtime error
                             Ok(result) => match result {
                                 CommandResult::Submit(loop_result) => return Ok(loop_result),
-                                CommandResult::Output(output) => println!("{output}"),
+                                CommandResult::Output(output) => {
+                                    OutputLog::print(&output);
+                                }
                             },
                         }
                     }
@@ -111,3 +208,65 @@ pub fn take_input(
     };
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{core::testing, text_context};
+
+    use super::*;
+
+    const PROMPTS: &str = "
+start:
+  choices:
+    - input:
+        variable: name
+      jump: start
+";
+
+    /// Answers the input prompt at `start` with `Ann`, goes back, and returns the input context shown next.
+    fn reprompt(reprompt_input: bool) -> InputContext {
+        let config = testing::manifest(&format!(
+            "settings:\n  history:\n    reprompt_input: {reprompt_input}\n"
+        ));
+        let resources = testing::resources(PROMPTS);
+        let stc = StaticContext::new(&config, &resources, true);
+        let mut player = testing::player(&config);
+        let path = testing::path("start");
+        let prompt = Prompt::get(&resources.prompts, &path).unwrap();
+
+        let text_context = text_context!(&stc, player);
+        let model = prompt.model(&text_context).unwrap();
+        let input = NamedVariableEntry::new("name".to_owned(), "Ann".to_owned(), &player.variables);
+        player
+            .choose(&prompt.choices[0], &None, Some(input), &model, &stc, &text_context)
+            .unwrap();
+        player.back(reprompt_input).unwrap();
+
+        let text_context = text_context!(&stc, player);
+        let choices = prompt
+            .usable_choices(&path, &player.notes, &player.session, &text_context)
+            .unwrap();
+        next_input_context(prompt, &model, &choices, &text_context)
+            .unwrap()
+            .expect("input prompts always take input")
+    }
+
+    #[test]
+    fn kept_input_is_prefilled() {
+        match reprompt(false) {
+            InputContext::Variable(name, _, default) => {
+                assert_eq!(name, "name");
+                assert_eq!(default.as_deref(), Some("Ann"));
+            }
+            _ => panic!("expected a variable input context"),
+        }
+    }
+
+    #[test]
+    fn reprompted_input_is_not_prefilled() {
+        match reprompt(true) {
+            InputContext::Variable(_, _, default) => assert_eq!(default, None),
+            _ => panic!("expected a variable input context"),
+        }
+    }
+}
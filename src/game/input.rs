@@ -1,31 +1,47 @@
+use std::collections::VecDeque;
+
 use anyhow::{anyhow, Result};
 use clap::Parser;
 use rustyline::DefaultEditor;
 
-use crate::cmd::runtime::RuntimeCommand;
+use crate::{cmd::runtime::RuntimeCommand, core::text::output::OutputLog};
 
 #[derive(Debug)]
 pub struct InputController {
     rl: DefaultEditor,
     quit: bool,
+    /// Input lines to take in order instead of reading from the terminal, if any.
+    script: Option<VecDeque<String>>,
 }
 
 pub enum InputContext {
-    Choices(usize),
-    Variable(String, Option<String>),
+    /// The amount of choices, the input label, and the amount of pages they are shown across.
+    Choices(usize, Option<String>, usize),
+    /// The response lines to pick from with arrow keys and the input label.
+    /// Typed lines, such as those of a replay, are read as choice numbers.
+    Select(Vec<String>, Option<String>),
+    Keywords(Vec<Vec<String>>, Option<String>),
+    /// The variable name, its input label, and the default value to pre-fill, if any.
+    Variable(String, Option<String>, Option<String>),
+    /// The input label and the words accepted besides empty input, in lowercase.
+    Continue(Option<String>, Vec<String>),
 }
 
 impl InputContext {
     const PROMPT: &'static str = "> ";
+    const PAUSE_PROMPT: &'static str = "Press enter to continue ";
 
     pub fn prompt(&self) -> String {
         use InputContext::*;
         match self {
-            Choices(_) => Self::PROMPT.to_owned(),
-            Variable(_, prompt) => prompt
+            Choices(_, label, _) | Keywords(_, label) | Select(_, label) => {
+                label.clone().unwrap_or(Self::PROMPT.to_owned())
+            }
+            Variable(_, prompt, _) => prompt
                 .clone()
                 .map(|s| format!("{s}: "))
                 .unwrap_or(Self::PROMPT.to_owned()),
+            Continue(label, _) => label.clone().unwrap_or(Self::PAUSE_PROMPT.to_owned()),
         }
     }
 }
@@ -33,7 +49,12 @@ impl InputContext {
 pub enum InputResult {
     Quit(bool),
     Choice(usize),
-    Variable { name: String, value: String },
+    /// Whether to show the next page of choices rather than the previous one.
+    Page(bool),
+    Variable {
+        name: String,
+        value: String,
+    },
     Command(Result<RuntimeCommand>),
 }
 
@@ -42,6 +63,18 @@ impl InputController {
         Ok(Self {
             rl: DefaultEditor::new()?,
             quit: false,
+            script: None,
+        })
+    }
+
+    /// Creates a controller that takes input from a list of lines rather than the terminal.
+    ///
+    /// Each line is echoed after the input prompt as if it were typed.
+    /// Once all lines have been taken, the controller signals the game to shut down.
+    pub fn scripted(lines: Vec<String>) -> Result<Self> {
+        Ok(Self {
+            script: Some(lines.into()),
+            ..Self::new()?
         })
     }
 
@@ -56,48 +89,141 @@ impl InputController {
         RuntimeCommand::try_parse_from(args).map_err(|e| anyhow!(e))
     }
 
-    pub fn handle_line(line: String, context: &InputContext) -> Result<InputResult> {
-        if line.is_empty() {
-            return Err(anyhow!("Input cannot be empty"));
+    /// Parses a choice number out of `choices` choices.
+    fn parse_choice(line: &str, choices: usize) -> Result<InputResult> {
+        let choice = line
+            .parse::<usize>()
+            .map_err(|_| anyhow!("Input must be a number"))?;
+        if choice < 1 || choice > choices {
+            return Err(anyhow!("Input out of range"));
+        }
+        Ok(InputResult::Choice(choice))
+    }
+
+    /// The entry at the end of an arrow key selection list that lets the player type a command instead.
+    const COMMAND_ENTRY: &'static str = "Type a command...";
+
+    /// Lets the player pick one of some response lines with arrow keys.
+    ///
+    /// Picking the [command entry](Self::COMMAND_ENTRY) reads a line with or without the leading `.`,
+    /// which is handled as a runtime command. Cancelling the list is treated like signalling quit.
+    fn select(&mut self, lines: &[String], label: &Option<String>) -> Result<InputResult> {
+        let message = label
+            .as_deref()
+            .map(|label| label.trim().trim_end_matches(':').to_owned())
+            .unwrap_or("Choose a response".to_owned());
+        let question = requestty::Question::select("choice")
+            .message(message)
+            .choices(lines.iter().cloned())
+            .separator("─────")
+            .choice(Self::COMMAND_ENTRY)
+            .build();
+        let index = match requestty::prompt_one(question) {
+            Ok(answer) => answer.as_list_item().unwrap().index,
+            Err(_) => return Ok(self.signal_quit()),
+        };
+        self.quit = false;
+        // The separator takes up an index before the command entry
+        if index < lines.len() {
+            OutputLog::write_input(&(index + 1).to_string());
+            return Ok(InputResult::Choice(index + 1));
         }
+        let line = match self.rl.readline(InputContext::PROMPT) {
+            Ok(line) => line,
+            Err(_) => return Ok(self.signal_quit()),
+        };
+        let line = line.trim();
+        OutputLog::write_input(line);
+        self.rl.add_history_entry(line)?;
+        let command = match line.strip_prefix('.') {
+            Some(_) => line.to_owned(),
+            None => format!(".{line}"),
+        };
+        Ok(InputResult::Command(Self::parse_command(command)))
+    }
+
+    /// Signals a quit, which shuts the game down if it directly follows another.
+    fn signal_quit(&mut self) -> InputResult {
+        let result = InputResult::Quit(self.quit);
+        self.quit = true;
+        result
+    }
+
+    pub fn handle_line(line: String, context: &InputContext) -> Result<InputResult> {
         if line.starts_with(".") {
             return Ok(InputResult::Command(Self::parse_command(line)));
         }
+        // Empty input is only meaningful when continuing past a pause
+        if line.is_empty() && !matches!(context, InputContext::Continue(..)) {
+            return Err(anyhow!("Input cannot be empty"));
+        }
         match context {
-            &InputContext::Choices(choices) => {
-                let choice = line
-                    .parse::<usize>()
-                    .map_err(|_| anyhow!("Input must be a number"))?;
-                if choice < 1 || choice > choices {
-                    return Err(anyhow!("Input out of range"));
-                }
-                Ok(InputResult::Choice(choice))
+            &InputContext::Choices(_, _, pages) if pages > 1 && (line == "n" || line == "p") => {
+                Ok(InputResult::Page(line == "n"))
             }
-            InputContext::Variable(name, _) => Ok(InputResult::Variable {
+            &InputContext::Choices(choices, _, _) => Self::parse_choice(&line, choices),
+            InputContext::Select(lines, _) => Self::parse_choice(&line, lines.len()),
+            InputContext::Keywords(keywords, _) => {
+                let typed = line.to_lowercase();
+                let choice = keywords
+                    .iter()
+                    .position(|aliases| aliases.contains(&typed))
+                    .ok_or_else(|| {
+                        let hints: Vec<&str> = keywords
+                            .iter()
+                            .filter_map(|aliases| aliases.first())
+                            .map(String::as_str)
+                            .collect();
+                        anyhow!("Unrecognized input; try one of: {}", hints.join(", "))
+                    })?;
+                Ok(InputResult::Choice(choice + 1))
+            }
+            InputContext::Variable(name, _, _) => Ok(InputResult::Variable {
                 name: name.clone(),
                 value: line,
             }),
+            InputContext::Continue(_, words) => {
+                if !line.is_empty() && !words.contains(&line.to_lowercase()) {
+                    let hint = match words.first() {
+                        Some(word) => format!("Press enter or type '{word}' to continue"),
+                        None => "Press enter to continue".to_owned(),
+                    };
+                    return Err(anyhow!(hint));
+                }
+                Ok(InputResult::Choice(1))
+            }
         }
     }
 
     pub fn take(&mut self, context: &InputContext) -> Result<InputResult> {
         use InputResult::*;
-        match self.rl.readline(&context.prompt()) {
+        let line = match &mut self.script {
+            Some(script) => match script.pop_front() {
+                Some(line) => {
+                    OutputLog::print(&format!("{}{line}", context.prompt()));
+                    Ok(line)
+                }
+                None => return Ok(Quit(true)),
+            },
+            None => match context {
+                InputContext::Select(lines, label) => return self.select(lines, label),
+                InputContext::Variable(_, _, Some(default)) => self
+                    .rl
+                    .readline_with_initial(&context.prompt(), (default.as_str(), "")),
+                _ => self.rl.readline(&context.prompt()),
+            },
+        };
+        match line {
             Ok(line) => {
                 if self.quit {
                     self.quit = false;
                 }
+                OutputLog::write_input(line.trim());
                 let result = Self::handle_line(line.trim().to_owned(), context)?;
                 self.rl.add_history_entry(line)?;
                 Ok(result)
             }
-            Err(_) => {
-                let result = Quit(self.quit);
-                if !self.quit {
-                    self.quit = true;
-                }
-                return Ok(result);
-            }
+            Err(_) => Ok(self.signal_quit()),
         }
     }
 }
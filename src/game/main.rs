@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
 use anyhow::{anyhow, Result};
 
 use crate::{
@@ -6,21 +8,42 @@ use crate::{
         context::{StaticContext, TextContext},
         discord::RichPresence,
         manifest::Manifest,
+        path::PathData,
         player::Player,
         prompt::{Prompt, PromptModel},
-        text::display::Text,
+        text::{display::Text, output::OutputLog},
     },
-    loading::saves::SaveManager,
+    loading::{saves::SaveManager, watcher::ContentWatcher},
     text_context,
 };
 
 use super::{
-    gloop::{next_input_context, take_input, GameLoopResult},
+    gloop::{end_game, next_input_context, take_input, GameLoopResult},
     input::InputController,
 };
 
-pub fn first_play_init(stc: &StaticContext, player: &mut Player) -> Result<()> {
+/// Prepares a new game, resolving its enabled sound channels and printing the entrypoint background.
+///
+/// Channels start as configured in `settings.channels`, then follow the player's last toggles from the game's profile.
+pub fn first_play_init(
+    stc: &StaticContext,
+    player: &mut Player,
+    saves: &SaveManager,
+) -> Result<()> {
     let text_context = text_context!(stc, player);
+    let mut channels = stc.config.settings.enabled_audio_channels(&text_context)?;
+    let declared = |channel: &str| {
+        stc.config
+            .settings
+            .channels
+            .as_ref()
+            .map(|map| map.contains_key(channel))
+            .unwrap_or(false)
+    };
+    saves
+        .load_profile(stc.config)?
+        .apply_channels(&mut channels, declared);
+    player.channels = channels;
     if let Some(background) = &stc.config.entry.background {
         Text::print_lines_nl(background, player, &text_context)?;
     }
@@ -29,45 +52,129 @@ pub fn first_play_init(stc: &StaticContext, player: &mut Player) -> Result<()> {
     Ok(())
 }
 
+/// The reason the game loop was exited.
+pub enum LoopExit {
+    /// The game is shutting down, silently or not.
+    Shutdown(bool),
+    /// Watched content files changed, so resources should be reloaded before resuming.
+    Reload,
+}
+
+/// A prompt that was redirected from along with the player's notes and variables at the time.
+type RedirectState = (PathData, BTreeSet<String>, BTreeMap<String, String>);
+
+/// The maximum amount of redirects in a row without any input, in case state keeps changing forever.
+const MAX_REDIRECTS: usize = 1000;
+
+/// Records the player's current prompt as being redirected from, failing if it was already redirected from
+/// with the same notes and variables since the player last gave input.
+///
+/// Since redirects only depend on player state, revisiting a prompt with unchanged state means the redirects
+/// would cycle forever. Chains whose state changes on every pass are allowed up to [`MAX_REDIRECTS`].
+fn check_redirect(redirects: &mut HashSet<RedirectState>, player: &Player) -> Result<()> {
+    let path = &player.latest_entry()?.path;
+    let state = (
+        path.clone(),
+        player.notes.iter().cloned().collect(),
+        player.variables.clone().into_iter().collect(),
+    );
+    if !redirects.insert(state) {
+        return Err(anyhow!(
+            "Redirect loop detected; prompt '{path}' redirects back to itself without any input or state change"
+        ));
+    }
+    if redirects.len() > MAX_REDIRECTS {
+        return Err(anyhow!(
+            "Redirect limit exceeded; over {MAX_REDIRECTS} prompts were redirected from without any input"
+        ));
+    }
+    Ok(())
+}
+
 pub fn begin(
     stc: &StaticContext,
     player: &mut Player,
     saves: &SaveManager,
     drpc: &mut Option<RichPresence>,
     input: &mut InputController,
-) -> Result<bool> {
+    watcher: Option<&ContentWatcher>,
+) -> Result<LoopExit> {
+    // A player that already began is resuming, and may need reminding of where they left off
+    let mut recap = player.began && stc.config.settings.resume_recap;
     if !player.began {
-        first_play_init(stc, player)?;
+        first_play_init(stc, player, saves)?;
     }
 
     stc.config
         .set_rich_presence(drpc, &player.latest_entry()?.path.to_string())?;
 
-    let silent = 'outer: loop {
+    // The prompts redirected from since the player last gave input, used to detect redirect loops
+    let mut redirects: HashSet<RedirectState> = HashSet::new();
+
+    let exit = 'outer: loop {
+        // Content changes are only checked between prompts so that a prompt is never reloaded mid-input
+        if watcher.map(ContentWatcher::changed).unwrap_or(false) {
+            break 'outer LoopExit::Reload;
+        }
+        if player.interrupt(stc) {
+            stc.config
+                .set_rich_presence(drpc, &player.latest_entry()?.path.to_string())?;
+        }
         // Text context owns variables to avoid immutable and mutable borrow overlap
-        let text_context = text_context!(stc, player);
+        let mut text_context = text_context!(stc, player);
         let entry = player.latest_entry()?;
         let next_prompt = Prompt::get(&stc.resources.prompts, &entry.path)?;
+        // Guards apply on arrival, before anything about the prompt is shown
+        if let Some(path) = next_prompt.guard_redirect(&entry.path, &text_context)? {
+            check_redirect(&mut redirects, player)?;
+            player.redirect(&path, stc);
+            continue;
+        }
         let model = next_prompt.model(&text_context)?;
-        let choices = next_prompt.usable_choices(&player.notes, &text_context)?;
+        let choices = next_prompt.usable_choices(
+            &entry.path,
+            &player.notes,
+            &player.session,
+            &text_context,
+        )?;
+        text_context.untried = Some(player.untried_count(next_prompt, &entry.path, &choices));
 
         if choices.is_empty() {
-            return Err(anyhow!("No usable choices"));
+            // Without a configured ending, a dead end is treated as an authoring bug
+            let ending = stc
+                .config
+                .settings
+                .dead_end_ending
+                .as_ref()
+                .ok_or(anyhow!("No usable choices"))?;
+            if entry.display || recap {
+                if let Some(lines) = next_prompt.lines(&text_context)? {
+                    Text::print_lines_nl(lines, player, &text_context)?;
+                }
+            }
+            end_game(ending, None, player, saves, stc, &text_context)?;
+            break 'outer LoopExit::Shutdown(true);
         }
 
         let raw_choices: Vec<&Choice> = choices.iter().map(|(choice, _)| *choice).collect();
-        next_prompt.print(player, &model, entry.display, &raw_choices, &text_context)?;
+        next_prompt.print(player, &model, entry.display || recap, &raw_choices, &text_context)?;
+        recap = false;
+        let mut page = 0;
 
         match model {
             PromptModel::Redirect(choice) => {
-                player.choose_full(choice, &None, None, drpc, &model, stc, &text_context)?
+                check_redirect(&mut redirects, player)?;
+                player.choose_full(choice, &None, None, drpc, saves, &model, stc, &text_context)?
             }
             PromptModel::Ending(lines) => {
-                Text::print_lines(lines, player, &text_context)?;
-                break 'outer true;
+                let choice = &next_prompt.choices[0];
+                end_game(lines, Some(choice), player, saves, stc, &text_context)?;
+                break 'outer LoopExit::Shutdown(true);
             }
             _ => loop {
-                let context = next_input_context(&model, &choices, &text_context)?
+                redirects.clear();
+                let pages = stc.config.settings.text.choice_pages(choices.len());
+                let context = next_input_context(next_prompt, &model, &choices, &text_context)?
                     .ok_or(anyhow!("Could not resolve input context"))?;
 
                 match take_input(
@@ -83,19 +190,28 @@ pub fn begin(
                 )? {
                     GameLoopResult::Retry(flush) => {
                         if flush {
-                            println!()
+                            OutputLog::print("");
                         }
                     }
+                    GameLoopResult::Page(next) => {
+                        page = if next {
+                            (page + 1) % pages
+                        } else {
+                            (page + pages - 1) % pages
+                        };
+                        OutputLog::print("");
+                        Prompt::print_choices(player, &model, &raw_choices, page, &text_context)?;
+                    }
                     GameLoopResult::Continue => {
-                        println!();
+                        OutputLog::print("");
                         break;
                     }
-                    GameLoopResult::Shutdown(silent) => break 'outer silent,
+                    GameLoopResult::Shutdown(silent) => break 'outer LoopExit::Shutdown(silent),
                 }
             },
         }
     };
-    Ok(silent)
+    Ok(exit)
 }
 
 pub fn crash_context(config: &Manifest) -> String {
@@ -106,3 +222,165 @@ pub fn crash_context(config: &Manifest) -> String {
         .unwrap_or(String::new());
     format!("The game has crashed; it's not your fault!{contact}")
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{manifest::EndingSummary, testing};
+
+    use super::*;
+
+    /// Plays the test prompts from `start` with some starting `variables`, taking input from `lines`.
+    ///
+    /// Returns how the game loop exited along with the player afterward.
+    fn play_as(
+        prompts: &str,
+        variables: &[(&str, &str)],
+        lines: &[&str],
+    ) -> (Result<LoopExit>, Player) {
+        let config = testing::manifest("");
+        let resources = testing::resources(prompts);
+        let stc = StaticContext::new(&config, &resources, true);
+        let mut player = testing::player(&config);
+        for (name, value) in variables {
+            player.variables.insert(name.to_string(), value.to_string());
+        }
+        let saves = testing::saves(&config);
+        let lines = lines.iter().map(|line| line.to_string()).collect();
+        let exit = InputController::scripted(lines)
+            .and_then(|mut input| begin(&stc, &mut player, &saves, &mut None, &mut input, None));
+        (exit, player)
+    }
+
+    fn play(prompts: &str, variables: &[(&str, &str)], lines: &[&str]) -> Result<LoopExit> {
+        play_as(prompts, variables, lines).0
+    }
+
+    fn assert_redirect_error(result: Result<LoopExit>, expected: &str) {
+        match result {
+            Err(err) => assert!(err.to_string().contains(expected), "{err}"),
+            Ok(_) => panic!("expected a redirect error"),
+        }
+    }
+
+    #[test]
+    fn redirect_cycle_is_detected() {
+        let prompts = "
+start:
+  choices:
+    - jump: other
+other:
+  choices:
+    - jump: start
+";
+        assert_redirect_error(play(prompts, &[], &[]), "Redirect loop");
+    }
+
+    #[test]
+    fn guard_cycle_is_detected() {
+        let prompts = "
+start:
+  guard:
+    if: false
+    else: other
+  choices:
+    - jump: start
+other:
+  guard:
+    if: false
+    else: start
+  choices:
+    - jump: start
+";
+        assert_redirect_error(play(prompts, &[], &[]), "Redirect loop");
+    }
+
+    #[test]
+    fn revisit_with_changed_state_is_allowed() {
+        let prompts = "
+start:
+  choices:
+    - jump: gate
+gate:
+  guard:
+    if: '<open>'
+    else: key
+  choices:
+    - response: { text: Enter }
+      jump: gate
+key:
+  choices:
+    - variables: { open: 'true' }
+      jump: start
+";
+        let exit = play(prompts, &[("open", "false")], &[]).unwrap();
+        assert!(matches!(exit, LoopExit::Shutdown(_)));
+    }
+
+    #[test]
+    fn endlessly_changing_chain_is_limited() {
+        let prompts = "
+start:
+  choices:
+    - variables:
+        - name: count
+          value: 1
+          op: add
+      jump: start
+";
+        assert_redirect_error(play(prompts, &[], &[]), "Redirect limit");
+    }
+
+    #[test]
+    fn input_resets_redirects() {
+        let prompts = "
+start:
+  choices:
+    - jump: ask
+ask:
+  choices:
+    - response: { text: Again }
+      jump: start
+";
+        let exit = play(prompts, &[], &["1"]).unwrap();
+        assert!(matches!(exit, LoopExit::Shutdown(_)));
+    }
+
+    #[test]
+    fn history_summary_lists_chosen_responses() {
+        let prompts = "
+start:
+  choices:
+    - response: { text: Open the door }
+      jump: hall
+    - response: { text: Leave }
+      jump: start
+hall:
+  choices:
+    - jump: yard
+yard:
+  choices:
+    - response: { text: Rest }
+      jump: start
+    - response: { text: Wait }
+      jump: yard
+";
+        let (exit, player) = play_as(prompts, &[], &["1", "1"]);
+        assert!(matches!(exit.unwrap(), LoopExit::Shutdown(_)));
+        assert_eq!(EndingSummary::History.lines(&player), ["Open the door", "Rest"]);
+    }
+
+    #[test]
+    fn input_choices_are_counted() {
+        let prompts = "
+start:
+  choices:
+    - input:
+        variable: name
+      jump: start
+";
+        let (exit, player) = play_as(prompts, &[], &["Ann"]);
+        assert!(matches!(exit.unwrap(), LoopExit::Shutdown(_)));
+        assert_eq!(player.variables["name"], "Ann");
+        assert_eq!(player.total_choices, 1);
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     collections::BTreeMap,
     fs::File,
-    io::{self, Cursor, Read, Seek},
+    io::{self, Read},
 };
 
 use anyhow::{anyhow, Context, Result};
@@ -13,19 +13,105 @@ use piz::{
     read::{as_tree, DirectoryContents, FileTree},
     ZipArchive,
 };
-use playback_rs::{Hint, Song};
 use result::OptionResultExt;
-use serde::de::DeserializeOwned;
+use serde::{
+    de::{DeserializeOwned, Error as DeError, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
 use walkdir::WalkDir;
 
-use crate::core::audio::Sounds;
+use crate::core::audio::{SoundSource, SoundSources};
+
+use super::watcher::ContentWatcher;
 
 /// An ordered map of content container names to values within a single file.
 pub type ContentFile<T> = BTreeMap<String, T>;
+/// A [`ContentFile`] that fails to deserialize if any content container name is repeated,
+/// rather than silently keeping the last value.
+struct UniqueContentFile<T>(ContentFile<T>);
+
+struct UniqueContentFileVisitor<T>(std::marker::PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for UniqueContentFileVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = ContentFile<T>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut result = BTreeMap::new();
+        while let Some((key, value)) = map.next_entry::<String, T>()? {
+            if result.contains_key(&key) {
+                return Err(A::Error::custom(format!("Duplicate key '{key}'")));
+            }
+            result.insert(key, value);
+        }
+        Ok(result)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for UniqueContentFile<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let file =
+            deserializer.deserialize_map(UniqueContentFileVisitor(std::marker::PhantomData))?;
+        Ok(Self(file))
+    }
+}
+
 /// An ordered map of file names to content files.
 pub type Contents<T> = BTreeMap<String, ContentFile<T>>;
 /// An ordered map of file names to their raw content.
 pub type RawContents = BTreeMap<String, String>;
+/// An ordered map of file names to the locations of their content containers.
+pub type SourceMaps = BTreeMap<String, SourceMap>;
+
+/// The path of a content file and the line on which each of its top-level containers is declared.
+///
+/// Lines are only found for YAML files; containers in other formats are located by path alone.
+pub struct SourceMap {
+    pub path: Utf8PathBuf,
+    lines: BTreeMap<String, usize>,
+}
+
+impl SourceMap {
+    pub fn new(path: Utf8PathBuf, content: &str) -> Self {
+        let mut lines = BTreeMap::new();
+        if path.extension() != Some("json") {
+            for (index, line) in content.lines().enumerate() {
+                // Top-level keys are unindented and aren't comments or sequence items
+                if line.starts_with(|c: char| c.is_whitespace() || c == '#' || c == '-') {
+                    continue;
+                }
+                if let Some((key, _)) = line.split_once(':') {
+                    let key = key.trim().trim_matches(|c| c == '"' || c == '\'');
+                    lines.entry(key.to_owned()).or_insert(index + 1);
+                }
+            }
+        }
+        Self { path, lines }
+    }
+
+    /// Describes where a container is declared, such as `prompts/main.yml:12`.
+    pub fn locate(&self, key: &str) -> String {
+        match self.lines.get(key) {
+            Some(line) => format!("{}:{line}", self.path),
+            None => self.path.to_string(),
+        }
+    }
+}
 
 /// Handles the loading of content and data through the file system.
 pub enum Backend<'a> {
@@ -107,6 +193,24 @@ impl<'a> Loader<'a> {
         Ok(result)
     }
 
+    /// The content directories that are watched for changes by [`Loader::watch`].
+    pub const WATCHED_DIRS: [&'static str; 3] = ["prompts", "lang", "info"];
+
+    /// Creates a [`ContentWatcher`] over the game's [`Loader::WATCHED_DIRS`] that exist.
+    ///
+    /// Only the folder backend is supported, since archived content can't be edited in place.
+    pub fn watch(&self) -> Result<ContentWatcher> {
+        match self.backend {
+            Backend::Folder => ContentWatcher::new(
+                Self::WATCHED_DIRS
+                    .iter()
+                    .map(|dir| self.get_path(dir))
+                    .filter(|path| path.is_dir()),
+            ),
+            Backend::Zip(..) => Err(anyhow!("Watching content is unsupported for zipped games")),
+        }
+    }
+
     pub fn dir_or_current(dir: Option<Utf8PathBuf>) -> Utf8PathBuf {
         dir.unwrap_or(Utf8PathBuf::from("."))
     }
@@ -200,14 +304,30 @@ impl<'a> Loader<'a> {
             .with_context(|| format!("{} doesn't exist", path.as_ref()))
     }
 
+    /// Parses some [`String`] content from a JSON file into a deserializable type.
+    pub fn parse_json<T>(content: String) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let parsed = serde_json::from_str::<T>(&content)
+            .map_err(|err| SerdeError::new(content.clone(), err))?;
+        Ok(parsed)
+    }
+
     /// Reads a file given a path and deserializes it into the specified type.
+    ///
+    /// Files with a `.json` extension are parsed as JSON; all others are parsed as YAML.
     pub fn load<P, T>(&self, path: P, raw: bool) -> Result<T>
     where
         P: AsRef<Utf8Path>,
         T: DeserializeOwned,
     {
         let content = self.read(&path, raw)?;
-        Self::parse(content).with_context(|| format!("Failed to parse {}", path.as_ref()))
+        let parsed = match path.as_ref().extension() {
+            Some("json") => Self::parse_json(content),
+            _ => Self::parse(content),
+        };
+        parsed.with_context(|| format!("Failed to parse {}", path.as_ref()))
     }
 
     /// Iterates over content files, performs the specified operation on the file path,
@@ -250,42 +370,58 @@ impl<'a> Loader<'a> {
         self.map_content(path, |local| Ok(self.read(local, false)?))
     }
 
+    /// Iterates over content files and maps where their content containers are declared.
+    pub fn load_sources<P>(&self, path: P) -> Result<SourceMaps>
+    where
+        P: AsRef<Utf8Path>,
+    {
+        self.map_content(path, |local| {
+            let content = self.read(&local, false)?;
+            Ok(SourceMap::new(local, &content))
+        })
+    }
+
     /// Iterates over content files, deserializes their content, and combines them into a [`Contents`] map.
+    ///
+    /// Fails if any content file repeats a content container name.
     pub fn load_content<P, T>(&self, path: P) -> Result<Contents<T>>
     where
         P: AsRef<Utf8Path>,
         T: DeserializeOwned,
     {
-        self.map_content(path, |local| Ok(self.load(local, false)?))
+        self.map_content(path, |local| {
+            let UniqueContentFile(file) = self.load(local, false)?;
+            Ok(file)
+        })
     }
 
-    fn load_sound_file<P>(&self, path: P) -> Result<Song>
+    fn load_sound_source<P>(&self, path: P) -> Result<SoundSource>
     where
         P: AsRef<Utf8Path>,
     {
         use Backend::*;
         let result = match self.backend {
-            Folder => Song::from_file(path.as_ref(), None),
+            Folder => SoundSource::File(path.as_ref().to_path_buf()),
             Zip(archive, tree) => {
-                let mut hint = Hint::new();
-                if let Some(extension) = path.as_ref().extension() {
-                    hint.with_extension(extension);
-                }
+                // Archived files can't be seeked, so their encoded content is buffered instead
+                let extension = path.as_ref().extension().map(str::to_owned);
                 let mut reader = Self::create_reader(archive, tree, path)?;
-                let mut cursor = Cursor::new(Vec::new());
-                io::copy(&mut reader, &mut cursor)?;
-                cursor.seek(io::SeekFrom::Start(0))?;
-                Song::new(Box::new(cursor), &hint, None)
+                let mut content = Vec::new();
+                reader.read_to_end(&mut content)?;
+                SoundSource::Buffer {
+                    extension,
+                    content: content.into(),
+                }
             }
         };
-        result.map_err(|err| anyhow!(err))
+        Ok(result)
     }
 
-    /// Loads and parses sounds using [`load_sound_file`].
-    pub fn load_sounds<P>(&self, path: P) -> Result<Sounds>
+    /// Indexes sound files as [`SoundSource`]s using [`load_sound_source`].
+    pub fn load_sound_sources<P>(&self, path: P) -> Result<SoundSources>
     where
         P: AsRef<Utf8Path>,
     {
-        self.map_content(path, |local| Ok(self.load_sound_file(local)?))
+        self.map_content(path, |local| Ok(self.load_sound_source(local)?))
     }
 }
@@ -1,46 +1,311 @@
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    io::{Read, Write},
+    thread::JoinHandle,
+};
+
 use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use camino::{Utf8Path, Utf8PathBuf};
+use chrono::{DateTime, Local, Utc};
+use flate2::{
+    read::{DeflateDecoder, GzDecoder},
+    write::{DeflateEncoder, GzEncoder},
+    Compression,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
 
-use crate::core::{manifest::Manifest, player::Player};
+use crate::core::{
+    manifest::{AutoSaveSettingsContents, CompactSaveSettings, Manifest, SaveFormat},
+    path::PathData,
+    player::Player,
+    state::{carryover::Carryover, profile::Profile},
+};
 
 use super::loader::Loader;
 
+#[derive(Serialize, Deserialize, Debug)]
+/// Details about a save file shown when picking a save, stored in a `<name>.meta.yml` file beside it.
+pub struct SaveMetadata {
+    /// When the save file was first written.
+    pub created_at: DateTime<Utc>,
+    /// When the save file was last written.
+    pub updated_at: DateTime<Utc>,
+    /// The total amount of choices the player had made.
+    pub total_choices: usize,
+    /// The prompt the player was at.
+    pub last_prompt: PathData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    /// A name to show instead of the save file name, which players may set by editing the file.
+    pub custom_name: Option<String>,
+}
+
+impl SaveMetadata {
+    /// Creates metadata for a player that is being written, keeping the creation time and custom name of any `previous` metadata.
+    fn new(previous: Option<SaveMetadata>, player: &Player) -> Result<Self> {
+        let now = Utc::now();
+        let (created_at, custom_name) = match previous {
+            Some(previous) => (previous.created_at, previous.custom_name),
+            None => (now, None),
+        };
+        Ok(Self {
+            created_at,
+            updated_at: now,
+            total_choices: player.total_choices,
+            last_prompt: player.latest_entry()?.path.clone(),
+            custom_name,
+        })
+    }
+
+    /// Describes a save file for the save picker, given the label of its file name.
+    ///
+    /// The `custom_name`, if any, is shown in place of the label.
+    fn describe(&self, label: String) -> String {
+        let label = self.custom_name.clone().unwrap_or(label);
+        let updated = self.updated_at.with_timezone(&Local);
+        format!("{label} — {} — {}", self.last_prompt, updated.format("%Y-%m-%d %H:%M"))
+    }
+}
+
 pub struct SaveManager {
+    /// The root directory holding every game's data, as resolved by [`SaveManager::generic_dir`].
+    root: Utf8PathBuf,
     dir: Utf8PathBuf,
     pub save_file: Option<Utf8PathBuf>,
+    /// The key used to obfuscate and deobfuscate save content.
+    key: Vec<u8>,
+    /// Whether saves should be written in obfuscated form.
+    obfuscated: bool,
+    /// The format of new save files.
+    format: SaveFormat,
+    /// Where autosaves are written and how many backups of them are kept.
+    auto_save: AutoSaveSettingsContents,
+    /// How much volatile player data to trim from saves, if at all.
+    compact: Option<CompactSaveSettings>,
+    /// Whether writing saves and carryovers is skipped, such as during a replay.
+    read_only: bool,
+    /// The background write started by [`SaveManager::write_autosave`], if any.
+    pending: RefCell<Option<JoinHandle<()>>>,
 }
 
 impl SaveManager {
-    pub fn generic_dir() -> Result<Utf8PathBuf> {
+    /// The prefix that marks a save file as obfuscated.
+    const OBFUSCATED_MAGIC: &'static [u8] = b"NAGESAVE\x01";
+    /// The prefix that marks a shareable save code.
+    const CODE_PREFIX: &'static str = "nage1-";
+
+    /// The environment variable that overrides the root save directory.
+    pub const DIR_ENV: &'static str = "NAGE_SAVES_DIR";
+
+    /// Resolves the root directory holding every game's data.
+    ///
+    /// In order of precedence, this is the `custom` directory passed through `--saves-dir`,
+    /// the [`SaveManager::DIR_ENV`] environment variable, and finally the `games` directory
+    /// within the OS config directory.
+    pub fn generic_dir(custom: Option<&Utf8Path>) -> Result<Utf8PathBuf> {
+        if let Some(dir) = custom {
+            return Ok(dir.to_owned());
+        }
+        if let Some(dir) = std::env::var_os(Self::DIR_ENV) {
+            return Utf8PathBuf::from_path_buf(dir.into())
+                .map_err(|dir| anyhow!("Invalid save directory '{}'", dir.display()));
+        }
         Ok(Loader::config_dir()?.join("games"))
     }
 
-    pub fn game_dir(config: &Manifest) -> Result<Utf8PathBuf> {
-        let dir = Self::generic_dir()?
+    pub fn game_dir(config: &Manifest, custom: Option<&Utf8Path>) -> Result<Utf8PathBuf> {
+        let dir = Self::generic_dir(custom)?
             .join(config.metadata.game_id())
             .join("saves");
         Ok(dir)
     }
 
-    fn dir(config: &Manifest) -> Result<Utf8PathBuf> {
-        let dir = Self::game_dir(config)?;
+    fn dir(config: &Manifest, custom: Option<&Utf8Path>) -> Result<Utf8PathBuf> {
+        let dir = Self::game_dir(config, custom)?;
         if !dir.exists() {
             std::fs::create_dir_all(&dir)?;
         }
         Ok(dir)
     }
 
-    pub fn new(config: &Manifest, pick: bool, new: bool) -> Result<Self> {
-        let dir = Self::dir(config)?;
+    pub fn new(
+        config: &Manifest,
+        pick: bool,
+        new: bool,
+        custom: Option<&Utf8Path>,
+    ) -> Result<Self> {
+        let root = Self::generic_dir(custom)?;
+        let dir = Self::dir(config, custom)?;
         let saves = Self::saves(&dir)?;
         let save_file = if new || saves.is_empty() {
             None
         } else if pick {
-            Some(Self::choose_save(&saves)?)
+            let autosave = config.settings.auto_save.settings.name();
+            Some(Self::choose_save(&dir, &saves, autosave)?)
         } else {
             Self::last_save_file(&dir).ok()
         };
-        Ok(Self { dir, save_file })
+        Ok(Self::with_dirs(config, root, dir, save_file))
+    }
+
+    /// Creates a [read-only](SaveManager::read_only) manager for a new game, such as during a replay.
+    ///
+    /// Unlike [`SaveManager::new`], the save directory is not created if it doesn't exist.
+    pub fn headless(config: &Manifest, custom: Option<&Utf8Path>) -> Result<Self> {
+        let root = Self::generic_dir(custom)?;
+        let dir = Self::game_dir(config, custom)?;
+        Ok(Self::with_dirs(config, root, dir, None).read_only())
+    }
+
+    fn with_dirs(
+        config: &Manifest,
+        root: Utf8PathBuf,
+        dir: Utf8PathBuf,
+        save_file: Option<Utf8PathBuf>,
+    ) -> Self {
+        Self {
+            root,
+            dir,
+            save_file,
+            key: config.metadata.game_id().as_bytes().to_vec(),
+            obfuscated: config.settings.save_obfuscation,
+            format: config.settings.save_format,
+            auto_save: config.settings.auto_save.settings.clone(),
+            compact: config.settings.compact_saves.clone(),
+            read_only: false,
+            pending: RefCell::new(None),
+        }
+    }
+
+    /// Whether no save file was selected, so that [`SaveManager::load`] starts a new game.
+    pub fn is_new_game(&self) -> bool {
+        self.save_file.is_none()
+    }
+
+    /// Disables writing any player data.
+    pub fn read_only(self) -> Self {
+        Self {
+            read_only: true,
+            ..self
+        }
+    }
+
+    /// XORs some bytes in place with the repeated obfuscation key.
+    fn xor(&self, bytes: &mut [u8]) {
+        if self.key.is_empty() {
+            return;
+        }
+        for (byte, key) in bytes.iter_mut().zip(self.key.iter().cycle()) {
+            *byte ^= key;
+        }
+    }
+
+    /// Compresses save content and XORs it with the game ID, prefixing the result with [`SaveManager::OBFUSCATED_MAGIC`].
+    ///
+    /// This only deters casual editing of save files; it is **not** encryption, as the key is the game's own ID.
+    fn obfuscate(&self, content: &str) -> Result<Vec<u8>> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content.as_bytes())?;
+        let mut body = encoder.finish()?;
+        self.xor(&mut body);
+        Ok([Self::OBFUSCATED_MAGIC, &body].concat())
+    }
+
+    /// Decodes save content, reversing [`SaveManager::obfuscate`] if the content starts with the obfuscation prefix.
+    /// Otherwise, the content is treated as a plain YAML save.
+    fn deobfuscate(&self, content: Vec<u8>) -> Result<String> {
+        let mut body = match content.strip_prefix(Self::OBFUSCATED_MAGIC) {
+            Some(body) => body.to_vec(),
+            None => return Ok(String::from_utf8(content)?),
+        };
+        self.xor(&mut body);
+        let mut result = String::new();
+        DeflateDecoder::new(body.as_slice())
+            .read_to_string(&mut result)
+            .with_context(|| "Failed to decode obfuscated save content")?;
+        Ok(result)
+    }
+
+    /// Compresses save file content according to a [`SaveFormat`].
+    fn compress(format: SaveFormat, content: Vec<u8>) -> Result<Vec<u8>> {
+        let result = match format {
+            SaveFormat::Yaml => content,
+            SaveFormat::YamlGz => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&content)?;
+                encoder.finish()?
+            }
+            SaveFormat::YamlZstd => zstd::encode_all(content.as_slice(), 0)?,
+        };
+        Ok(result)
+    }
+
+    /// Reverses [`SaveManager::compress`] for some save file content.
+    fn decompress(format: SaveFormat, content: Vec<u8>) -> Result<Vec<u8>> {
+        let result = match format {
+            SaveFormat::Yaml => content,
+            SaveFormat::YamlGz => {
+                let mut result = Vec::new();
+                GzDecoder::new(content.as_slice()).read_to_end(&mut result)?;
+                result
+            }
+            SaveFormat::YamlZstd => zstd::decode_all(content.as_slice())?,
+        };
+        Ok(result)
+    }
+
+    /// Encodes a player into a shareable save code.
+    ///
+    /// A code is [`SaveManager::CODE_PREFIX`] followed by the URL-safe base64 of the compressed save content,
+    /// which is preceded by the game ID on its own line.
+    pub fn export_code(config: &Manifest, player: &Player) -> Result<String> {
+        let content = format!("{}\n{}", config.metadata.game_id(), serde_yaml::to_string(player)?);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(content.as_bytes())?;
+        let body = URL_SAFE_NO_PAD.encode(encoder.finish()?);
+        Ok(format!("{}{body}", Self::CODE_PREFIX))
+    }
+
+    /// Decodes a save code created by [`SaveManager::export_code`].
+    ///
+    /// Fails if the code was exported from a game with a different ID.
+    pub fn import_code(config: &Manifest, code: &str) -> Result<Player> {
+        let body = code
+            .trim()
+            .strip_prefix(Self::CODE_PREFIX)
+            .ok_or(anyhow!("Not a valid save code"))?;
+        let bytes = URL_SAFE_NO_PAD
+            .decode(body)
+            .with_context(|| "Failed to decode save code")?;
+        let mut content = String::new();
+        DeflateDecoder::new(bytes.as_slice())
+            .read_to_string(&mut content)
+            .with_context(|| "Failed to decompress save code")?;
+        let (game_id, save) = content
+            .split_once('\n')
+            .ok_or(anyhow!("Save code is missing a game ID"))?;
+        if game_id != config.metadata.game_id() {
+            return Err(anyhow!("Save code belongs to a different game ('{game_id}')"));
+        }
+        let mut player: Player =
+            Loader::parse(save.to_owned()).with_context(|| "Failed to parse save code")?;
+        Self::fill_difficulty(config, &mut player)
+            .with_context(|| "Invalid difficulty in save code")?;
+        Ok(player)
+    }
+
+    /// Validates a loaded player's difficulty, defaulting it if the player has none.
+    fn fill_difficulty(config: &Manifest, player: &mut Player) -> Result<()> {
+        // Saves from before the game declared difficulties start at the easiest level
+        if player.difficulty.is_none() {
+            player.difficulty = config.settings.default_difficulty();
+        }
+        config
+            .settings
+            .validate_difficulty(player.difficulty.as_ref())
     }
 
     fn save_name_storage<P>(path: P) -> Utf8PathBuf
@@ -62,9 +327,12 @@ impl SaveManager {
     where
         P: AsRef<Utf8Path>,
     {
-        let content = std::fs::read_to_string(self.dir.join(&file))?;
-        Loader::parse(content)
-            .with_context(|| anyhow!("Failed to parse save file '{}'", file.as_ref()))
+        let file = file.as_ref();
+        let format = SaveFormat::detect(file.as_str()).unwrap_or_default();
+        let content = Self::decompress(format, std::fs::read(self.dir.join(file))?)
+            .with_context(|| format!("Failed to decompress save file '{file}'"))?;
+        let content = self.deobfuscate(content)?;
+        Loader::parse(content).with_context(|| anyhow!("Failed to parse save file '{file}'"))
     }
 
     fn saves<P>(dir: P) -> Result<Vec<Utf8PathBuf>>
@@ -74,18 +342,119 @@ impl SaveManager {
         let result = std::fs::read_dir(dir.as_ref())?
             .filter_map(|entry| entry.ok())
             .filter_map(|entry| Utf8PathBuf::from_path_buf(entry.path()).ok())
-            .filter(|path| path.extension().map(|p| p == "yml").unwrap_or(false))
+            .filter(|path| SaveFormat::detect(path.as_str()).is_some())
+            .filter(|path| !Self::save_name(path).ends_with(Self::METADATA_SUFFIX))
             .collect();
         Ok(result)
     }
 
-    fn choose_save<P>(saves: &Vec<P>) -> Result<Utf8PathBuf>
+    /// The name of a save file without its directory or save file extension.
+    pub fn save_name(save: &Utf8Path) -> &str {
+        let file = save.file_name().unwrap_or(save.as_str());
+        match SaveFormat::detect(file) {
+            Some(format) => format.strip(file),
+            None => file,
+        }
+    }
+
+    /// Lists the save files in this game's save directory.
+    pub fn list(&self) -> Result<Vec<Utf8PathBuf>> {
+        Self::saves(&self.dir)
+    }
+
+    /// Finds a save file in this game's save directory by its [name](SaveManager::save_name).
+    pub fn find(&self, name: &str) -> Result<Utf8PathBuf> {
+        self.list()?
+            .into_iter()
+            .find(|save| Self::save_name(save) == name)
+            .ok_or(anyhow!("No save file named '{name}'"))
+    }
+
+    /// Whether a save file is the one selected for the current session.
+    pub fn is_active(&self, save_file: &Utf8Path) -> bool {
+        self.save_file
+            .as_ref()
+            .map(|active| self.dir.join(active) == self.dir.join(save_file))
+            .unwrap_or(false)
+    }
+
+    /// Deletes a save file.
+    ///
+    /// If it was the last used save, the record of it in `save.txt` is removed as well,
+    /// so that the next run starts a new game unless another save is picked.
+    pub fn delete(&self, save_file: &Utf8Path) -> Result<()> {
+        if self.read_only {
+            return Err(anyhow!("Save files can't be deleted right now"));
+        }
+        self.wait_pending();
+        let path = self.dir.join(save_file);
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to delete save file '{save_file}'"))?;
+        let _ = std::fs::remove_file(Self::metadata_file(&self.dir, save_file));
+        if let Ok(last) = Self::last_save_file(&self.dir) {
+            if self.dir.join(last) == path {
+                let _ = std::fs::remove_file(Self::save_name_storage(&self.dir));
+            }
+        }
+        Ok(())
+    }
+
+    /// The backup number of a save file name if it is a backup of the autosave named `autosave`.
+    fn backup_number(name: &str, autosave: &str) -> Option<usize> {
+        name.strip_prefix(autosave)?.strip_prefix('.')?.parse().ok()
+    }
+
+    /// Labels a save file name for the save picker, marking autosaves and their backups.
+    fn save_label(name: &str, autosave: &str) -> String {
+        if name == autosave {
+            return format!("{name} (autosave)");
+        }
+        match Self::backup_number(name, autosave) {
+            Some(number) => format!("{name} (autosave backup #{number})"),
+            None => name.to_owned(),
+        }
+    }
+
+    /// The suffix of a save file's name that marks its [`SaveMetadata`] file.
+    const METADATA_SUFFIX: &'static str = ".meta";
+
+    /// The [`SaveMetadata`] file of a save file, such as `save.meta.yml` for `save.yml.gz`.
+    fn metadata_file(dir: &Utf8Path, save: &Utf8Path) -> Utf8PathBuf {
+        dir.join(format!(
+            "{}{}.{}",
+            Self::save_name(save),
+            Self::METADATA_SUFFIX,
+            SaveFormat::Yaml.extension()
+        ))
+    }
+
+    /// Reads the [`SaveMetadata`] of a save file, if it has any that can be parsed.
+    pub fn read_metadata(dir: &Utf8Path, save: &Utf8Path) -> Option<SaveMetadata> {
+        let content = std::fs::read_to_string(Self::metadata_file(dir, save)).ok()?;
+        Loader::parse(content).ok()
+    }
+
+    /// Serializes updated [`SaveMetadata`] for a save file that is about to be written.
+    fn metadata_content(&self, save: &Utf8Path, player: &Player) -> Option<String> {
+        let previous = Self::read_metadata(&self.dir, save);
+        let metadata = SaveMetadata::new(previous, player).ok()?;
+        serde_yaml::to_string(&metadata).ok()
+    }
+
+    fn choose_save<P>(dir: &Utf8Path, saves: &Vec<P>, autosave: &str) -> Result<Utf8PathBuf>
     where
         P: AsRef<Utf8Path>,
     {
         let save_names: Vec<String> = saves
             .iter()
-            .map(|save| save.as_ref().file_stem().map(ToString::to_string).unwrap())
+            .map(|save| {
+                let save = save.as_ref();
+                let label = Self::save_label(Self::save_name(save), autosave);
+                match Self::read_metadata(dir, save) {
+                    Some(metadata) => metadata.describe(label),
+                    None => label,
+                }
+            })
             .collect();
         let prompt = requestty::Question::select("Choose a save file")
             .choices(save_names)
@@ -99,41 +468,321 @@ impl SaveManager {
 
     pub fn load(&self, config: &Manifest) -> Result<Player> {
         match &self.save_file {
-            Some(save) => self.load_player(save),
-            None => Ok(Player::new(config)),
+            Some(save) => {
+                let mut player = self.load_player(save)?;
+                Self::fill_difficulty(config, &mut player)
+                    .with_context(|| format!("Invalid difficulty in save '{save}'"))?;
+                Ok(player)
+            }
+            None => {
+                let mut player = Player::new(config, &config.entry.select_path()?);
+                player.difficulty = config.settings.select_difficulty()?;
+                if let Some(speed) = self.load_profile(config)?.speed {
+                    player.speed = speed;
+                }
+                if let Some(carryover) = self.prompt_carryover(config)? {
+                    player.carry_over(carryover);
+                }
+                Ok(player)
+            }
+        }
+    }
+
+    /// The file that stores the [`Carryover`] of the last finished playthrough.
+    fn carryover_file(&self, config: &Manifest) -> Utf8PathBuf {
+        self.root
+            .join(config.metadata.game_id())
+            .join("carryover.yml")
+    }
+
+    /// If the game carries over state and a finished playthrough recorded some,
+    /// asks the player whether to start a New Game+ with it.
+    fn prompt_carryover(&self, config: &Manifest) -> Result<Option<Carryover>> {
+        let file = self.carryover_file(config);
+        if config.carryover.is_empty() || !file.exists() {
+            return Ok(None);
+        }
+        let carryover: Carryover = Loader::parse(std::fs::read_to_string(&file)?)
+            .with_context(|| "Failed to parse carryover file")?;
+        let prompt = requestty::Question::confirm("Start a New Game+ with your previous progress?")
+            .default(true)
+            .build();
+        let accepted = requestty::prompt_one(prompt)?.as_bool().unwrap();
+        println!();
+        Ok(accepted.then_some(carryover))
+    }
+
+    /// Records the configured [`Carryover`] of a player that finished the game, replacing any previous one.
+    pub fn write_carryover(&self, config: &Manifest, player: &Player) -> Result<()> {
+        if self.read_only || player.preview || config.carryover.is_empty() {
+            return Ok(());
+        }
+        let carryover = Carryover::new(&config.carryover, player);
+        std::fs::write(self.carryover_file(config), serde_yaml::to_string(&carryover)?)?;
+        Ok(())
+    }
+
+    /// The file that stores the game's [`Profile`], shared by all of its save files.
+    fn profile_file(&self, config: &Manifest) -> Utf8PathBuf {
+        self.root
+            .join(config.metadata.game_id())
+            .join("profile.yml")
+    }
+
+    /// Reads the game's [`Profile`], or an empty one if none has been recorded.
+    pub fn load_profile(&self, config: &Manifest) -> Result<Profile> {
+        let file = self.profile_file(config);
+        if !file.exists() {
+            return Ok(Profile::default());
         }
+        Loader::parse(std::fs::read_to_string(&file)?)
+            .with_context(|| "Failed to parse profile file")
     }
 
-    fn prompt_new_save_file() -> Result<String> {
+    /// Reads the game's [`Profile`], modifies it, and writes it back if the modifier returns `true`.
+    fn update_profile<F>(&self, config: &Manifest, modifier: F) -> Result<()>
+    where
+        F: FnOnce(&mut Profile) -> bool,
+    {
+        if self.read_only {
+            return Ok(());
+        }
+        let mut profile = self.load_profile(config)?;
+        if modifier(&mut profile) {
+            let file = self.profile_file(config);
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(file, serde_yaml::to_string(&profile)?)?;
+        }
+        Ok(())
+    }
+
+    /// Records an ending ID in the game's [`Profile`].
+    pub fn record_ending(&self, config: &Manifest, id: &str) -> Result<()> {
+        self.update_profile(config, |profile| profile.endings.insert(id.to_owned()))
+    }
+
+    /// Records the player's text speed multiplier in the game's [`Profile`] so that new games start from it.
+    pub fn record_speed(&self, config: &Manifest, speed: f32) -> Result<()> {
+        self.update_profile(config, |profile| {
+            let changed = profile.speed != Some(speed);
+            profile.speed = Some(speed);
+            changed
+        })
+    }
+
+    /// Records whether each of some channels is enabled in the game's [`Profile`] so that new games inherit them.
+    pub fn record_channels<'a, I>(
+        &self,
+        config: &Manifest,
+        channels: I,
+        enabled: &HashSet<String>,
+    ) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        self.update_profile(config, |profile| {
+            for channel in channels {
+                profile
+                    .channels
+                    .insert(channel.clone(), enabled.contains(channel));
+            }
+            true
+        })
+    }
+
+    fn prompt_new_save_file(&self) -> Result<String> {
         println!();
         let prompt = requestty::Question::input("Save file name")
             .validate(|file, _| {
-                if !sanitize_filename::is_sanitized(file) {
+                if !sanitize_filename::is_sanitized(file) || file.ends_with(Self::METADATA_SUFFIX) {
                     return Err("Invalid file name".to_owned());
                 }
                 Ok(())
             })
             .build();
         let answer = requestty::prompt_one(prompt)?;
-        Ok(format!("{}.yml", answer.as_string().unwrap()))
+        Ok(format!("{}.{}", answer.as_string().unwrap(), self.format.extension()))
+    }
+
+    /// Serializes player data, omitting the oldest history and log entries if saves are compact.
+    fn serialize_player(&self, player: &Player) -> Result<String> {
+        let compact = match &self.compact {
+            Some(compact) => compact,
+            None => return Ok(serde_yaml::to_string(player)?),
+        };
+        let (history_start, log_start) = player.compact_trim(compact);
+        let mut value = serde_yaml::to_value(player)?;
+        for (key, start) in [("history", history_start), ("log", log_start)] {
+            if let Some(Value::Sequence(entries)) = value.get_mut(key) {
+                entries.drain(..start);
+            }
+        }
+        Ok(serde_yaml::to_string(&value)?)
+    }
+
+    /// Serializes player data into the bytes written to a save file, obfuscating them if enabled.
+    ///
+    /// The content is compressed according to the [`SaveFormat`] of the save file's extension.
+    fn encode_player(&self, player: &Player, save: &Utf8Path) -> Option<Vec<u8>> {
+        let content = self.serialize_player(player).ok()?;
+        let content = if self.obfuscated {
+            self.obfuscate(&content).ok()?
+        } else {
+            content.into_bytes()
+        };
+        let format = SaveFormat::detect(save.as_str()).unwrap_or_default();
+        Self::compress(format, content).ok()
     }
 
     fn write_player<P>(&self, save_file: P, player: &Player)
     where
         P: AsRef<Utf8Path>,
     {
-        if let Ok(content) = serde_yaml::to_string(player) {
+        self.wait_pending();
+        if let Some(content) = self.encode_player(player, save_file.as_ref()) {
             let _ = std::fs::write(self.dir.join(&save_file), content);
         }
     }
 
+    /// Blocks until the background write started by [`SaveManager::write_autosave`], if any, finishes.
+    fn wait_pending(&self) {
+        if let Some(handle) = self.pending.borrow_mut().take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// The autosave file, or its numbered backup if `backup` is present, such as `autosave.2.yml`.
+    fn autosave_file(&self, backup: Option<usize>) -> Utf8PathBuf {
+        let name = self.auto_save.name();
+        let extension = self.auto_save.format(self.format).extension();
+        let file = match backup {
+            Some(number) => format!("{name}.{number}.{extension}"),
+            None => format!("{name}.{extension}"),
+        };
+        Utf8PathBuf::from(file)
+    }
+
+    /// Backups of the autosave numbered beyond the configured amount, such as after `backups` was lowered.
+    fn stale_backups(&self) -> Vec<Utf8PathBuf> {
+        let name = self.auto_save.name();
+        Self::saves(&self.dir)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|save| {
+                Self::backup_number(Self::save_name(save), name)
+                    .map(|number| number > self.auto_save.backups)
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    /// Moves each backup one number back, replacing the oldest, and moves the current autosave to the first backup.
+    fn rotate(current: &Utf8Path, backups: &[Utf8PathBuf]) {
+        if backups.is_empty() || !current.exists() {
+            return;
+        }
+        for index in (1..backups.len()).rev() {
+            let _ = std::fs::rename(&backups[index - 1], &backups[index]);
+        }
+        let _ = std::fs::rename(current, &backups[0]);
+    }
+
+    /// Writes player data to the autosave file on a background thread, such as after every choice with `settings.auto_save`.
+    ///
+    /// The previous autosave is rotated into the numbered backups first, and backups beyond `settings.auto_save.backups` are pruned.
+    /// The autosave is only recorded as the last used save if no save file was selected, so the player's own save is still resumed.
+    ///
+    /// Any failure is ignored so that play is never interrupted by a disk error.
+    /// Writes are made in order; a new write waits for the previous one to finish first.
+    pub fn write_autosave(&self, player: &Player) {
+        if self.read_only || player.preview {
+            return;
+        }
+        let save = self.autosave_file(None);
+        let content = match self.encode_player(player, &save) {
+            Some(content) => content,
+            None => return,
+        };
+        let path = self.dir.join(&save);
+        let backups: Vec<Utf8PathBuf> = (1..=self.auto_save.backups)
+            .map(|number| self.dir.join(self.autosave_file(Some(number))))
+            .collect();
+        let stale = self.stale_backups();
+        let storage = self
+            .save_file
+            .is_none()
+            .then(|| Self::save_name_storage(&self.dir));
+        self.wait_pending();
+        let metadata = self
+            .metadata_content(&save, player)
+            .map(|content| (Self::metadata_file(&self.dir, &save), content));
+        let handle = std::thread::spawn(move || {
+            for backup in stale {
+                let _ = std::fs::remove_file(backup);
+            }
+            Self::rotate(&path, &backups);
+            if std::fs::write(path, content).is_ok() {
+                if let Some((file, content)) = metadata {
+                    let _ = std::fs::write(file, content);
+                }
+                if let Some(storage) = storage {
+                    let _ = std::fs::write(storage, save.to_string());
+                }
+            }
+        });
+        *self.pending.borrow_mut() = Some(handle);
+    }
+
     pub fn write(&self, player: &Player) -> Result<()> {
+        if self.read_only || player.preview {
+            return Ok(());
+        }
         let save = match &self.save_file {
             Some(value) => value.clone(),
-            None => Utf8PathBuf::from(Self::prompt_new_save_file()?),
+            None => Utf8PathBuf::from(self.prompt_new_save_file()?),
         };
-        self.write_player(&save, player);
-        let _ = std::fs::write(Self::save_name_storage(&self.dir), save.to_string());
+        self.write_to(&save, player);
         Ok(())
     }
+
+    /// The name of the save file a checkpoint is written to if no save file was selected.
+    const CHECKPOINT_NAME: &'static str = "checkpoint";
+
+    /// The save file a checkpoint is written to if no save file was selected, in the format of new save files.
+    fn checkpoint_file(&self) -> Utf8PathBuf {
+        Utf8PathBuf::from(format!("{}.{}", Self::CHECKPOINT_NAME, self.format.extension()))
+    }
+
+    /// Writes player data as a checkpoint in the middle of play.
+    ///
+    /// Unlike [`SaveManager::write`], the player is never prompted for a save file name;
+    /// if no save file was selected, [`SaveManager::checkpoint_file`] is used instead.
+    pub fn write_checkpoint(&self, player: &Player) -> Result<()> {
+        if self.read_only || player.preview {
+            return Ok(());
+        }
+        let save = self
+            .save_file
+            .clone()
+            .unwrap_or_else(|| self.checkpoint_file());
+        self.write_to(&save, player);
+        Ok(())
+    }
+
+    /// Writes player data and its [`SaveMetadata`] to a save file and records it as the last used save.
+    fn write_to(&self, save: &Utf8Path, player: &Player) {
+        self.write_player(save, player);
+        if let Some(content) = self.metadata_content(save, player) {
+            let _ = std::fs::write(Self::metadata_file(&self.dir, save), content);
+        }
+        let _ = std::fs::write(Self::save_name_storage(&self.dir), save.to_string());
+    }
+}
+
+impl Drop for SaveManager {
+    fn drop(&mut self) {
+        self.wait_pending();
+    }
 }
@@ -0,0 +1,44 @@
+use std::sync::mpsc::{self, Receiver};
+
+use anyhow::Result;
+use camino::Utf8PathBuf;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Watches content directories and signals when any of their files change.
+pub struct ContentWatcher {
+    /// The underlying watcher, which stops sending events once dropped.
+    _watcher: RecommendedWatcher,
+    receiver: Receiver<()>,
+}
+
+impl ContentWatcher {
+    /// Recursively watches each of the specified directories.
+    pub fn new<I>(dirs: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = Utf8PathBuf>,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<Event>| {
+            if let Ok(event) = result {
+                // Reading files, such as when loading content, isn't a change
+                if !event.kind.is_access() {
+                    let _ = sender.send(());
+                }
+            }
+        })?;
+        for dir in dirs {
+            watcher.watch(dir.as_std_path(), RecursiveMode::Recursive)?;
+        }
+        Ok(Self {
+            _watcher: watcher,
+            receiver,
+        })
+    }
+
+    /// Whether any watched files have changed since the last call. Does not block.
+    ///
+    /// Drains all pending events so that a burst of changes is only reported once.
+    pub fn changed(&self) -> bool {
+        self.receiver.try_iter().count() > 0
+    }
+}
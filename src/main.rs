@@ -1,15 +1,25 @@
 #![feature(result_flattening)]
 #![feature(iterator_try_collect)]
 
-use crate::core::{context::StaticContext, manifest::Manifest, resources::Resources};
+use crate::{
+    core::{
+        context::{StaticContext, TextContext},
+        manifest::Manifest,
+        player::Player,
+        resources::Resources,
+        text::{display::TextSpeed, output::OutputLog},
+        warnings::Warnings,
+    },
+    text_context,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use camino::Utf8PathBuf;
 use clap::Parser;
 use cmd::cli::CliCommand;
 use game::{
     input::InputController,
-    main::{begin, crash_context},
+    main::{begin, crash_context, LoopExit},
 };
 use loading::{loader::Loader, saves::SaveManager};
 
@@ -20,7 +30,98 @@ mod loading;
 
 pub const NAGE_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-fn run(path: Utf8PathBuf, pick: bool, new: bool) -> Result<()> {
+/// Loads and validates resources, such as when reloading changed content.
+///
+/// Unless `quiet` is set, prints a summary of any warnings found.
+/// If `strict` is set, any warning fails loading after the summary is printed.
+fn load_resources(
+    loader: &Loader,
+    config: &Manifest,
+    seed: u64,
+    accessible: bool,
+    quiet: bool,
+    strict: bool,
+) -> Result<Resources> {
+    let resources = Resources::load(loader, config, seed)?;
+    let mut warnings = Warnings::new();
+    resources.validate(&StaticContext::new(config, &resources, accessible), &mut warnings)?;
+    if strict && !warnings.is_empty() {
+        warnings.print_summary();
+        return Err(anyhow!("Content warnings are treated as errors with `--strict`"));
+    }
+    if !quiet {
+        warnings.print_summary();
+    }
+    Ok(resources)
+}
+
+#[derive(Default)]
+/// Options for running a game, usually passed from the command line.
+struct RunOptions {
+    /// Whether to pick from a list of saves instead of loading the last used one.
+    pick: bool,
+    /// Whether to start a new save file.
+    new: bool,
+    seed: Option<u64>,
+    /// Variables to set if a new game is started.
+    vars: Vec<(String, String)>,
+    /// A directory to store game data in instead of the default location.
+    saves_dir: Option<Utf8PathBuf>,
+    /// Whether to hide the summary of content warnings.
+    quiet: bool,
+    /// Whether content warnings fail loading instead.
+    strict: bool,
+    /// Input lines to play the game headlessly from, if any.
+    replay: Option<Vec<String>>,
+    /// A file to copy player-facing output to, if any.
+    log_output: Option<Utf8PathBuf>,
+    /// Whether the output log also records player input.
+    log_input: bool,
+}
+
+/// Prints a summary of a player's state after a replay.
+fn print_final_state(player: &Player) -> Result<()> {
+    let mut variables: Vec<String> = player
+        .variables
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect();
+    variables.sort();
+    let mut notes: Vec<&String> = player.notes.iter().collect();
+    notes.sort();
+    OutputLog::print(&format!("\nFinal prompt: {}", player.latest_entry()?.path));
+    OutputLog::print(&format!("Notes: {}", itertools::join(notes, ", ")));
+    OutputLog::print(&format!("Variables: {}", variables.join(", ")));
+    Ok(())
+}
+
+/// Runs a game at a path.
+///
+/// Any `vars` are set on the player if a new game is started.
+///
+/// If `saves_dir` is present, game data is stored there instead of the default location.
+///
+/// If `replay` lines are present, the game is played headlessly from them: a new player is created
+/// without prompting, text is printed instantly, nothing is saved, and warnings are hidden.
+/// Without a seed from the command line or the config, [`Resources::REPLAY_SEED`] is used.
+fn run(path: Utf8PathBuf, options: RunOptions) -> Result<()> {
+    let RunOptions {
+        pick,
+        new,
+        seed,
+        vars,
+        saves_dir,
+        quiet,
+        strict,
+        replay,
+        log_output,
+        log_input,
+    } = options;
+    if let Some(file) = &log_output {
+        OutputLog::open(file, log_input)?;
+    }
+    let headless = replay.is_some();
+    let quiet = quiet || headless;
     // Create content loader
     let mapping = Loader::mapping(&path)?;
     let archive = Loader::archive(&mapping)?;
@@ -28,23 +129,88 @@ fn run(path: Utf8PathBuf, pick: bool, new: bool) -> Result<()> {
     let loader = Loader::new(path, &archive, &tree)?;
     // Load content and data
     let config = Manifest::load(&loader)?;
-    let resources = Resources::load(&loader, &config)?;
-    // Load player
-    let saves = SaveManager::new(&config, pick, new)?;
-    let mut player = saves.load(&config)?;
-    // Validate loaded resources
-    let stc = StaticContext::new(&config, &resources);
-    resources.validate(&stc)?;
+    let seed = match seed.or(config.settings.seed) {
+        None if headless => Resources::REPLAY_SEED,
+        _ => Resources::seed(&config, seed),
+    };
+    if config.settings.debug {
+        OutputLog::print(&format!("Seed: {seed}"));
+    }
+    // Load and validate resources
+    let accessible = config.settings.text.accessible();
+    let mut resources = load_resources(&loader, &config, seed, accessible, quiet, strict)?;
+    if !headless {
+        config.settings.await_terminal_size()?;
+    }
+    // Load player, or create a fresh one that is never saved for a replay
+    let (saves, mut player) = if headless {
+        let mut player = Player::new(&config, config.entry.default_path()?);
+        player.speed = TextSpeed::INSTANT;
+        (SaveManager::headless(&config, saves_dir.as_deref())?, player)
+    } else {
+        let saves = SaveManager::new(&config, pick, new, saves_dir.as_deref())?;
+        let mut player = saves.load(&config)?;
+        player.endings_reached = saves.load_profile(&config)?.endings;
+        (saves, player)
+    };
+    // Preset variables only apply to new games since a save already holds its own state
+    if !vars.is_empty() {
+        if headless || saves.is_new_game() {
+            player.variables.extend(vars);
+        } else {
+            println!("Ignoring preset variables since an existing save was loaded\n");
+        }
+    }
+    // Watch content files for changes in developer mode
+    let watcher = if config.settings.debug && !headless {
+        loader
+            .watch()
+            .map_err(|err| println!("Not watching content files: {err}"))
+            .ok()
+    } else {
+        None
+    };
     // Load rich presence
-    let mut drpc = config.connect_rich_presence();
+    let mut drpc = if headless {
+        None
+    } else {
+        config.connect_rich_presence()
+    };
     // Create input controller
-    let mut input = InputController::new()?;
-    // Begin game loop
-    let silent = begin(&stc, &mut player, &saves, &mut drpc, &mut input)
-        .with_context(|| crash_context(&config))?;
+    let mut input = match replay {
+        Some(lines) => InputController::scripted(lines)?,
+        None => InputController::new()?,
+    };
+    // Show a startup tip once, since the game loop restarts after every reload
+    {
+        let stc = StaticContext::new(&config, &resources, accessible);
+        config.entry.print_tip(&text_context!(&stc, player))?;
+    }
+    // Begin game loop, restarting it whenever content is reloaded
+    let silent = loop {
+        let stc = StaticContext::new(&config, &resources, accessible);
+        let exit = begin(&stc, &mut player, &saves, &mut drpc, &mut input, watcher.as_ref())
+            .with_context(|| crash_context(&config))?;
+        match exit {
+            LoopExit::Shutdown(silent) => break silent,
+            LoopExit::Reload => {
+                match load_resources(&loader, &config, seed, accessible, quiet, strict) {
+                    Ok(reloaded) => {
+                        println!("Reloaded content files\n");
+                        resources = reloaded;
+                    }
+                    // Keep playing with the previous resources so that a typo doesn't crash the game
+                    Err(err) => println!("Failed to reload content files: {err:?}\n"),
+                }
+            }
+        }
+    };
     // Shut down game with silence based on game loop result
     if !silent {
-        println!("Exiting...");
+        OutputLog::print("Exiting...");
+    }
+    if headless {
+        return print_final_state(&player);
     }
     // Save player data
     if config.settings.save {
@@ -55,11 +221,64 @@ fn run(path: Utf8PathBuf, pick: bool, new: bool) -> Result<()> {
 }
 
 fn main() -> Result<()> {
-    // Parse CLI command - if 'run', use logic above
+    // Parse CLI command - if 'run', 'replay', or 'launch', use logic above
     // otherwise, uses its own method
     let command = CliCommand::parse();
-    if let CliCommand::Run { path, pick, new } = command {
-        return run(Loader::dir_or_current(path), pick, new);
+    match command {
+        CliCommand::Run {
+            path,
+            pick,
+            new,
+            seed,
+            vars,
+            saves_dir,
+            quiet,
+            strict,
+            log_output,
+            log_input,
+        } => run(
+            Loader::dir_or_current(path),
+            RunOptions {
+                pick,
+                new,
+                seed,
+                vars,
+                saves_dir,
+                quiet,
+                strict,
+                log_output,
+                log_input,
+                ..Default::default()
+            },
+        ),
+        CliCommand::Replay {
+            script,
+            path,
+            seed,
+            expected,
+        } => {
+            let lines = CliCommand::replay_script(&script)?;
+            if expected.is_some() {
+                OutputLog::capture();
+            }
+            run(
+                Loader::dir_or_current(path),
+                RunOptions {
+                    seed,
+                    replay: Some(lines),
+                    ..Default::default()
+                },
+            )?;
+            match expected {
+                Some(expected) => CliCommand::compare_replay(&expected, &OutputLog::captured()),
+                None => Ok(()),
+            }
+        }
+        CliCommand::Launch { dir } => {
+            let path =
+                CliCommand::launch(dir).with_context(|| "Failed to find a game to launch")?;
+            run(path, RunOptions::default())
+        }
+        _ => command.run(),
     }
-    command.run()
 }